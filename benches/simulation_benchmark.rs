@@ -1,6 +1,35 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use std::time::Duration;
-use rts_assignment::run_simulation; // Import from your library
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use rts_assignment::{run_simulation, run_simulation_async, Sensor, SensorType, SimulationConfig, StopCondition, SystemLog, Verbosity}; // Import from your library
+use rts_assignment::share::{Deadlines, SensorData, FILTER_WINDOW};
+
+// Counts every allocation and reallocation, so the benches can report heap traffic
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
 
 fn benchmark_system_integration(c: &mut Criterion) {
     // Define a group to configure sample size if needed
@@ -20,5 +49,66 @@ fn benchmark_system_integration(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, benchmark_system_integration);
+// Same 10ms run on both runtime flavors; compare the jitter in the printed reports
+fn benchmark_async_runtime(c: &mut Criterion) {
+    let mut group = c.benchmark_group("async_runtime");
+    group.sample_size(20);
+
+    for (name, worker_threads) in [("current_thread", Some(1)), ("multi_thread", None)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                run_simulation_async(SimulationConfig {
+                    stop_after: StopCondition::Duration(Duration::from_millis(10)),
+                    verbosity: Verbosity::Silent,
+                    worker_threads,
+                    ..SimulationConfig::default()
+                });
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// A fresh sensor's first 100 samples through `Sensor::process_data`: a window grown
+// on demand reallocates as it fills, the preallocated default never does
+fn benchmark_filter_window(c: &mut Criterion) {
+    let mut group = c.benchmark_group("filter_window");
+
+    let log = Arc::new(Mutex::new(SystemLog::in_memory()));
+    // Lenient deadline, so a late sample is never logged and counted as an allocation
+    let sensor = |capacity| Sensor::new(SensorType::Force, log.clone())
+        .with_deadlines(Deadlines { processing: Duration::from_secs(1), ..Deadlines::default() })
+        .with_window_capacity(capacity);
+    let reading = |id| SensorData {
+        id,
+        sensor_type: SensorType::Force,
+        value: 20.0 + (id % 10) as f64, // Inside the Force range, so every sample enters the window
+        anomaly: false,
+        timestamp: Instant::now(),
+        processed_timestamp: None,
+        capture_time: None,
+        stamps: Default::default(),
+    };
+    let filter = |sensor: &mut Sensor| {
+        let mut last = None;
+        for id in 0..100 {
+            last = sensor.process_data(reading(id));
+        }
+        last
+    };
+
+    for (name, capacity) in [("grow_on_demand", 0), ("preallocated", FILTER_WINDOW)] {
+        let mut probe = sensor(capacity);
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        black_box(filter(&mut probe));
+        println!("filter_window/{}: {} allocations over 100 samples", name, ALLOCATIONS.load(Ordering::Relaxed) - before);
+
+        group.bench_function(name, |b| b.iter_batched_ref(|| sensor(capacity), |s| black_box(filter(s)), criterion::BatchSize::SmallInput));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_system_integration, benchmark_async_runtime, benchmark_filter_window);
 criterion_main!(benches);
\ No newline at end of file
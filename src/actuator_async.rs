@@ -3,15 +3,20 @@ use std::sync::Arc;
 use tokio::sync::mpsc::{Sender, Receiver};
 use tokio::time::{self, Duration, Instant};
 use rand::Rng;
-use crate::share::{BenchmarkStats, Feedback, SensorData, SensorType, SystemLog};
+use crate::share::{BenchmarkStats, DeadlineHooks, DeadlinePolicy, Deadlines, Feedback, LogLevel, SensorData, SensorType, Stage, SystemLog};
 
 pub struct ActuatorAsync {
     name: String,
     sensor_type: SensorType,
     operation_deadline: Duration,
+    operation_time: Duration, // Simulated actuation work per command
+    e2e_deadline: Duration,
+    expected_interval: Duration, // Command period the jitter is measured against, the sensor cycle
     log: Arc<Mutex<SystemLog>>,
     benchmark_stats: BenchmarkStats,
     last_arrival_time: Option<std::time::Instant>,
+    deadline_hooks: DeadlineHooks,
+    deadline_policy: DeadlinePolicy,
 }
 
 impl ActuatorAsync {
@@ -20,13 +25,46 @@ impl ActuatorAsync {
             name,
             sensor_type,
             operation_deadline: Duration::from_micros(2000),
+            operation_time: Duration::from_micros(100),
+            e2e_deadline: Duration::from_millis(5),
+            expected_interval: Duration::from_millis(5),
             log,
             benchmark_stats: BenchmarkStats::new(),
-            last_arrival_time:None
+            last_arrival_time:None,
+            deadline_hooks: DeadlineHooks::default(),
+            deadline_policy: DeadlinePolicy::MarkAndContinue,
         }
     }
 
-    fn generate_feedback(&self) -> Feedback {
+    // Tune the simulated work and its deadline independently, e.g. to push the
+    // work close to the deadline and watch the miss rate near saturation
+    pub fn with_operation(mut self, work: Duration, deadline: Duration) -> Self {
+        self.operation_time = work;
+        self.operation_deadline = deadline;
+        self
+    }
+
+    pub fn with_deadlines(mut self, deadlines: Deadlines) -> Self {
+        self.operation_deadline = deadlines.actuation;
+        self.operation_time = deadlines.actuation_work;
+        self.e2e_deadline = deadlines.end_to_end;
+        self.expected_interval = deadlines.sensor_cycle;
+        self.deadline_policy = deadlines.policies.actuation;
+        self
+    }
+
+    // Drop suppresses the feedback of a late actuation; Escalate acts like MarkAndContinue
+    pub fn with_deadline_policy(mut self, policy: DeadlinePolicy) -> Self {
+        self.deadline_policy = policy;
+        self
+    }
+
+    pub fn with_deadline_hooks(mut self, hooks: DeadlineHooks) -> Self {
+        self.deadline_hooks = hooks;
+        self
+    }
+
+    fn generate_feedback(&self, origin: &SensorData) -> Feedback {
         let mut rng = rand::rng();
         if rng.random_bool(0.95) {
             Feedback {
@@ -34,13 +72,20 @@ impl ActuatorAsync {
                 error_msg: "no".to_string(),
                 recalibrate_offset: 0.0,
                 timestamp: std::time::Instant::now(),
+                origin: Some((origin.id, origin.captured_at())),
             }
         } else {
+            // Same bounds as the threaded actuator, so drift alone keeps Position inside its anomaly band
+            let max_offset = match self.sensor_type {
+                SensorType::Position => 0.01,
+                SensorType::Force | SensorType::Temperature => 0.5,
+            };
             Feedback {
                 is_ack: false,
                 error_msg: "Drift Check".to_string(),
-                recalibrate_offset: rng.random_range(-0.5..0.5),
+                recalibrate_offset: rng.random_range(-max_offset..max_offset),
                 timestamp: std::time::Instant::now(),
+                origin: Some((origin.id, origin.captured_at())),
             }
         }
     }
@@ -53,15 +98,10 @@ impl ActuatorAsync {
         if let Some(last_time) = self.last_arrival_time{
             let interval = current_time.duration_since(last_time);
 
-            // Define expected rate (Consider making this a constant or struct field later)
-            let expected_interval = Duration::from_millis(5);
+            let expected_interval = self.expected_interval;
 
             // Calculate absolute difference (Jitter)
-            let jitter = if interval > expected_interval {
-                interval - expected_interval
-            } else {
-                expected_interval - interval
-            };
+            let jitter = interval.abs_diff(expected_interval);
 
             // Update Stats
             self.benchmark_stats.total_at_jitter += jitter;
@@ -77,6 +117,7 @@ impl ActuatorAsync {
         mut rx_data: Receiver<SensorData>,
         tx_feedback: Sender<Feedback>,
     ) -> BenchmarkStats {
+        self.benchmark_stats.e2e_deadline = self.e2e_deadline;
 
         while let Some(data) = rx_data.recv().await {
 
@@ -86,16 +127,20 @@ impl ActuatorAsync {
 
             // 1. Simulate Actuation (NON-BLOCKING SLEEP)
             // println!("Actuator [{}] acting...", self.name);
-            time::sleep(Duration::from_micros(100)).await;
+            time::sleep(self.operation_time).await;
 
             // 2. Deadline Check
-            if start.elapsed() > self.operation_deadline {
+            let operation_duration = start.elapsed();
+            let missed = operation_duration > self.operation_deadline;
+            if missed {
                 self.benchmark_stats.actuator_missed_deadlines += 1;
+                self.deadline_hooks.notify(Stage::Actuation, self.sensor_type, operation_duration - self.operation_deadline);
             }
 
             // 3. Feedback
-            let fb = self.generate_feedback();
-            if fb.recalibrate_offset != 0.0 {
+            let fb = self.generate_feedback(&data);
+            let dropped = missed && self.deadline_policy == DeadlinePolicy::Drop;
+            if fb.recalibrate_offset != 0.0 && !dropped {
                 let _ = tx_feedback.send(fb).await;
             }
 
@@ -106,9 +151,52 @@ impl ActuatorAsync {
 
             // E2E Latency calculation
             let now = std::time::Instant::now();
-            self.benchmark_stats.total_latency += now.duration_since(data.timestamp);
+            let e2e_latency = data.age_since(now);
+            self.benchmark_stats.total_latency += e2e_latency;
+            self.benchmark_stats.latency_histogram.record(e2e_latency);
+            if e2e_latency > self.e2e_deadline {
+                self.benchmark_stats.e2e_deadline_misses += 1;
+                self.benchmark_stats.try_log(&self.log, LogLevel::Warn, format!("[Deadline] Actuator [{}] end-to-end latency {:?} for sample {} (Limit: {:?})", self.name, e2e_latency, data.id, self.e2e_deadline));
+            }
         }
 
         self.benchmark_stats
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Misses of an actuator doing `work` per command against `deadline`, over three commands
+    async fn misses_with(work: Duration, deadline: Duration) -> u32 {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(crate::share::Verbosity::Silent);
+        let actuator = ActuatorAsync::new("test".to_string(), SensorType::Force, Arc::new(Mutex::new(log)))
+            .with_operation(work, deadline);
+        let (tx_data, rx_data) = tokio::sync::mpsc::channel(8);
+        let (tx_feedback, _rx_feedback) = tokio::sync::mpsc::channel(8);
+        for id in 0..3 {
+            tx_data.send(SensorData {
+                id,
+                sensor_type: SensorType::Force,
+                value: 30.0,
+                anomaly: false,
+                timestamp: std::time::Instant::now(),
+                processed_timestamp: None,
+                capture_time: None,
+                stamps: Default::default(),
+            }).await.unwrap();
+        }
+        drop(tx_data);
+        actuator.run(rx_data, tx_feedback).await.actuator_missed_deadlines
+    }
+
+    #[tokio::test]
+    async fn miss_counter_flips_as_the_work_crosses_the_deadline() {
+        // A sleep never returns early, so work past the deadline always misses; below it
+        // the margin only has to cover the timer's millisecond resolution and a busy machine
+        assert_eq!(misses_with(Duration::from_millis(10), Duration::from_millis(9)).await, 3);
+        assert_eq!(misses_with(Duration::from_millis(10), Duration::from_millis(25)).await, 0);
+    }
+}
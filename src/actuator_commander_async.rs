@@ -2,8 +2,7 @@ use std::collections::HashMap;
 use tokio::sync::Mutex;
 use std::sync::Arc;
 use tokio::sync::mpsc::{Sender, Receiver};
-use tokio::time::{Instant, Duration};
-use crate::share::{BenchmarkStats, PidController, SensorData, SensorType, SystemLog, SystemMode};
+use crate::share::{default_profile, BenchmarkStats, DeadlineCallback, DeadlineHooks, DeadlinePolicy, Deadlines, LogLevel, ModeTransition, PidController, SensorData, SensorType, ShutdownReason, SnapshotHandle, Stage, SystemLog, SystemMode};
 
 pub struct ActuatorCommanderAsync {
     pids: HashMap<SensorType, PidController>,
@@ -11,7 +10,11 @@ pub struct ActuatorCommanderAsync {
     log: Arc<Mutex<SystemLog>>,
     system_mode: SystemMode,
     consecutive_anomalies: u32,
+    anomaly_streaks: HashMap<SensorType, u32>, // Same per-sensor counting as the threaded commander
+    monitor: SnapshotHandle,
     benchmark_stats: BenchmarkStats,
+    deadline_hooks: DeadlineHooks,
+    deadlines: Deadlines,
 }
 
 impl ActuatorCommanderAsync {
@@ -19,10 +22,10 @@ impl ActuatorCommanderAsync {
         sender_actuators: HashMap<SensorType, Sender<SensorData>>,
         log: Arc<Mutex<SystemLog>>,
     ) -> Self {
-        let mut pids = HashMap::new();
-        pids.insert(SensorType::Force, PidController::new(1.5, 0.1, 0.05));
-        pids.insert(SensorType::Position, PidController::new(0.8, 0.2, 0.1));
-        pids.insert(SensorType::Temperature, PidController::new(0.5, 0.05, 0.01));
+        let pids = SensorType::all().iter().map(|&s_type| {
+            let (kp, ki, kd) = default_profile(s_type).gains;
+            (s_type, PidController::new(kp, ki, kd))
+        }).collect();
 
         Self {
             pids,
@@ -30,7 +33,76 @@ impl ActuatorCommanderAsync {
             log,
             system_mode: SystemMode::Normal,
             consecutive_anomalies: 0,
+            anomaly_streaks: HashMap::new(),
+            monitor: SnapshotHandle::default(),
             benchmark_stats: BenchmarkStats::new(),
+            deadline_hooks: DeadlineHooks::default(),
+            deadlines: Deadlines::default(),
+        }
+    }
+
+    pub fn with_deadlines(mut self, deadlines: Deadlines) -> Self {
+        self.deadlines = deadlines;
+        self
+    }
+
+    // Register a callback fired on every deadline miss of any stage
+    pub fn on_deadline_miss(&self, callback: DeadlineCallback) {
+        self.deadline_hooks.register(callback);
+    }
+
+    pub fn deadline_hooks(&self) -> DeadlineHooks {
+        self.deadline_hooks.clone()
+    }
+
+    // Keep a handle before `run` moves the commander into its task
+    pub fn snapshot_handle(&self) -> SnapshotHandle {
+        self.monitor.clone()
+    }
+
+    pub fn mode_transitions(&self) -> Vec<ModeTransition> {
+        self.monitor.transitions()
+    }
+
+    fn set_mode(&mut self, mode: SystemMode, triggering_sensor: SensorType) {
+        let from = self.system_mode;
+        self.system_mode = mode;
+        self.monitor.record_transition(ModeTransition {
+            from,
+            to: mode,
+            at: std::time::Instant::now(),
+            triggering_sensor,
+            consecutive_anomalies: self.consecutive_anomalies,
+        });
+    }
+
+    // Fail-Safe Mode, mirrors `ActuatorCommander::fail_safe`
+    async fn fail_safe(&mut self, data: &SensorData) {
+        let streak = self.anomaly_streaks.entry(data.sensor_type).or_insert(0);
+        if data.anomaly {
+            *streak += 1;
+        } else if *streak > 0 && self.system_mode != SystemMode::EmergencyStop {
+            *streak -= 1; // Recovery: every clean sample pays back one anomaly
+        }
+        self.consecutive_anomalies = self.anomaly_streaks.values().copied().max().unwrap_or(0);
+
+        if data.anomaly {
+            // Case 1: Switch to Degraded
+            if self.consecutive_anomalies >= 3 && self.system_mode == SystemMode::Normal {
+                self.set_mode(SystemMode::Degraded, data.sensor_type);
+                self.log.lock().await.alert("High Anomaly Rate! Switching to DEGRADED MODE.".to_string());
+            }
+            // Case 2: Switch to E-STOP
+            if self.consecutive_anomalies >= 10 && self.system_mode != SystemMode::EmergencyStop {
+                self.set_mode(SystemMode::EmergencyStop, data.sensor_type);
+                let mut log = self.log.lock().await;
+                log.alert("CRITICAL FAILURE! Switching to E-STOP.".to_string());
+                log.request_shutdown(ShutdownReason::EmergencyStop);
+            }
+        } else if self.consecutive_anomalies == 0 && self.system_mode == SystemMode::Degraded {
+            // Recovery logic
+            self.set_mode(SystemMode::Normal, data.sensor_type);
+            self.log.lock().await.alert("System Stabilized. Returning to NORMAL MODE.".to_string());
         }
     }
 
@@ -38,24 +110,60 @@ impl ActuatorCommanderAsync {
         let arrival_time = std::time::Instant::now(); // Use Std Instant for duration math with data.timestamp
 
         // 1. Stats & Deadline
-        if let Some(start_time) = data.processed_timestamp {
-            let elapsed = arrival_time.duration_since(start_time);
+        if let Some(elapsed) = data.transit_since(arrival_time) {
             self.benchmark_stats.total_trans_time += elapsed;
-            if elapsed > Duration::from_micros(100) {
+            let deadline_transmit = self.deadlines.transmission.get(data.sensor_type);
+            if elapsed > deadline_transmit {
                 self.benchmark_stats.sensor_missed_deadlines += 1;
+                *self.benchmark_stats.transmission_misses.get_mut(data.sensor_type) += 1;
+                self.deadline_hooks.notify(Stage::Transmission, data.sensor_type, elapsed - deadline_transmit);
+
+                match self.deadlines.policies.transmission {
+                    DeadlinePolicy::Drop => return,
+                    DeadlinePolicy::MarkAndContinue => {}
+                    DeadlinePolicy::Escalate => data.anomaly = true, // A late sample counts like an anomalous one
+                }
             }
         }
 
+        // Never feed a corrupt reading into the controller
+        if let Err(e) = data.validate(std::time::Instant::now()) {
+            self.benchmark_stats.invalid_samples += 1;
+            self.log.lock().await.write_level(LogLevel::Warn, format!("[Commander] Invalid sample from {:?} (ID: {}): {}. Skipping.", data.sensor_type, data.id, e));
+            return;
+        }
+
+        // 1.1 Fault tolerance; E-STOP latches and bypasses the controller
+        self.fail_safe(&data).await;
+        self.monitor.record_sample(data.sensor_type, data.value, data.anomaly, self.system_mode, self.consecutive_anomalies);
+        if self.system_mode == SystemMode::EmergencyStop {
+            // Safe command: the raw reading is forwarded without any control effort
+            if data.anomaly {
+                if let Some(tx) = self.sender_actuators.get(&data.sensor_type) {
+                    let _ = tx.send(data).await;
+                }
+            }
+            return;
+        }
+
         // 2. PID Control Logic
-        let setpoint = match data.sensor_type {
-            SensorType::Force => 30.0,
-            SensorType::Position => 0.0,
-            SensorType::Temperature => 240.0,
-        };
+        let setpoint = default_profile(data.sensor_type).setpoint;
 
         if let Some(pid) = self.pids.get_mut(&data.sensor_type) {
             let scale = if self.system_mode == SystemMode::Degraded { 0.5 } else { 1.0 };
-            let effort = pid.compute(setpoint, data.value, 0.005, scale);
+            let effort = match pid.compute_checked(setpoint, data.value, 0.005, scale) {
+                Ok(effort) => effort,
+                Err(e) => {
+                    // Same reaction as `ActuatorCommander::pid_diverged`
+                    if self.system_mode != SystemMode::EmergencyStop {
+                        self.set_mode(SystemMode::EmergencyStop, data.sensor_type);
+                    }
+                    let mut log = self.log.lock().await;
+                    log.alert(format!("{:?} PID diverged: {}. Switching to E-STOP.", data.sensor_type, e));
+                    log.request_shutdown(ShutdownReason::EmergencyStop);
+                    return;
+                }
+            };
             data.value = effort; // Update data with control effort
 
             // 3. Forward to Actuator (Async Send)
@@ -74,7 +182,8 @@ impl ActuatorCommanderAsync {
         mut rx_temp: Receiver<SensorData>,
     ) -> BenchmarkStats {
 
-        // Loop continuously waiting for ANY of the 3 sensors
+        // Loop continuously waiting for ANY of the 3 sensors. A closed channel
+        // disables its branch, so the others keep being served until all close.
         loop {
             tokio::select! {
                 Some(data) = rx_force.recv() => self.handle_sensor_data(data).await,
@@ -85,4 +194,94 @@ impl ActuatorCommanderAsync {
         }
         self.benchmark_stats
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn anomaly(id: i32) -> SensorData {
+        SensorData {
+            id,
+            sensor_type: SensorType::Force,
+            value: 999.0,
+            anomaly: true,
+            timestamp: std::time::Instant::now(),
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn anomalies_escalate_like_the_threaded_commander() {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(crate::share::Verbosity::Silent);
+        let log = Arc::new(Mutex::new(log));
+        let commander = ActuatorCommanderAsync::new(HashMap::new(), log.clone());
+        let monitor = commander.snapshot_handle();
+        let (tx_force, rx_force) = tokio::sync::mpsc::channel(16);
+        let (tx_pos, rx_pos) = tokio::sync::mpsc::channel(1);
+        let (tx_temp, rx_temp) = tokio::sync::mpsc::channel(1);
+        for id in 0..10 {
+            tx_force.send(anomaly(id)).await.unwrap();
+        }
+        drop((tx_force, tx_pos, tx_temp));
+        commander.run(rx_force, rx_pos, rx_temp).await;
+
+        let mut threaded_log = SystemLog::in_memory();
+        threaded_log.set_verbosity(crate::share::Verbosity::Silent);
+        let mut threaded = crate::ActuatorCommander::new(HashMap::new(), HashMap::new(), Arc::new(std::sync::Mutex::new(threaded_log)));
+        for id in 0..10 {
+            threaded.fail_safe(anomaly(id));
+        }
+
+        let trail = |t: Vec<ModeTransition>| t.iter().map(|t| (t.from, t.to, t.consecutive_anomalies)).collect::<Vec<_>>();
+        assert_eq!(trail(monitor.transitions()), trail(threaded.mode_transitions()));
+        assert_eq!(monitor.snapshot().mode, SystemMode::EmergencyStop);
+        assert_eq!(log.lock().await.shutdown_reason(), Some(ShutdownReason::EmergencyStop));
+    }
+
+    #[tokio::test]
+    async fn closing_one_sensor_channel_keeps_the_others_served() {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(crate::share::Verbosity::Silent);
+        let (pos_tx, mut pos_rx) = tokio::sync::mpsc::channel(16);
+        let (temp_tx, mut temp_rx) = tokio::sync::mpsc::channel(16);
+        let actuators = HashMap::from([(SensorType::Position, pos_tx), (SensorType::Temperature, temp_tx)]);
+        let commander = ActuatorCommanderAsync::new(actuators, Arc::new(Mutex::new(log)));
+        let (tx_force, rx_force) = tokio::sync::mpsc::channel(1);
+        let (tx_pos, rx_pos) = tokio::sync::mpsc::channel(16);
+        let (tx_temp, rx_temp) = tokio::sync::mpsc::channel(16);
+        let running = tokio::spawn(commander.run(rx_force, rx_pos, rx_temp));
+
+        drop(tx_force);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        for id in 0..3 {
+            let calm = |sensor_type, value| SensorData { sensor_type, value, anomaly: false, ..anomaly(id) };
+            tx_pos.send(calm(SensorType::Position, 0.0)).await.unwrap();
+            tx_temp.send(calm(SensorType::Temperature, 25.0)).await.unwrap();
+        }
+        drop((tx_pos, tx_temp));
+        running.await.unwrap();
+
+        let drain = |rx: &mut tokio::sync::mpsc::Receiver<SensorData>| std::iter::from_fn(|| rx.try_recv().ok()).count();
+        assert_eq!((drain(&mut pos_rx), drain(&mut temp_rx)), (3, 3));
+    }
+
+    #[tokio::test]
+    async fn invalid_samples_are_counted_and_skipped() {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(crate::share::Verbosity::Silent);
+        let (force_tx, mut force_rx) = tokio::sync::mpsc::channel(16);
+        let mut commander = ActuatorCommanderAsync::new(HashMap::from([(SensorType::Force, force_tx)]), Arc::new(Mutex::new(log)));
+        let calm = |id, value| SensorData { value, anomaly: false, ..anomaly(id) };
+        commander.handle_sensor_data(calm(1, f64::INFINITY)).await;
+        commander.handle_sensor_data(calm(-1, 10.0)).await;
+        commander.handle_sensor_data(SensorData { capture_time: Some(std::time::Instant::now() + Duration::from_secs(1)), ..calm(2, 10.0) }).await;
+
+        assert_eq!(commander.benchmark_stats.invalid_samples, 3);
+        assert!(force_rx.try_recv().is_err());
+    }
+}
@@ -1,45 +1,407 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use crossbeam::{channel, select};
-use crossbeam::channel::{Receiver, Sender};
-use crate::share::{BenchmarkStats, Feedback, PidController, SensorData, SensorType, SystemLog, SystemMode};
+use crossbeam::channel::{Receiver, Sender, TryRecvError, TrySendError};
+use crate::replay::{RecordedStep, Recorder};
+use crate::share::{default_profile, ActuatorRetry, ActuatorStatus, AnomalyRateGate, BenchmarkStats, ComponentRegistry, DataError, DeadLetterLog, DEFAULT_SETTLING_BAND, DeadlineCallback, DeadlineHooks, DeadlinePolicy, Deadlines, DropReason, Feedback, InfluxSink, Interpolation, InvariantChecker, LogLevel, ModeTimes, ModeTransition, PidController, PidError, PidTrace, SensorData, SetpointSchedule, SensorSet, SensorType, ShutdownReason, SnapshotHandle, Stage, StepTracker, SystemEvent, SystemLog, SystemMode, SystemSnapshot, ThroughputWindow};
+
+// Retries of a command the actuator channel could not take
+const SEND_ATTEMPTS: u32 = 4;
+const SEND_BACKOFF: Duration = Duration::from_micros(50); // Doubled after every retry
+const MAX_SEND_BACKOFF: Duration = Duration::from_millis(1);
+const SHUTDOWN_DRAIN: Duration = Duration::from_millis(50); // Longest wait for the sensors' last samples after the stop
+
+// Requests sent to a running commander from outside its thread
+pub enum ControlCommand {
+    // Reply with the instant every effort first stayed within `tolerance` of its
+    // previous value for `consecutive` cycles in a row
+    WatchSteadyState { tolerance: f64, consecutive: u32, reply: Sender<Instant> },
+    // Replace the setpoint schedule of one sensor with a constant
+    SetSetpoint { sensor_type: SensorType, value: f64 },
+    // Reply with a sender the commander serves like the built-in sensor of `sensor_type`
+    AddSensor { sensor_type: SensorType, reply: Sender<Sender<SensorData>> },
+}
+
+// Mode state shared by the parallel commanders, see `run_parallel`. Held for the
+// whole mode update so two sensors can never switch the mode at the same time.
+struct SharedMode {
+    mode: SystemMode,
+    streaks: HashMap<SensorType, u32>,
+    lost_actuators: SensorSet,
+}
+
+// Where the routing thread of `run_parallel` forwards what belongs to one sensor
+struct Route {
+    status: Sender<ActuatorStatus>,
+    control: Sender<ControlCommand>,
+    batches: Sender<Vec<SensorData>>,
+    added: Sender<SensorData>,
+}
+
+struct SettleWatch {
+    tolerance: f64,
+    consecutive: u32,
+    reply: Sender<Instant>,
+    streaks: HashMap<SensorType, u32>,
+}
 
 pub struct ActuatorCommander {
-    pids: HashMap<SensorType, PidController>,
+    normal_pids: HashMap<SensorType, PidController>,
+    degraded_pids: HashMap<SensorType, PidController>, // Active while in Degraded mode
     sender_actuators: HashMap<SensorType, Sender<SensorData>>,
     // receiver_feedbacks: HashMap<SensorType, Receiver<Feedback>>,
-    // sender_feedback: HashMap<SensorType,Sender<Feedback>>,
+    sender_feedback: HashMap<SensorType,Sender<Feedback>>,
     log:Arc<Mutex<SystemLog>>,
     system_mode: SystemMode,
-    consecutive_anomalies: u32,
+    mode_since: Instant, // Start of the current mode, for `BenchmarkStats::time_in_mode`
+    consecutive_anomalies: u32,          // Longest current streak of any sensor
+    anomaly_streaks: HashMap<SensorType, u32>,
+    rate_gate: Option<AnomalyRateGate>,
+    anomaly_windows: HashMap<SensorType, VecDeque<(Instant, bool)>>, // Samples inside the rate gate window
     benchmark_stats: BenchmarkStats,
+    deadline_hooks: DeadlineHooks,
+    monitor: SnapshotHandle,
+    throughput: ThroughputWindow, // Published to the monitor on every maintenance tick
+    deadlines: Deadlines,
+    registry: ComponentRegistry,
+    pid_trace: Option<PidTrace>,
+    influx: Option<InfluxSink>,
+    effort_history: HashMap<SensorType, VecDeque<f64>>,
+    oscillation_streak: HashMap<SensorType, usize>, // Consecutive windows above the ratio
+    stability_window: usize,          // Efforts kept per sensor
+    max_oscillation_ratio: f64,       // Allowed share of slope sign changes in the window
+    control: Receiver<ControlCommand>,
+    added_inputs: Receiver<SensorData>, // See `ControlCommand::AddSensor`
+    added_tx: Sender<SensorData>,
+    batch_inputs: Receiver<Vec<SensorData>>, // Shared by every batching sensor, see `batch_sender`
+    batch_tx: Sender<Vec<SensorData>>,
+    actuator_status: Receiver<ActuatorStatus>,
+    settle_watch: Option<SettleWatch>,
+    dead_letters: DeadLetterLog,
+    tick: Duration,                             // Longest wait in `select!` before a maintenance pass
+    sensor_priority: Vec<SensorType>,           // Order the ready sensor channels are served in, every type once
+    staged: HashMap<SensorType, VecDeque<SensorData>>, // Samples of received batches waiting for their priority turn
+    heartbeat: bool,
+    last_seen: HashMap<SensorType, Instant>,    // Last sample received from each sensor
+    stalled: SensorSet,
+    invariants: Option<Arc<Mutex<InvariantChecker>>>, // Shared by the parallel commanders
+    setpoints: HashMap<SensorType, SetpointSchedule>,
+    degraded_setpoints: HashMap<SensorType, SetpointSchedule>, // Missing types keep the normal schedule
+    started_at: Instant, // Reference for the setpoint schedules, reset when `run` starts
+    recorder: Option<Recorder>,
+    deadbands: HashMap<SensorType, f64>, // Smallest effort change worth a command; missing types send every effort
+    last_sent: HashMap<SensorType, f64>,  // Last effort an actuator was sent
+    drift_window: Option<usize>, // Readings averaged per bias check, None sends no corrections
+    bias_windows: HashMap<SensorType, (f64, usize)>, // Sum and count of the current window
+    last_setpoints: HashMap<SensorType, f64>,
+    steps: HashMap<SensorType, StepTracker>, // Step response in progress per sensor
+    settling_band: f64,
+    failed_actuators: SensorSet, // Inside a failure window; their commands are held back until they recover
+    actuator_retry: Option<ActuatorRetry>,
+    actuator_failures: HashMap<SensorType, (u32, Instant)>, // Failures in a row, and when the next retry is due
+    lost_actuators: SensorSet, // Out of retries; the system stays Degraded
+    shared_mode: Option<Arc<Mutex<SharedMode>>>, // Set on the commanders of `run_parallel`
 }
 
 impl ActuatorCommander {
     pub fn new(
         sender_actuators: HashMap<SensorType, Sender<SensorData>>,
         // receiver_feedbacks: HashMap<SensorType, Receiver<Feedback>>,
-        // sender_feedback: HashMap<SensorType, Sender<Feedback>>,
+        sender_feedback: HashMap<SensorType, Sender<Feedback>>,
         log: Arc<Mutex<SystemLog>>,
     ) -> Self {
 
         let mut pids = HashMap::new();
-        pids.insert(SensorType::Force, PidController::new(1.5, 0.1, 0.05));
-        pids.insert(SensorType::Position, PidController::new(0.8, 0.2, 0.1));
-        pids.insert(SensorType::Temperature, PidController::new(0.5, 0.05, 0.01));
+        let mut setpoints = HashMap::new();
+        for &s_type in SensorType::all() {
+            let profile = default_profile(s_type);
+            let (kp, ki, kd) = profile.gains;
+            pids.insert(s_type, PidController::new(kp, ki, kd));
+            setpoints.insert(s_type, SetpointSchedule::constant(profile.setpoint));
+        }
+        let degraded_pids = pids.iter().map(|(s_type, pid)| (*s_type, pid.halved())).collect();
+        let (added_tx, added_inputs) = channel::unbounded();
+        let (batch_tx, batch_inputs) = channel::unbounded();
 
         Self {
-            pids,
+            normal_pids: pids,
+            degraded_pids,
             sender_actuators,
             // receiver_feedbacks,
-            // sender_feedback,
+            sender_feedback,
             log,
             system_mode: SystemMode::Normal,
+            mode_since: Instant::now(),
             consecutive_anomalies: 0,
+            anomaly_streaks: HashMap::new(),
+            rate_gate: Some(AnomalyRateGate::default()),
+            anomaly_windows: HashMap::new(),
             benchmark_stats: BenchmarkStats::new(),
+            deadline_hooks: DeadlineHooks::default(),
+            monitor: SnapshotHandle::default(),
+            throughput: ThroughputWindow::new(),
+            deadlines: Deadlines::default(),
+            registry: ComponentRegistry::new(),
+            pid_trace: None,
+            influx: None,
+            effort_history: HashMap::new(),
+            oscillation_streak: HashMap::new(),
+            stability_window: 20,
+            max_oscillation_ratio: 0.9,
+            control: channel::never(),
+            added_inputs,
+            added_tx,
+            batch_inputs,
+            batch_tx,
+            actuator_status: channel::never(),
+            settle_watch: None,
+            dead_letters: DeadLetterLog::default(),
+            tick: Duration::from_millis(100),
+            sensor_priority: SensorType::all().to_vec(),
+            staged: HashMap::new(),
+            heartbeat: false,
+            last_seen: HashMap::new(),
+            stalled: SensorSet::default(),
+            invariants: None,
+            setpoints,
+            degraded_setpoints: HashMap::new(),
+            started_at: Instant::now(),
+            recorder: None,
+            deadbands: HashMap::new(),
+            last_sent: HashMap::new(),
+            failed_actuators: SensorSet::default(),
+            actuator_retry: None,
+            actuator_failures: HashMap::new(),
+            lost_actuators: SensorSet::default(),
+            shared_mode: None,
+            drift_window: None,
+            bias_windows: HashMap::new(),
+            last_setpoints: HashMap::new(),
+            steps: HashMap::new(),
+            settling_band: DEFAULT_SETTLING_BAND,
+        }
+    }
+
+    // Escalate on the anomaly rate within a time window as well as on streaks
+    pub fn with_anomaly_rate_gate(mut self, gate: Option<AnomalyRateGate>) -> Self {
+        self.rate_gate = gate;
+        self
+    }
+
+    // Flag a sensor as unstable when the effort slope keeps flipping sign in more
+    // than `max_ratio` of the last `window` samples for `window` samples in a row
+    pub fn with_stability_check(mut self, window: usize, max_ratio: f64) -> Self {
+        self.stability_window = window.max(3);
+        self.max_oscillation_ratio = max_ratio;
+        self
+    }
+
+    // Run a maintenance pass (shutdown check, stalled sensors, optional
+    // heartbeat) at least every `tick`, even when no sample arrives
+    pub fn with_tick(mut self, tick: Duration, heartbeat: bool) -> Self {
+        self.tick = tick;
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    // Order the sensor channels are served in when several have a sample waiting.
+    // Types left out follow in the default order, duplicates count once.
+    pub fn with_sensor_priority(mut self, order: &[SensorType]) -> Self {
+        self.sensor_priority.clear();
+        for &s_type in order.iter().chain(SensorType::all()) {
+            if !self.sensor_priority.contains(&s_type) {
+                self.sensor_priority.push(s_type);
+            }
+        }
+        self
+    }
+
+    // Check every event against these invariants; the first violation stops the run
+    pub fn with_invariants(mut self, invariants: InvariantChecker) -> Self {
+        self.invariants = Some(Arc::new(Mutex::new(invariants)));
+        self
+    }
+
+    // Replace the default gains of one controller. The degraded profile follows at
+    // half these gains unless `with_degraded_gains` is called afterwards
+    pub fn with_gains(mut self, sensor_type: SensorType, kp: f64, ki: f64, kd: f64) -> Self {
+        let mut pid = PidController::new(kp, ki, kd);
+        if let Some(old) = self.normal_pids.get(&sensor_type) {
+            pid.divergence_limit = old.divergence_limit;
+        }
+        self.degraded_pids.insert(sensor_type, pid.halved());
+        self.normal_pids.insert(sensor_type, pid);
+        self
+    }
+
+    // Gains used instead of the normal ones while in Degraded mode
+    pub fn with_degraded_gains(mut self, sensor_type: SensorType, kp: f64, ki: f64, kd: f64) -> Self {
+        let mut pid = PidController::new(kp, ki, kd);
+        if let Some(old) = self.degraded_pids.get(&sensor_type) {
+            pid.divergence_limit = old.divergence_limit;
+        }
+        self.degraded_pids.insert(sensor_type, pid);
+        self
+    }
+
+    // Effort magnitude above which a controller counts as diverged and E-STOP latches
+    pub fn with_divergence_limit(mut self, limit: f64) -> Self {
+        for pid in self.normal_pids.values_mut().chain(self.degraded_pids.values_mut()) {
+            pid.divergence_limit = limit;
         }
+        self
+    }
+
+    // Efforts within `deadband` of the last one sent are not sent again
+    pub fn with_deadband(mut self, sensor_type: SensorType, deadband: f64) -> Self {
+        self.deadbands.insert(sensor_type, deadband);
+        self
+    }
+
+    // Feed a recalibration back to any sensor whose readings drift off the middle of its range
+    pub fn with_drift_correction(mut self, window: Option<usize>) -> Self {
+        self.drift_window = window;
+        self
+    }
+
+    // Retry a failed actuator on this policy instead of waiting for it to recover
+    pub fn with_actuator_retry(mut self, retry: Option<ActuatorRetry>) -> Self {
+        self.actuator_retry = retry;
+        self
+    }
+
+    // Band around the new setpoint a step response has to stay in to count as settled
+    pub fn with_settling_band(mut self, band: f64) -> Self {
+        self.settling_band = band;
+        self
+    }
+
+    // Schedule times count from the start of `run`
+    pub fn with_setpoint_schedule(mut self, sensor_type: SensorType, schedule: SetpointSchedule) -> Self {
+        self.setpoints.insert(sensor_type, schedule);
+        self
+    }
+
+    // Setpoint followed while in Degraded mode, same time base as the normal schedule
+    pub fn with_degraded_setpoint_schedule(mut self, sensor_type: SensorType, schedule: SetpointSchedule) -> Self {
+        self.degraded_setpoints.insert(sensor_type, schedule);
+        self
+    }
+
+    // Record every input sample and the command produced for it, see `verify_replay`
+    pub fn with_recorder(mut self, recorder: Option<Recorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    // Accept `ControlCommand`s while running
+    pub fn with_control(mut self, control: Receiver<ControlCommand>) -> Self {
+        self.control = control;
+        self
+    }
+
+    // Hold the integral of a controller while its actuator reports saturation
+    pub fn with_actuator_status(mut self, actuator_status: Receiver<ActuatorStatus>) -> Self {
+        self.actuator_status = actuator_status;
+        self
+    }
+
+    // Append one CSV row per handled sample; flushed when the commander stops
+    pub fn with_pid_trace(mut self, trace: PidTrace) -> Self {
+        self.pid_trace = Some(trace);
+        self
+    }
+
+    // Measured values and transmission latency, as InfluxDB lines
+    pub fn with_influx(mut self, influx: Option<InfluxSink>) -> Self {
+        self.influx = influx;
+        self
+    }
+
+    pub fn with_registry(mut self, registry: ComponentRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    pub fn with_deadlines(mut self, deadlines: Deadlines) -> Self {
+        self.deadlines = deadlines;
+        self
+    }
+
+    // Share one log with the sensors so every drop site ends up in the same place
+    pub fn with_dead_letters(mut self, dead_letters: DeadLetterLog) -> Self {
+        self.dead_letters = dead_letters;
+        self
+    }
+
+    pub fn dead_letters(&self) -> DeadLetterLog {
+        self.dead_letters.clone()
+    }
+
+    // Current state, safe to call from any thread through `snapshot_handle`
+    pub fn snapshot(&self) -> SystemSnapshot {
+        self.monitor.snapshot()
+    }
+
+    // Audit trail of every mode change
+    pub fn mode_transitions(&self) -> Vec<ModeTransition> {
+        self.monitor.transitions()
+    }
+
+    // Keep a handle before `run` moves the commander into its thread
+    pub fn snapshot_handle(&self) -> SnapshotHandle {
+        self.monitor.clone()
+    }
+
+    // Channel for sensors that send their samples in batches
+    pub fn batch_sender(&self) -> Sender<Vec<SensorData>> {
+        self.batch_tx.clone()
+    }
+
+    // Register a callback fired on every deadline miss of any stage
+    pub fn on_deadline_miss(&self, callback: DeadlineCallback) {
+        self.deadline_hooks.register(callback);
+    }
+
+    // Hooks to hand to the sensors/actuators so their misses reach the same callbacks
+    pub fn deadline_hooks(&self) -> DeadlineHooks {
+        self.deadline_hooks.clone()
+    }
+
+    // FUNCTION 0: Start-up Self-Test
+    // Every sensor type the commander controls must have an actuator sender and a
+    // feedback sender wired, otherwise its commands or feedback silently go nowhere.
+    pub fn self_test(&self) -> Result<(), String> {
+        let mut missing = Vec::new();
+
+        for &s_type in SensorType::all() {
+            if !self.normal_pids.contains_key(&s_type) { continue; }
+
+            if !self.sender_actuators.contains_key(&s_type) {
+                missing.push(format!("{} has no actuator channel", self.registry.sensor_label(s_type)));
+            }
+            if !self.sender_feedback.contains_key(&s_type) {
+                missing.push(format!("{} has no feedback channel", self.registry.sensor_label(s_type)));
+            }
+        }
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Channel wiring incomplete: {}", missing.join(", ")))
+        }
+    }
+
+    // Only drift correction sends feedback from the commander. Without it the senders
+    // are dropped once the self-test has seen them, so a sensor's feedback channel
+    // disconnects when its actuator stops instead of staying open for the whole run.
+    pub fn release_unused_feedback(mut self) -> Self {
+        if self.drift_window.is_none() {
+            self.sender_feedback.clear();
+        }
+        self
     }
 
     // FUNCTION 1: Record Jitter
@@ -49,116 +411,816 @@ impl ActuatorCommander {
     fn handle_sensor_data(&mut self, mut data:SensorData) {
         // 1. Capture Reception Time immediately
         let arrival_time = Instant::now();
+        data.stamps.received = Some(arrival_time);
 
-        if let Some(start_time) = data.processed_timestamp {
-            let elapsed = arrival_time.duration_since(start_time);
+        // 2.3 Send data to specific actuator
+        if let Some(command) = self.process_sample(data, arrival_time) {
+            if self.within_deadband(&command) {
+                self.benchmark_stats.suppressed_commands += 1;
+            } else {
+                self.last_sent.insert(command.sensor_type, command.value);
+                self.send_command(command.sensor_type, command);
+            }
+        }
+
+        let duration = arrival_time.elapsed();
+        self.benchmark_stats.total_actuator_time += duration;
+
+        // 2.4 Check the commander's own time slice
+        let budget = self.deadlines.commander_budget;
+        if duration > budget {
+            self.benchmark_stats.commander_overruns += 1;
+            if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                log.write_level(LogLevel::Warn, format!("[DEADLINE] Commander overran its budget! Took: {:?} (Budget: {:?})", duration, budget));
+            }
+        }
+    }
+
+    // Everything `handle_sensor_data` does short of sending: returns the
+    // command for the actuator, or None when the sample is dropped
+    pub fn process_sample(&mut self, data: SensorData, arrival_time: Instant) -> Option<SensorData> {
+        let input = self.recorder.is_some().then(|| data.clone());
+        let command = self.control_sample(data, arrival_time);
+        if let (Some(recorder), Some(input)) = (&self.recorder, input) {
+            recorder.record(RecordedStep {
+                arrival: arrival_time,
+                offset: arrival_time.saturating_duration_since(self.started_at),
+                input,
+                effort: command.as_ref().map(|c| c.value),
+            });
+        }
+        command
+    }
+
+    fn control_sample(&mut self, mut data: SensorData, arrival_time: Instant) -> Option<SensorData> {
+        self.last_seen.insert(data.sensor_type, arrival_time);
+        self.throughput.record(arrival_time);
+        self.emit(SystemEvent::SampleReceived { sensor_type: data.sensor_type, id: data.id });
+
+        if let Some(elapsed) = data.transit_since(arrival_time) {
 
             // Update Stats
             self.benchmark_stats.total_trans_time += elapsed;
+            if let Some(influx) = &self.influx {
+                influx.latency("transmission", elapsed);
+            }
 
-            // 3. Check Deadline (0.1ms = 100 microseconds)
-            let deadline_transmit = Duration::from_micros(100);
+            // 3. Check Deadline (per sensor type, 0.1ms by default)
+            let deadline_transmit = self.deadlines.transmission.get(data.sensor_type);
 
             if elapsed > deadline_transmit {
                 self.benchmark_stats.sensor_missed_deadlines += 1;
+                *self.benchmark_stats.transmission_misses.get_mut(data.sensor_type) += 1;
 
                 // Log the miss
-                if let Ok(mut log) = self.log.lock() {
-                    log.write(format!(
-                        "[DEADLINE] Sensor {:?} (ID: {}) took {:?} (limit: 100µs)",
-                        data.sensor_type, data.id, elapsed
+                if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                    log.write_level(LogLevel::Warn, format!(
+                        "[DEADLINE] Sensor {} (ID: {}) took {:?} (limit: {:?})",
+                        self.registry.sensor_label(data.sensor_type), data.id, elapsed, deadline_transmit
                     ));
                 }
+                self.deadline_hooks.notify(Stage::Transmission, data.sensor_type, elapsed - deadline_transmit);
+
+                match self.deadlines.policies.transmission {
+                    DeadlinePolicy::Drop => {
+                        self.dead_letters.record(data, DropReason::TransmissionDeadline);
+                        return None;
+                    }
+                    DeadlinePolicy::MarkAndContinue => {}
+                    DeadlinePolicy::Escalate => data.anomaly = true, // A late sample counts like an anomalous one
+                }
             }
         }
 
-        // 2.1 Perform PID
-        let setpoint = match data.sensor_type {
-            SensorType::Force => 30.0,
-            SensorType::Position => 0.0,
-            SensorType::Temperature => 240.0,
+        // 2.0 Never feed a corrupt reading into the controller
+        if let Err(e) = data.validate(arrival_time) {
+            self.benchmark_stats.invalid_samples += 1;
+            if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                log.write_level(LogLevel::Warn, format!("[Commander] Invalid sample from {} (ID: {}): {}. Skipping.", self.registry.sensor_label(data.sensor_type), data.id, e));
+            }
+            let reason = if let DataError::NonFinite(_) = e { DropReason::NonFinite } else { DropReason::Invalid };
+            self.dead_letters.record(data, reason);
+            return None;
+        }
+
+        if let Some(influx) = &self.influx {
+            influx.sensor_value(data.sensor_type, data.value);
+        }
+
+        // 2.1 Fault tolerance; E-STOP latches and bypasses the controller
+        self.update_mode(&data, arrival_time);
+        if !data.anomaly {
+            self.track_bias(&data);
+        }
+        self.monitor.record_sample(data.sensor_type, data.value, data.anomaly, self.system_mode, self.consecutive_anomalies);
+        if self.system_mode == SystemMode::EmergencyStop {
+            // Safe command: anomalous readings are passed through untouched
+            return data.anomaly.then_some(data);
+        }
+
+        // 2.2 Perform PID with the profile of the current mode
+        let degraded = self.system_mode == SystemMode::Degraded;
+        let schedule = match self.degraded_setpoints.get(&data.sensor_type) {
+            Some(schedule) if degraded => Some(schedule),
+            _ => self.setpoints.get(&data.sensor_type),
         };
+        let elapsed = arrival_time.duration_since(self.started_at);
+        let stepped = schedule.is_some_and(|schedule| schedule.interpolation() == Interpolation::Step);
+        let setpoint = schedule.map_or(0.0, |schedule| schedule.at(elapsed));
+        if stepped {
+            self.track_step(data.sensor_type, setpoint, elapsed, data.value);
+        }
 
-        if let Some(pid) = self.pids.get_mut(&data.sensor_type) {
-            let scale = if self.system_mode == SystemMode::Degraded { 0.5 } else { 1.0 };
-            let effort = pid.compute(setpoint, data.value, 0.005,scale);
-            data.value = effort;
+        let pid = self.active_pids().get_mut(&data.sensor_type)?;
+        let terms = pid.compute_detailed(setpoint, data.value, 0.005, 1.0);
+        let checked = pid.check(terms.output);
+        if let Some(trace) = self.pid_trace.as_mut() {
+            trace.record(data.sensor_type, setpoint, data.value, &terms);
+        }
+        if let Err(e) = checked {
+            self.pid_diverged(data.sensor_type, e);
+            return None;
+        }
+        data.value = terms.output;
+        self.emit(SystemEvent::EffortComputed { sensor_type: data.sensor_type, effort: terms.output });
+        self.track_settling(data.sensor_type, terms.output);
+        self.check_stability(data.sensor_type, terms.output);
+        Some(data)
+    }
 
-            // 2.2 Send data to specific actuator
-            self.send_command(data.sensor_type, data);
+    // FUNCTION 2.1: Detect sustained effort oscillation
+    fn check_stability(&mut self, s_type: SensorType, effort: f64) {
+        let window = self.stability_window;
+        let history = self.effort_history.entry(s_type).or_default();
+        if history.len() >= window { history.pop_front(); }
+        history.push_back(effort);
+        if history.len() < window { return; }
+
+        // Count sign changes of the effort derivative over the window
+        let slopes: Vec<f64> = history.iter().zip(history.iter().skip(1)).map(|(a, b)| b - a).collect();
+        let sign_changes = slopes.windows(2).filter(|w| w[0] * w[1] < 0.0).count();
+        let ratio = sign_changes as f64 / (slopes.len() - 1) as f64;
+
+        // Noise alone flips the slope about 2/3 of the time, so only a sustained
+        // excess counts as oscillation
+        let streak = self.oscillation_streak.entry(s_type).or_insert(0);
+        *streak = if ratio > self.max_oscillation_ratio { *streak + 1 } else { 0 };
+
+        if *streak >= window && !self.benchmark_stats.stability_warning.contains(s_type) {
+            self.benchmark_stats.stability_warning.insert(s_type);
+            if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                log.write_level(LogLevel::Warn, format!(
+                    "[STABILITY] {} effort is oscillating ({} slope sign changes in {} samples). Gains may be too aggressive.",
+                    self.registry.sensor_label(s_type), sign_changes, window
+                ));
+            }
         }
+    }
 
-        let duration = arrival_time.elapsed();
-        self.benchmark_stats.total_actuator_time += duration;
+    // FUNCTION 2.7: Measure the response to every setpoint step; ramps are not steps
+    fn track_step(&mut self, s_type: SensorType, setpoint: f64, elapsed: Duration, value: f64) {
+        let previous = self.last_setpoints.insert(s_type, setpoint);
+        if let Some(from) = previous.filter(|from| *from != setpoint) {
+            let tracker = StepTracker::new(s_type, elapsed, from, setpoint).with_band(self.settling_band);
+            if let Some(done) = self.steps.insert(s_type, tracker) {
+                self.monitor.record_step_response(done.finish());
+            }
+        }
+        if let Some(tracker) = self.steps.get_mut(&s_type) {
+            tracker.record(elapsed, value);
+        }
+    }
+
+    // FUNCTION 2.2: Report when every effort has settled
+    fn track_settling(&mut self, s_type: SensorType, effort: f64) {
+        let Some(watch) = self.settle_watch.as_mut() else { return; };
+
+        // Compare against the last effort before `check_stability` records this one
+        let previous = self.effort_history.get(&s_type).and_then(|h| h.back().copied());
+        let streak = watch.streaks.entry(s_type).or_insert(0);
+        *streak = match previous {
+            Some(prev) if (effort - prev).abs() < watch.tolerance => *streak + 1,
+            _ => 0,
+        };
+
+        let settled = self.normal_pids.keys()
+            .all(|s| watch.streaks.get(s).is_some_and(|n| *n >= watch.consecutive));
+        if settled {
+            let _ = watch.reply.send(Instant::now());
+            self.settle_watch = None;
+            self.log_status("[Commander] Steady state reached.".to_string());
+        }
+    }
+
+    fn handle_actuator_status(&mut self, status: ActuatorStatus) {
+        match status {
+            ActuatorStatus::ActionComplete { sensor_type, effort, saturated } => {
+                if let Some(pid) = self.active_pids().get_mut(&sensor_type) {
+                    pid.hold_integral = saturated;
+                }
+                if saturated {
+                    self.log_status(format!("[Saturation] {:?} actuator clamped to {:.2}, holding the integral", sensor_type, effort));
+                }
+            }
+            ActuatorStatus::HardwareFailure { sensor_type, msg } => {
+                // Reports about commands sent before the first one are no news
+                if self.failed_actuators.contains(sensor_type) || self.lost_actuators.contains(sensor_type) { return; }
+                self.failed_actuators.insert(sensor_type);
+                if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                    log.write_level(LogLevel::Critical, format!("[Actuator] Hardware failure: {}, holding {:?} commands", msg, sensor_type));
+                }
+                self.schedule_retry(sensor_type);
+            }
+            ActuatorStatus::Recovered { sensor_type } => {
+                if self.lost_actuators.contains(sensor_type) { return; }
+                self.failed_actuators.remove(sensor_type);
+                self.actuator_failures.remove(&sensor_type);
+                self.last_sent.remove(&sensor_type); // The actuator missed the commands in between
+                self.log_status(format!("[Actuator] {:?} actuator recovered, resuming commands", sensor_type));
+            }
+        }
+    }
+
+    // FUNCTION 3.2: Count a failure against the retry policy; once the retries are
+    // used up the actuator is given up on and the system drops to Degraded
+    fn schedule_retry(&mut self, s_type: SensorType) {
+        let Some(retry) = self.actuator_retry else { return; };
+        let now = Instant::now();
+        let failures = self.actuator_failures.entry(s_type).or_insert((0, now));
+        failures.0 += 1;
+        let attempt = failures.0;
+        if attempt <= retry.max_retries {
+            let backoff = retry.backoff_for(attempt);
+            failures.1 = now + backoff;
+            if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                log.write_level(LogLevel::Warn, format!("[Actuator] {:?} actuator retry {}/{} in {:?}", s_type, attempt, retry.max_retries, backoff));
+            }
+            return;
+        }
+
+        self.lost_actuators.insert(s_type);
+        self.failed_actuators.remove(s_type);
+        self.actuator_faulted(s_type, DropReason::ActuatorDown);
+        if self.system_mode == SystemMode::Normal {
+            self.set_mode(SystemMode::Degraded, s_type);
+        }
+        if let Some(mut state) = self.shared_mode.as_ref().and_then(|s| s.lock().ok()) {
+            state.lost_actuators.insert(s_type);
+            if state.mode == SystemMode::Normal { state.mode = SystemMode::Degraded; }
+        }
+        if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+            log.alert(format!("{:?} actuator failed {} times in a row, giving up. Switching to DEGRADED.", s_type, attempt));
+        }
+    }
+
+    fn handle_control(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::WatchSteadyState { tolerance, consecutive, reply } => {
+                self.settle_watch = Some(SettleWatch { tolerance, consecutive, reply, streaks: HashMap::new() });
+            }
+            ControlCommand::SetSetpoint { sensor_type, value } => {
+                self.setpoints.insert(sensor_type, SetpointSchedule::constant(value));
+                self.log_status(format!("[Commander] {} setpoint set to {}", self.registry.sensor_label(sensor_type), value));
+            }
+            ControlCommand::AddSensor { sensor_type, reply } => {
+                if reply.send(self.added_tx.clone()).is_ok() {
+                    self.log_status(format!("[Commander] Serving an added {:?} sensor", sensor_type));
+                }
+            }
+        }
+    }
+
+    // FUNCTION 2.3: Periodic maintenance
+    fn maintenance(&mut self, start_run: Instant) {
+        self.throughput.advance(Instant::now());
+        self.monitor.record_throughput(self.throughput.per_second());
+
+        // A sensor is stalled after 20 missed cycles (counted from start-up if it never reported)
+        let stall_after = self.deadlines.sensor_cycle * 20;
+        for &s_type in SensorType::all() {
+            if !self.normal_pids.contains_key(&s_type) { continue; }
+            let last = self.last_seen.get(&s_type).copied().unwrap_or(start_run);
+            let quiet = last.elapsed();
+
+            if quiet > stall_after && !self.stalled.contains(s_type) {
+                self.stalled.insert(s_type);
+                if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                    log.write_level(LogLevel::Warn, format!("[Watchdog] {} silent for {:?}", self.registry.sensor_label(s_type), quiet));
+                }
+            } else if quiet <= stall_after && self.stalled.contains(s_type) {
+                self.stalled.remove(s_type);
+                self.log_status(format!("[Watchdog] {} reporting again", self.registry.sensor_label(s_type)));
+            }
+        }
+
+        if self.heartbeat {
+            self.log_status(format!("[Heartbeat] Commander alive, mode {:?}, {} samples", self.system_mode, self.monitor.snapshot().samples_processed));
+        }
+    }
+
+    // FUNCTION 2.4: Feed the invariant checker
+    fn emit(&mut self, event: SystemEvent) {
+        let Some(checker) = &self.invariants else { return; };
+        let checked = checker.lock().map(|mut checker| checker.check(&event));
+        if let Ok(Err(violation)) = checked {
+            if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                log.alert(format!("Invariant violated: {}", violation));
+                log.request_shutdown(ShutdownReason::InvariantViolation(violation));
+            }
+        }
+    }
+
+    // Every mode change goes through here so it is audited and reported as an event
+    fn set_mode(&mut self, mode: SystemMode, triggering_sensor: SensorType) {
+        let from = self.system_mode;
+        self.adopt_mode(mode);
+        self.monitor.record_transition(ModeTransition {
+            from,
+            to: mode,
+            at: Instant::now(),
+            triggering_sensor,
+            consecutive_anomalies: self.consecutive_anomalies,
+        });
+        self.emit(SystemEvent::ModeChanged { from, to: mode });
+    }
+
+    // Switch the controllers over without auditing, e.g. to follow another parallel commander
+    fn adopt_mode(&mut self, mode: SystemMode) {
+        let from = self.system_mode;
+        if from == mode { return; }
+        self.close_mode_dwell();
+        self.system_mode = mode;
+        if (from == SystemMode::Degraded) != (mode == SystemMode::Degraded) {
+            self.swap_profile(from == SystemMode::Degraded);
+        }
+    }
+
+    fn active_pids(&mut self) -> &mut HashMap<SensorType, PidController> {
+        if self.system_mode == SystemMode::Degraded { &mut self.degraded_pids } else { &mut self.normal_pids }
+    }
 
+    // The incoming controllers start without integral, and take over the error and
+    // saturation state of the outgoing ones so the switch causes no derivative kick
+    fn swap_profile(&mut self, leaving_degraded: bool) {
+        let (outgoing, incoming) = if leaving_degraded {
+            (&self.degraded_pids, &mut self.normal_pids)
+        } else {
+            (&self.normal_pids, &mut self.degraded_pids)
+        };
+        for (s_type, pid) in incoming.iter_mut() {
+            let Some(prev) = outgoing.get(s_type) else { continue; };
+            pid.integral = 0.0;
+            pid.prev_error = prev.prev_error;
+            pid.hold_integral = prev.hold_integral;
+        }
+    }
+
+    fn close_mode_dwell(&mut self) {
+        let now = Instant::now();
+        *self.benchmark_stats.time_in_mode.get_mut(self.system_mode) += now.duration_since(self.mode_since);
+        self.mode_since = now;
+    }
+
+    // FUNCTION 2.5: A diverged controller cannot be trusted with any actuator
+    fn pid_diverged(&mut self, s_type: SensorType, error: PidError) {
+        if self.system_mode != SystemMode::EmergencyStop {
+            self.set_mode(SystemMode::EmergencyStop, s_type);
+        }
+        if let Some(mut state) = self.shared_mode.as_ref().and_then(|s| s.lock().ok()) {
+            state.mode = SystemMode::EmergencyStop;
+        }
+        if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+            log.alert(format!("{} PID diverged: {}. Switching to E-STOP.", self.registry.sensor_label(s_type), error));
+            log.request_shutdown(ShutdownReason::EmergencyStop);
+        }
+    }
+
+    // FUNCTION 2.6: Command too close to the last one to be worth sending; safe
+    // commands in E-STOP always go out
+    fn within_deadband(&self, command: &SensorData) -> bool {
+        if self.system_mode == SystemMode::EmergencyStop { return false; }
+        let Some(deadband) = self.deadbands.get(&command.sensor_type) else { return false; };
+        self.last_sent.get(&command.sensor_type).is_some_and(|last| (command.value - last).abs() < *deadband)
     }
 
     // FUNCTION 3: Send command to actuator
-    fn send_command(&self, s_type: SensorType, data: SensorData) {
-        if let Some(tx) = self.sender_actuators.get(&s_type) {
-            let _ = tx.send(data);
+    // A full channel is retried with exponential backoff. When the retries run out or
+    // the actuator has hung up, it is marked faulted and its later commands are dropped.
+    fn send_command(&mut self, s_type: SensorType, mut data: SensorData) {
+        let Some(tx) = self.sender_actuators.get(&s_type) else { return; };
+        if self.lost_actuators.contains(s_type) {
+            self.dead_letters.record(data, DropReason::ActuatorDown);
+            return;
+        }
+        if self.benchmark_stats.faulted_actuators.contains(s_type) {
+            self.dead_letters.record(data, DropReason::Disconnected);
+            return;
+        }
+        if self.failed_actuators.contains(s_type) {
+            // Under a retry policy the actuator gets this command once the backoff is over
+            let due = self.actuator_failures.get(&s_type).filter(|_| self.actuator_retry.is_some()).map(|(failures, at)| (*failures, *at));
+            match due {
+                Some((failures, at)) if Instant::now() >= at => {
+                    self.failed_actuators.remove(s_type);
+                    if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                        log.write(format!("[Actuator] Retrying the {:?} actuator after {} failures", s_type, failures));
+                    }
+                }
+                _ => {
+                    self.dead_letters.record(data, DropReason::ActuatorDown);
+                    return;
+                }
+            }
+        }
+
+        data.stamps.commanded = Some(Instant::now());
+        let mut backoff = SEND_BACKOFF;
+        for attempt in 1..=SEND_ATTEMPTS {
+            match tx.try_send(data) {
+                Ok(()) => return,
+                Err(TrySendError::Full(returned)) if attempt < SEND_ATTEMPTS => {
+                    if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                        log.write_level(LogLevel::Warn, format!("[Commander] {:?} actuator busy, retry {} in {:?}", s_type, attempt, backoff));
+                    }
+                    data = returned;
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_SEND_BACKOFF);
+                }
+                Err(err) => {
+                    let reason = if err.is_full() { DropReason::Overflow } else { DropReason::Disconnected };
+                    self.dead_letters.record(err.into_inner(), reason);
+                    self.actuator_faulted(s_type, reason);
+                    return;
+                }
+            }
+        }
+    }
+
+    // FUNCTION 3.1: Stop commanding an actuator that cannot take commands
+    fn actuator_faulted(&mut self, s_type: SensorType, reason: DropReason) {
+        self.benchmark_stats.faulted_actuators.insert(s_type);
+        if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+            log.write_level(LogLevel::Critical, format!("[Actuator] {:?} actuator faulted ({:?}), dropping its commands", s_type, reason));
         }
     }
 
     // FUNCTION 4: Write System Log
-    fn log_status(&self, msg: String) {
-        if let Ok(mut log) = self.log.lock() {
+    fn log_status(&mut self, msg: String) {
+        if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
             log.write(msg);
         }
     }
 
     // FUNCTION 5: Send feedback to sensor
-    // fn handle_feedback(&self, s_type: SensorType, feedback: Feedback) {
-    //     if let Some(tx) = self.sender_feedback.get(&s_type) {
-    //         let _ = tx.send(feedback);
-    //     }
-    // }
+    fn handle_feedback(&mut self, s_type: SensorType, feedback: Feedback) {
+        if let Some(tx) = self.sender_feedback.get(&s_type) {
+            if tx.try_send(feedback).is_err() {
+                self.benchmark_stats.feedback_drops += 1;
+            }
+        }
+    }
+
+    // FUNCTION 5.2: Cancel a sensor's bias drift
+    // A window mean more than three standard errors off the middle of the profile's
+    // (uniform) range is sent back as an offset; noise alone stays below that.
+    // A window inside it confirms the calibration with `SensorFeedback::Maintain`.
+    fn track_bias(&mut self, data: &SensorData) {
+        let Some(window) = self.drift_window else { return; };
+        let (s_type, value) = (data.sensor_type, data.value);
+        let origin = Some((data.id, data.captured_at()));
+        let (sum, count) = self.bias_windows.entry(s_type).or_insert((0.0, 0));
+        *sum += value;
+        *count += 1;
+        if *count < window { return; }
+        let mean = std::mem::take(sum) / std::mem::take(count) as f64;
+
+        let (min, max) = default_profile(s_type).range;
+        let std_error = (max - min) / 12f64.sqrt() / (window as f64).sqrt();
+        let offset = (min + max) / 2.0 - mean;
+        if offset.abs() <= 3.0 * std_error {
+            self.handle_feedback(s_type, Feedback { is_ack: true, error_msg: "no".to_string(), recalibrate_offset: 0.0, timestamp: Instant::now(), origin });
+            return;
+        }
+
+        self.benchmark_stats.drift_corrections += 1;
+        self.handle_feedback(s_type, Feedback {
+            is_ack: false,
+            error_msg: "no".to_string(),
+            recalibrate_offset: offset,
+            timestamp: Instant::now(),
+            origin,
+        });
+        self.log_status(format!("[Commander] {} reads {:.3} off centre, recalibrating", self.registry.sensor_label(s_type), -offset));
+    }
+
+    // FUNCTION 5.0: One priority pass. Serves the first sensor type, in priority order,
+    // with a batched sample or a sample on its channel; Err is a sensor channel that
+    // disconnected, None means no sensor sample is waiting.
+    fn next_by_priority(
+        &mut self,
+        rx_force: &Receiver<SensorData>,
+        rx_pos: &Receiver<SensorData>,
+        rx_temp: &Receiver<SensorData>, ) -> Option<Result<SensorData, SensorType>>
+    {
+        while let Ok(batch) = self.batch_inputs.try_recv() {
+            self.stage_batch(batch);
+        }
+        for index in 0..self.sensor_priority.len() {
+            let s_type = self.sensor_priority[index];
+            if let Some(data) = self.staged.get_mut(&s_type).and_then(VecDeque::pop_front) {
+                return Some(Ok(data));
+            }
+            let rx = match s_type {
+                SensorType::Force => rx_force,
+                SensorType::Position => rx_pos,
+                SensorType::Temperature => rx_temp,
+            };
+            match rx.try_recv() {
+                Ok(data) => return Some(Ok(data)),
+                Err(TryRecvError::Disconnected) => return Some(Err(s_type)),
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        None
+    }
+
+    // Batches arrive in sample order; each sample waits in the queue of its type
+    fn stage_batch(&mut self, batch: Vec<SensorData>) {
+        for data in batch {
+            self.staged.entry(data.sensor_type).or_default().push_back(data);
+        }
+    }
+
+    // Non-blocking pass over the inputs besides the sensor channels, at most one
+    // message each, so a steady stream of samples cannot starve them
+    fn poll_other_inputs(&mut self) {
+        match self.control.try_recv() {
+            Ok(command) => self.handle_control(command),
+            Err(TryRecvError::Disconnected) => self.control = channel::never(),
+            Err(TryRecvError::Empty) => {}
+        }
+        match self.actuator_status.try_recv() {
+            Ok(status) => self.handle_actuator_status(status),
+            Err(TryRecvError::Disconnected) => self.actuator_status = channel::never(),
+            Err(TryRecvError::Empty) => {}
+        }
+        if let Ok(data) = self.added_inputs.try_recv() {
+            self.handle_sensor_data(data);
+        }
+    }
+
+    // FUNCTION 5.1: Sensor channel disconnected
+    fn channel_closed(&mut self, sensor_type: SensorType, open: &mut SensorSet) {
+        open.remove(sensor_type);
+        if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+            if log.active {
+                log.write_level(LogLevel::Warn, format!("[Commander] {} disconnected, serving the remaining sensors", self.registry.sensor_label(sensor_type)));
+            }
+        }
+    }
 
     // FUNCTION 6: Fail-Safe Mode
     pub fn fail_safe(&mut self, data:SensorData) {
+        self.update_mode(&data, Instant::now());
+
+        // 3. // --- Control Logic ---
+        if data.anomaly && self.system_mode == SystemMode::EmergencyStop {
+            self.send_command(data.sensor_type, data);
+        }
+    }
+
+    fn update_mode(&mut self, data: &SensorData, now: Instant) {
+        // Parallel commanders start from, and leave behind, the shared state
+        let shared_mode = self.shared_mode.clone();
+        let mut shared = shared_mode.as_ref().and_then(|s| s.lock().ok());
+        if let Some(state) = &shared {
+            self.adopt_mode(state.mode);
+            self.anomaly_streaks.clone_from(&state.streaks);
+            self.lost_actuators = self.lost_actuators.union(&state.lost_actuators);
+        }
+        self.apply_anomaly(data, now);
+        if let Some(state) = shared.as_mut() {
+            state.mode = self.system_mode;
+            state.streaks.clone_from(&self.anomaly_streaks);
+            state.lost_actuators = self.lost_actuators;
+        }
+    }
+
+    fn apply_anomaly(&mut self, data: &SensorData, now: Instant) {
         // 1. Fault Tolerance
+        // Each sensor keeps its own streak so clean samples from the others
+        // cannot hide a sensor that keeps failing
+        let streak = self.anomaly_streaks.entry(data.sensor_type).or_insert(0);
         if data.anomaly {
-            self.consecutive_anomalies += 1;
+            *streak += 1;
+        } else if *streak > 0 && self.system_mode != SystemMode::EmergencyStop {
+            *streak -= 1; // Recovery: every clean sample pays back one anomaly
+        }
+        self.consecutive_anomalies = self.anomaly_streaks.values().copied().max().unwrap_or(0);
+        let rate_exceeded = self.anomaly_rate_exceeded(data, now);
 
+        if data.anomaly {
             // Case 1: Switch to Degraded
-            if self.consecutive_anomalies >= 3 && self.system_mode != SystemMode::EmergencyStop {
-                self.system_mode = SystemMode::Degraded;
-                if let Ok(mut log) = self.log.lock() {
+            if (self.consecutive_anomalies >= 3 || rate_exceeded) && self.system_mode == SystemMode::Normal {
+                self.set_mode(SystemMode::Degraded, data.sensor_type);
+                if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
                     log.alert("High Anomaly Rate! Switching to DEGRADED MODE.".to_string());
                 }
             }
             // Case 2: Switch to E-STOP
-            if self.consecutive_anomalies >= 10 {
-                self.system_mode = SystemMode::EmergencyStop;
-                if let Ok(mut log) = self.log.lock() {
+            if self.consecutive_anomalies >= 10 && self.system_mode != SystemMode::EmergencyStop {
+                self.set_mode(SystemMode::EmergencyStop, data.sensor_type);
+                if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
                     log.alert("CRITICAL FAILURE! Switching to E-STOP.".to_string());
-                }
-            } else {
-                // Recovery logic
-                if self.consecutive_anomalies > 0 { self.consecutive_anomalies -= 1; }
-                if self.consecutive_anomalies == 0 && self.system_mode == SystemMode::Degraded {
-                    self.system_mode = SystemMode::Normal;
-                    if let Ok(mut log) = self.log.lock() {
-                        log.alert("System Stabilized. Returning to NORMAL MODE.".to_string());
-                    }
+                    log.request_shutdown(ShutdownReason::EmergencyStop);
                 }
             }
-
-            // 3. // --- Control Logic ---
-            if self.system_mode == SystemMode::EmergencyStop {
-                self.send_command(data.sensor_type, data);
-                return;
+        } else {
+            // Recovery logic; a lost actuator keeps the system Degraded
+            if self.consecutive_anomalies == 0 && !rate_exceeded && self.system_mode == SystemMode::Degraded && self.lost_actuators.is_empty() {
+                self.set_mode(SystemMode::Normal, data.sensor_type);
+                if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                    log.alert("System Stabilized. Returning to NORMAL MODE.".to_string());
+                }
             }
+        }
+    }
 
+    // Windowed gate, independent of the streaks: true while the sensor's anomaly rate is too high
+    fn anomaly_rate_exceeded(&mut self, data: &SensorData, now: Instant) -> bool {
+        let Some(gate) = self.rate_gate else { return false };
+        let window = self.anomaly_windows.entry(data.sensor_type).or_default();
+        window.push_back((now, data.anomaly));
+        while window.front().is_some_and(|(at, _)| now.duration_since(*at) > gate.window) {
+            window.pop_front();
         }
+
+        if window.len() < gate.min_samples { return false; }
+        let anomalous = window.iter().filter(|(_, anomaly)| *anomaly).count();
+        anomalous as f64 / window.len() as f64 > gate.max_rate
+    }
+
+    pub(crate) fn started_at(&self) -> Instant {
+        self.started_at
     }
 
+    // Channel-close semantics: a sensor channel that disconnects is dropped from
+    // the select and the others keep being served. The commander only stops on
+    // shutdown or once every sensor channel has closed, like the async commander.
     pub fn run(
+        self,
+        rx_force: Receiver<SensorData>,
+        rx_pos: Receiver<SensorData>,
+        rx_temp: Receiver<SensorData>, ) -> BenchmarkStats
+    {
+        let open = SensorSet::of(&[SensorType::Force, SensorType::Position, SensorType::Temperature]);
+        self.serve(rx_force, rx_pos, rx_temp, open)
+    }
+
+    // Parallel mode: one commander thread per sensor type, each with its own
+    // controllers, sharing the mode so an anomaly streak on one sensor degrades
+    // them all. This thread only routes actuator status, control commands, batches
+    // and added sensors to the commander of their sensor type.
+    pub fn run_parallel(
         mut self,
         rx_force: Receiver<SensorData>,
         rx_pos: Receiver<SensorData>,
         rx_temp: Receiver<SensorData>, ) -> BenchmarkStats
     {
+        if self.pid_trace.is_some() {
+            if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                log.write_level(LogLevel::Warn, "[PID Trace] Not written by parallel commanders".to_string());
+            }
+        }
+
+        let shared = Arc::new(Mutex::new(SharedMode { mode: self.system_mode, streaks: HashMap::new(), lost_actuators: self.lost_actuators }));
+        let mut routes = HashMap::new();
+        let mut handles = Vec::new();
+        for (s_type, rx) in [(SensorType::Force, rx_force), (SensorType::Position, rx_pos), (SensorType::Temperature, rx_temp)] {
+            let (status_tx, status_rx) = channel::unbounded();
+            let (control_tx, control_rx) = channel::unbounded();
+            let commander = self.fork(s_type, shared.clone())
+                .with_actuator_status(status_rx)
+                .with_control(control_rx);
+            routes.insert(s_type, Route { status: status_tx, control: control_tx, batches: commander.batch_sender(), added: commander.added_tx.clone() });
+
+            let never = channel::never;
+            let (rx_force, rx_pos, rx_temp) = match s_type {
+                SensorType::Force => (rx, never(), never()),
+                SensorType::Position => (never(), rx, never()),
+                SensorType::Temperature => (never(), never(), rx),
+            };
+            handles.push(thread::spawn(move || commander.serve(rx_force, rx_pos, rx_temp, SensorSet::of(&[s_type]))));
+        }
+
+        loop {
+            select! {
+                recv(self.actuator_status) -> msg => {
+                    match msg {
+                        Ok(status) => if let Some(route) = routes.get(&status.sensor_type()) { let _ = route.status.send(status); },
+                        Err(_) => self.actuator_status = channel::never(),
+                    }
+                },
+                recv(self.control) -> msg => {
+                    match msg {
+                        Ok(command) => Self::route_control(command, &routes),
+                        Err(_) => self.control = channel::never(),
+                    }
+                },
+                // A batch comes from a single sensor
+                recv(self.batch_inputs) -> msg => {
+                    if let Ok(batch) = msg {
+                        if let Some(route) = batch.first().and_then(|data| routes.get(&data.sensor_type)) {
+                            let _ = route.batches.send(batch);
+                        }
+                    }
+                },
+                recv(self.added_inputs) -> msg => {
+                    if let Ok(data) = msg {
+                        if let Some(route) = routes.get(&data.sensor_type) { let _ = route.added.send(data); }
+                    }
+                },
+                default(self.tick) => {}
+            }
+
+            let stopped = self.benchmark_stats.timed_lock(&self.log).is_some_and(|log| !log.active);
+            if stopped || handles.iter().all(|h| h.is_finished()) { break; }
+        }
+
+        // Every commander tracked the same mode, so the time in mode is taken from one of them
+        let mut stats = self.benchmark_stats;
+        for (index, handle) in handles.into_iter().enumerate() {
+            let Ok(mut commander_stats) = handle.join() else { continue; };
+            if index > 0 { commander_stats.time_in_mode = ModeTimes::default(); }
+            stats.merge(&commander_stats);
+        }
+        stats
+    }
+
+    // The commander of one sensor type in `run_parallel`, with this one's settings
+    fn fork(&self, s_type: SensorType, shared_mode: Arc<Mutex<SharedMode>>) -> ActuatorCommander {
+        let only = |map: &HashMap<SensorType, Sender<SensorData>>| map.get(&s_type).map(|tx| (s_type, tx.clone())).into_iter().collect();
+        let feedback = self.sender_feedback.get(&s_type).map(|tx| (s_type, tx.clone())).into_iter().collect();
+        let mut commander = ActuatorCommander::new(only(&self.sender_actuators), feedback, self.log.clone());
+        commander.normal_pids = self.normal_pids.get(&s_type).map(|pid| (s_type, pid.clone())).into_iter().collect();
+        commander.degraded_pids = self.degraded_pids.get(&s_type).map(|pid| (s_type, pid.clone())).into_iter().collect();
+        commander.setpoints = self.setpoints.clone();
+        commander.degraded_setpoints = self.degraded_setpoints.clone();
+        commander.deadbands = self.deadbands.clone();
+        commander.system_mode = self.system_mode;
+        commander.rate_gate = self.rate_gate;
+        commander.deadline_hooks = self.deadline_hooks.clone();
+        commander.monitor = self.monitor.clone();
+        commander.deadlines = self.deadlines;
+        commander.registry = self.registry.clone();
+        commander.influx = self.influx.clone();
+        commander.stability_window = self.stability_window;
+        commander.max_oscillation_ratio = self.max_oscillation_ratio;
+        commander.dead_letters = self.dead_letters.clone();
+        commander.tick = self.tick;
+        commander.sensor_priority = self.sensor_priority.clone();
+        commander.heartbeat = self.heartbeat;
+        commander.invariants = self.invariants.clone();
+        commander.recorder = self.recorder.clone();
+        commander.drift_window = self.drift_window;
+        commander.settling_band = self.settling_band;
+        commander.actuator_retry = self.actuator_retry;
+        commander.shared_mode = Some(shared_mode);
+        commander
+    }
+
+    // Hand a command to the commander(s) of `run_parallel` it concerns
+    fn route_control(command: ControlCommand, routes: &HashMap<SensorType, Route>) {
+        match command {
+            ControlCommand::SetSetpoint { sensor_type, .. } | ControlCommand::AddSensor { sensor_type, .. } => {
+                if let Some(route) = routes.get(&sensor_type) { let _ = route.control.send(command); }
+            }
+            // Every commander watches its own sensor; steady once the last of them is
+            ControlCommand::WatchSteadyState { tolerance, consecutive, reply } => {
+                let (settled_tx, settled_rx) = channel::unbounded();
+                for route in routes.values() {
+                    let _ = route.control.send(ControlCommand::WatchSteadyState { tolerance, consecutive, reply: settled_tx.clone() });
+                }
+                drop(settled_tx);
+                let expected = routes.len();
+                thread::spawn(move || {
+                    let settled: Vec<Instant> = settled_rx.iter().take(expected).collect();
+                    if settled.len() == expected {
+                        if let Some(latest) = settled.into_iter().max() { let _ = reply.send(latest); }
+                    }
+                });
+            }
+        }
+    }
+
+    // The select loop behind `run`; stops once every sensor in `open` has hung up
+    fn serve(
+        mut self,
+        mut rx_force: Receiver<SensorData>,
+        mut rx_pos: Receiver<SensorData>,
+        mut rx_temp: Receiver<SensorData>,
+        mut open: SensorSet, ) -> BenchmarkStats
+    {
 
         // 1. Set up for the feedback receiver
         // let rx_fb_force = self.receiver_feedbacks.get(&SensorType::Force).expect("Force FB missing").clone();
@@ -166,60 +1228,785 @@ impl ActuatorCommander {
         // let rx_fb_temp = self.receiver_feedbacks.get(&SensorType::Temperature).expect("Temp FB missing").clone();
 
         let mut active = true;
+        let mut stop_reason = ShutdownReason::DurationElapsed;
 
         let start_run = Instant::now();
+        self.started_at = start_run;
+        self.mode_since = start_run;
+        let mut last_tick = start_run;
 
         while active {
-            select! {
-                // --- SENSOR INPUTS ---
-                recv(rx_force) -> msg => {
-                    match msg {
-                        Ok(data) => self.handle_sensor_data(data),
-                        Err(_) => active = false, // Stop if channel disconnects
-                    }
-                },
-                recv(rx_pos) -> msg => {
-                    match msg {
-                        Ok(data) => self.handle_sensor_data(data),
-                        Err(_) => active = false,
-                    }
-                },
-                recv(rx_temp) -> msg => {
-                    match msg {
-                        Ok(data) => self.handle_sensor_data(data),
-                        Err(_) => active = false,
+            // `select!` picks among the ready channels at random, so every pass serves
+            // one sample in priority order and then polls the other inputs without
+            // blocking. The select is only the fallback when no sample is waiting.
+            match self.next_by_priority(&rx_force, &rx_pos, &rx_temp) {
+                Some(Ok(data)) => {
+                    self.handle_sensor_data(data);
+                    self.poll_other_inputs();
+                }
+                Some(Err(s_type)) => { // Disconnected, same as in the select below
+                    match s_type {
+                        SensorType::Force => rx_force = channel::never(),
+                        SensorType::Position => rx_pos = channel::never(),
+                        SensorType::Temperature => rx_temp = channel::never(),
                     }
+                    self.channel_closed(s_type, &mut open);
+                }
+                None => select! {
+                    // --- SENSOR INPUTS ---
+                    recv(rx_force) -> msg => {
+                        match msg {
+                            Ok(data) => self.handle_sensor_data(data),
+                            Err(_) => { // Stop serving a sensor once its channel disconnects
+                                rx_force = channel::never();
+                                self.channel_closed(SensorType::Force, &mut open);
+                            }
+                        }
+                    },
+                    recv(rx_pos) -> msg => {
+                        match msg {
+                            Ok(data) => self.handle_sensor_data(data),
+                            Err(_) => {
+                                rx_pos = channel::never();
+                                self.channel_closed(SensorType::Position, &mut open);
+                            }
+                        }
+                    },
+                    recv(rx_temp) -> msg => {
+                        match msg {
+                            Ok(data) => self.handle_sensor_data(data),
+                            Err(_) => {
+                                rx_temp = channel::never();
+                                self.channel_closed(SensorType::Temperature, &mut open);
+                            }
+                        }
+                    },
+                    // Sensors added at runtime share one channel; it never
+                    // disconnects because the commander keeps a sender itself
+                    recv(self.added_inputs) -> msg => {
+                        if let Ok(data) = msg { self.handle_sensor_data(data); }
+                    },
+                    // Staged for the next priority pass; same as the added sensors, never disconnects
+                    recv(self.batch_inputs) -> msg => {
+                        if let Ok(batch) = msg { self.stage_batch(batch); }
+                    },
+                    // --- CONTROL ---
+                    recv(self.control) -> msg => {
+                        match msg {
+                            Ok(command) => self.handle_control(command),
+                            Err(_) => self.control = channel::never(), // Nobody left to send commands
+                        }
+                    },
+                    recv(self.actuator_status) -> msg => {
+                        match msg {
+                            Ok(status) => self.handle_actuator_status(status),
+                            Err(_) => self.actuator_status = channel::never(),
+                        }
+                    },
+                    default(self.tick) => {} // Idle: fall through to the maintenance check
+
+
+                    // --- ACTUATOR FEEDBACK---
+                    // recv(rx_fb_force) -> msg => {
+                    //     match msg {
+                    //         Ok(fb) => self.handle_feedback(SensorType::Force, fb),
+                    //         Err(_) => active = false,
+                    //     }
+                    // },
+                    // recv(rx_fb_pos) -> msg => {
+                    //     match msg {
+                    //         Ok(fb) => self.handle_feedback(SensorType::Position, fb),
+                    //         Err(_) => active = false,
+                    //     }
+                    // },
+                    // recv(rx_fb_temp) -> msg => {
+                    //     match msg {
+                    //         Ok(fb) => self.handle_feedback(SensorType::Temperature, fb),
+                    //         Err(_) => active = false,
+                    //     }
+                    // },
+
                 },
-                //default(Duration::from_millis(100)) => {}
+            }
+            active = !open.is_empty();
 
+            if last_tick.elapsed() >= self.tick {
+                self.maintenance(start_run);
+                last_tick = Instant::now();
+            }
 
-                // --- ACTUATOR FEEDBACK---
-                // recv(rx_fb_force) -> msg => {
-                //     match msg {
-                //         Ok(fb) => self.handle_feedback(SensorType::Force, fb),
-                //         Err(_) => active = false,
-                //     }
-                // },
-                // recv(rx_fb_pos) -> msg => {
-                //     match msg {
-                //         Ok(fb) => self.handle_feedback(SensorType::Position, fb),
-                //         Err(_) => active = false,
-                //     }
-                // },
-                // recv(rx_fb_temp) -> msg => {
-                //     match msg {
-                //         Ok(fb) => self.handle_feedback(SensorType::Temperature, fb),
-                //         Err(_) => active = false,
-                //     }
-                // },
+            if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                if !active && log.active {
+                    // Every sensor hung up while the run was still meant to go on
+                    log.request_shutdown(ShutdownReason::ChannelDisconnected);
+                }
+                if !log.active {
+                    stop_reason = log.shutdown_reason().unwrap_or(ShutdownReason::DurationElapsed);
+                    break;
+                }
+            }
+        }
 
+        // Sensors flush their last partial batch once they see the stop, which can be
+        // after the check above; serve what they still send until they all hang up
+        let drain_until = Instant::now() + SHUTDOWN_DRAIN;
+        while !open.is_empty() && Instant::now() < drain_until {
+            match self.next_by_priority(&rx_force, &rx_pos, &rx_temp) {
+                Some(Ok(data)) => self.handle_sensor_data(data),
+                Some(Err(s_type)) => {
+                    match s_type {
+                        SensorType::Force => rx_force = channel::never(),
+                        SensorType::Position => rx_pos = channel::never(),
+                        SensorType::Temperature => rx_temp = channel::never(),
+                    }
+                    self.channel_closed(s_type, &mut open);
+                }
+                None => thread::sleep(Duration::from_micros(200)),
             }
+        }
 
-            if let Ok(log) = self.log.lock() {
-                if !log.active { break; }
+        self.close_mode_dwell();
+        for (_, tracker) in self.steps.drain() {
+            self.monitor.record_step_response(tracker.finish());
+        }
+
+        if let Some(trace) = self.pid_trace.as_mut() {
+            if let Err(e) = trace.flush() {
+                self.log_status(format!("[PID Trace] Flush failed: {}", e));
             }
         }
 
+        if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+            log.write(format!("[Shutdown] Commander stopped: {:?}", stop_reason));
+        }
         self.benchmark_stats
     }
-}
\ No newline at end of file
+}
+
+// In-memory stand-in for the channels: drives a commander synchronously,
+// one sample in, the actuator command out, without spawning any thread
+pub struct LoopbackTransport {
+    commander: ActuatorCommander,
+}
+
+impl LoopbackTransport {
+    pub fn new(commander: ActuatorCommander) -> Self {
+        Self { commander }
+    }
+
+    pub fn push(&mut self, data: SensorData) -> Option<SensorData> {
+        self.commander.process_sample(data, Instant::now())
+    }
+
+    pub fn commander(&self) -> &ActuatorCommander {
+        &self.commander
+    }
+
+    pub fn into_inner(self) -> ActuatorCommander {
+        self.commander
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(sensor_type: SensorType, id: i32, value: f64, anomaly: bool) -> SensorData {
+        SensorData {
+            id,
+            sensor_type,
+            value,
+            anomaly,
+            timestamp: Instant::now(),
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: Default::default(),
+        }
+    }
+
+    fn quiet_log() -> Arc<Mutex<SystemLog>> {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(crate::share::Verbosity::Silent);
+        Arc::new(Mutex::new(log))
+    }
+
+    fn commander() -> ActuatorCommander {
+        ActuatorCommander::new(HashMap::new(), HashMap::new(), quiet_log())
+    }
+
+    // Arrived `transit` after the sensor finished processing it
+    fn late_sample(sensor_type: SensorType, id: i32, transit: Duration) -> SensorData {
+        let mut data = sample(sensor_type, id, default_profile(sensor_type).setpoint, false);
+        data.processed_timestamp = Some(Instant::now() - transit);
+        data
+    }
+
+    // Sensor types in the order the commander handled them
+    fn handled_order(recorder: &Recorder) -> Vec<SensorType> {
+        recorder.recording().steps.iter().map(|step| step.input.sensor_type).collect()
+    }
+
+    #[test]
+    fn ready_channels_are_served_in_priority_order() {
+        let recorder = Recorder::new();
+        let commander = commander().with_recorder(Some(recorder.clone()));
+        let (tx_force, rx_force) = channel::unbounded();
+        let (tx_pos, rx_pos) = channel::unbounded();
+        let (tx_temp, rx_temp) = channel::unbounded();
+        for id in 0..3 {
+            tx_temp.send(sample(SensorType::Temperature, id, 25.0, false)).unwrap();
+            tx_pos.send(sample(SensorType::Position, id, 0.0, false)).unwrap();
+            tx_force.send(sample(SensorType::Force, id, 30.0, false)).unwrap();
+        }
+        drop((tx_force, tx_pos, tx_temp));
+
+        commander.run(rx_force, rx_pos, rx_temp);
+        use SensorType::*;
+        assert_eq!(handled_order(&recorder), [Force, Force, Force, Position, Position, Position, Temperature, Temperature, Temperature]);
+    }
+
+    #[test]
+    fn priority_order_is_configurable_and_applies_to_batches() {
+        let recorder = Recorder::new();
+        let commander = commander().with_recorder(Some(recorder.clone())).with_sensor_priority(&[SensorType::Temperature]);
+        let batches = commander.batch_sender();
+        batches.send((0..2).map(|id| sample(SensorType::Force, id, 30.0, false)).collect()).unwrap();
+        batches.send((0..2).map(|id| sample(SensorType::Position, id, 0.0, false)).collect()).unwrap();
+        batches.send((0..2).map(|id| sample(SensorType::Temperature, id, 25.0, false)).collect()).unwrap();
+
+        let (tx_force, rx_force) = channel::unbounded::<SensorData>();
+        let (tx_pos, rx_pos) = channel::unbounded();
+        let (tx_temp, rx_temp) = channel::unbounded();
+        drop((tx_force, tx_pos, tx_temp));
+
+        commander.run(rx_force, rx_pos, rx_temp);
+        use SensorType::*;
+        assert_eq!(handled_order(&recorder), [Temperature, Temperature, Force, Force, Position, Position]);
+    }
+
+    #[test]
+    fn control_commands_are_served_while_sensors_stay_busy() {
+        let recorder = Recorder::new();
+        let (control_tx, control_rx) = channel::unbounded();
+        let commander = commander().with_recorder(Some(recorder.clone())).with_control(control_rx);
+        let (tx_force, rx_force) = channel::unbounded();
+        let (tx_pos, rx_pos) = channel::unbounded();
+        let (tx_temp, rx_temp) = channel::unbounded();
+        for id in 0..1000 {
+            tx_force.send(sample(SensorType::Force, id, 30.0, false)).unwrap();
+        }
+        control_tx.send(ControlCommand::SetSetpoint { sensor_type: SensorType::Force, value: 45.0 }).unwrap();
+        drop((tx_force, tx_pos, tx_temp));
+
+        commander.run(rx_force, rx_pos, rx_temp);
+
+        // Readings sit on the default setpoint of 30 until the new one of 45 is applied,
+        // which has to happen right after the first sample, not once the queue is drained
+        let steps = recorder.recording().steps;
+        assert_eq!(steps.len(), 1000);
+        let first_effort = steps.iter().position(|step| step.effort.is_some_and(|e| e.abs() > 1.0));
+        assert_eq!(first_effort, Some(1));
+    }
+
+    // Actuator and feedback senders for every type but `missing_feedback`, with the receivers kept open
+    fn wired(missing_feedback: Option<SensorType>) -> (ActuatorCommander, Vec<Receiver<SensorData>>, Vec<Receiver<Feedback>>) {
+        let (mut actuators, mut feedback) = (HashMap::new(), HashMap::new());
+        let (mut actuator_rx, mut feedback_rx) = (Vec::new(), Vec::new());
+        for &s_type in SensorType::all() {
+            let (tx, rx) = channel::unbounded();
+            actuators.insert(s_type, tx);
+            actuator_rx.push(rx);
+            if Some(s_type) != missing_feedback {
+                let (tx, rx) = channel::unbounded();
+                feedback.insert(s_type, tx);
+                feedback_rx.push(rx);
+            }
+        }
+        (ActuatorCommander::new(actuators, feedback, quiet_log()), actuator_rx, feedback_rx)
+    }
+
+    #[test]
+    fn self_test_fails_when_a_feedback_channel_is_missing() {
+        let (complete, _actuators, _feedback) = wired(None);
+        assert_eq!(complete.self_test(), Ok(()));
+
+        let (incomplete, _actuators, _feedback) = wired(Some(SensorType::Temperature));
+        let err = incomplete.self_test().unwrap_err();
+        assert!(err.contains("no feedback channel"), "{}", err);
+        assert!(!err.contains("actuator channel"), "{}", err);
+    }
+
+    #[test]
+    fn released_feedback_senders_let_the_channel_disconnect() {
+        let (commander, _actuators, feedback) = wired(None);
+        let commander = commander.release_unused_feedback();
+        assert!(feedback.iter().all(|rx| rx.recv().is_err()));
+        drop(commander);
+
+        let (commander, _actuators, feedback) = wired(None);
+        let _commander = commander.with_drift_correction(Some(10)).release_unused_feedback();
+        assert!(feedback.iter().all(|rx| matches!(rx.try_recv(), Err(TryRecvError::Empty))));
+    }
+
+    #[test]
+    fn deadline_callback_counts_every_injected_miss() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        let mut commander = commander();
+        let misses = Arc::new(AtomicU32::new(0));
+        let counter = misses.clone();
+        commander.on_deadline_miss(Box::new(move |stage, sensor_type, overshoot| {
+            assert_eq!((stage, sensor_type), (Stage::Transmission, SensorType::Force));
+            assert!(overshoot > Duration::ZERO);
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        for id in 0..5 {
+            commander.process_sample(late_sample(SensorType::Force, id, Duration::from_millis(5)), Instant::now());
+            commander.process_sample(sample(SensorType::Force, 100 + id, 30.0, false), Instant::now()); // On time
+        }
+        assert_eq!(misses.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn deadline_callback_can_register_and_notify_without_deadlocking() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        let hooks = DeadlineHooks::default();
+        let calls = Arc::new(AtomicU32::new(0));
+        let (inner_hooks, inner_calls) = (hooks.clone(), calls.clone());
+        hooks.register(Box::new(move |stage, sensor_type, overshoot| {
+            if inner_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                let counted = inner_calls.clone();
+                inner_hooks.register(Box::new(move |_, _, _| { counted.fetch_add(1, Ordering::SeqCst); }));
+                inner_hooks.notify(stage, sensor_type, overshoot);
+            }
+        }));
+
+        hooks.notify(Stage::Processing, SensorType::Force, Duration::from_micros(10));
+        // The outer call, then the nested notify reaching both hooks
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn loopback_returns_the_pid_effort_for_each_sample() {
+        let mut transport = LoopbackTransport::new(commander());
+        // Force: kp 1.5, ki 0.1, kd 0.05 at dt 5ms, setpoint 30; the first sample
+        // also carries the derivative kick of the error jumping from 0 to 10
+        let first = transport.push(sample(SensorType::Force, 1, 20.0, false)).expect("command");
+        assert!((first.value - 115.005).abs() < 1e-9, "{}", first.value);
+        assert_eq!((first.id, first.sensor_type), (1, SensorType::Force));
+
+        let second = transport.push(sample(SensorType::Force, 2, 20.0, false)).expect("command");
+        assert!((second.value - 15.01).abs() < 1e-9, "{}", second.value);
+    }
+
+    #[test]
+    fn loopback_runs_are_deterministic() {
+        let inputs: Vec<SensorData> = (0..50).map(|id| sample(SensorType::Position, id, (id as f64 * 0.37).sin() * 0.2, false)).collect();
+        let run = || {
+            let mut transport = LoopbackTransport::new(commander());
+            inputs.iter().map(|data| transport.push(data.clone()).map(|c| c.value)).collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn loopback_drops_invalid_samples_into_the_dead_letters() {
+        let mut transport = LoopbackTransport::new(commander());
+        assert!(transport.push(sample(SensorType::Temperature, 7, f64::NAN, false)).is_none());
+
+        let commander = transport.into_inner();
+        let letters = commander.dead_letters().recent();
+        assert_eq!(letters.len(), 1);
+        assert_eq!((letters[0].data.id, letters[0].reason), (7, DropReason::NonFinite));
+    }
+
+    #[test]
+    fn snapshot_can_be_read_while_the_commander_runs() {
+        let commander = commander();
+        let handle = commander.snapshot_handle();
+        let (tx_force, rx_force) = channel::unbounded();
+        let (tx_pos, rx_pos) = channel::unbounded();
+        let (tx_temp, rx_temp) = channel::unbounded();
+        let running = thread::spawn(move || commander.run(rx_force, rx_pos, rx_temp));
+
+        tx_force.send(sample(SensorType::Force, 1, 31.0, false)).unwrap();
+        tx_pos.send(sample(SensorType::Position, 1, 12.0, false)).unwrap();
+        tx_force.send(sample(SensorType::Force, 2, 33.0, true)).unwrap();
+        let started = Instant::now();
+        while handle.snapshot().samples_processed < 3 {
+            assert!(started.elapsed() < Duration::from_secs(5), "commander never got to the samples");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // Still running: the sensor channels are open
+        let snapshot = handle.snapshot();
+        assert!(!running.is_finished());
+        assert_eq!(snapshot.mode, SystemMode::Normal);
+        assert_eq!(snapshot.consecutive_anomalies, 1);
+        assert_eq!(snapshot.last_values.get(&SensorType::Force), Some(&33.0));
+        assert_eq!(snapshot.last_values.get(&SensorType::Position), Some(&12.0));
+        assert_eq!(snapshot.last_values.get(&SensorType::Temperature), None);
+
+        drop((tx_force, tx_pos, tx_temp));
+        running.join().unwrap();
+    }
+
+    // Three late samples under `policy`: (misses, samples that reached the controller, dead letters, anomaly streak)
+    fn late_samples_under(policy: DeadlinePolicy) -> (u32, u64, u64, u32) {
+        let policies = crate::share::DeadlinePolicies { transmission: policy, ..Default::default() };
+        let mut commander = commander().with_deadlines(Deadlines { policies, ..Deadlines::default() });
+        for id in 0..3 {
+            commander.process_sample(late_sample(SensorType::Force, id, Duration::from_millis(5)), Instant::now());
+        }
+        let snapshot = commander.snapshot();
+        let dropped = commander.dead_letters().counts().get(&DropReason::TransmissionDeadline).copied().unwrap_or(0);
+        (commander.benchmark_stats.sensor_missed_deadlines, snapshot.samples_processed, dropped, snapshot.consecutive_anomalies)
+    }
+
+    #[test]
+    fn transmission_policies_have_their_documented_effect() {
+        assert_eq!(late_samples_under(DeadlinePolicy::Drop), (3, 0, 3, 0));
+        assert_eq!(late_samples_under(DeadlinePolicy::MarkAndContinue), (3, 3, 0, 0));
+        assert_eq!(late_samples_under(DeadlinePolicy::Escalate), (3, 3, 0, 3));
+    }
+
+    #[test]
+    fn transmission_misses_use_each_sensors_own_deadline() {
+        let transmission = crate::share::PerSensor {
+            force: Duration::from_millis(1),
+            position: Duration::from_millis(100),
+            temperature: Duration::from_secs(1),
+        };
+        let mut commander = commander().with_deadlines(Deadlines { transmission, ..Deadlines::default() });
+        // The same 10ms in transit is late for Force only
+        for (id, s_type) in SensorType::all().iter().enumerate() {
+            commander.process_sample(late_sample(*s_type, id as i32, Duration::from_millis(10)), Instant::now());
+        }
+
+        let misses = commander.benchmark_stats.transmission_misses;
+        assert_eq!((misses.force, misses.position, misses.temperature), (1, 0, 0));
+        assert_eq!(commander.benchmark_stats.sensor_missed_deadlines, 1);
+    }
+
+    #[test]
+    fn idle_commander_still_logs_a_heartbeat() {
+        let log = quiet_log();
+        let commander = ActuatorCommander::new(HashMap::new(), HashMap::new(), log.clone())
+            .with_tick(Duration::from_millis(10), true);
+        let (tx_force, rx_force) = channel::unbounded::<SensorData>();
+        let (tx_pos, rx_pos) = channel::unbounded();
+        let (tx_temp, rx_temp) = channel::unbounded();
+        let running = thread::spawn(move || commander.run(rx_force, rx_pos, rx_temp));
+
+        // No sample is ever sent
+        let heartbeats = || log.lock().unwrap().recent_entries().iter().filter(|l| l.contains("[Heartbeat]")).count();
+        let started = Instant::now();
+        while heartbeats() < 2 {
+            assert!(started.elapsed() < Duration::from_secs(5), "no heartbeat while idle");
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(log.lock().unwrap().recent_entries().iter().any(|l| l.contains("[Heartbeat] Commander alive, mode Normal, 0 samples")));
+
+        drop((tx_force, tx_pos, tx_temp));
+        running.join().unwrap();
+    }
+
+    #[test]
+    fn audit_trail_records_each_mode_change_in_order() {
+        let mut commander = commander();
+        for id in 0..12 {
+            commander.fail_safe(sample(SensorType::Position, id, 99.0, true));
+        }
+
+        let trail: Vec<(SystemMode, SystemMode, SensorType, u32)> = commander.mode_transitions().iter()
+            .map(|t| (t.from, t.to, t.triggering_sensor, t.consecutive_anomalies))
+            .collect();
+        assert_eq!(trail, vec![
+            (SystemMode::Normal, SystemMode::Degraded, SensorType::Position, 3),
+            (SystemMode::Degraded, SystemMode::EmergencyStop, SensorType::Position, 10),
+        ]);
+        let times: Vec<Instant> = commander.mode_transitions().iter().map(|t| t.at).collect();
+        assert!(times[0] <= times[1]);
+    }
+
+    // Force integral after 100 samples 30 below the setpoint, driving an actuator clamped to `limits`
+    fn integral_driving(limits: Option<(f64, f64)>) -> f64 {
+        let (status_tx, status_rx) = channel::unbounded();
+        let mut actuator = crate::Actuator::new("force".to_string(), SensorType::Force, quiet_log()).with_status(status_tx);
+        if let Some((min, max)) = limits {
+            actuator = actuator.with_limits(min, max);
+        }
+        let (command_tx, command_rx) = channel::unbounded();
+        let (feedback_tx, _feedback_rx) = channel::unbounded();
+        let acting = thread::spawn(move || actuator.run(command_rx, feedback_tx));
+
+        let mut commander = commander();
+        for id in 0..100 {
+            let command = commander.process_sample(sample(SensorType::Force, id, 0.0, false), Instant::now()).expect("no command");
+            command_tx.send(command).unwrap();
+            // The actuator only reports changes, so the first command is the one to wait for
+            if id == 0 && limits.is_some() {
+                let status = status_rx.recv_timeout(Duration::from_secs(5)).expect("no saturation report");
+                commander.handle_actuator_status(status);
+            }
+        }
+        drop(command_tx);
+        acting.join().unwrap();
+        commander.normal_pids[&SensorType::Force].integral
+    }
+
+    #[test]
+    fn saturating_actuator_stops_integral_windup() {
+        // 30 * 0.005 per sample
+        let free = integral_driving(None);
+        assert!((free - 15.0).abs() < 1e-6, "{}", free);
+        let clamped = integral_driving(Some((-5.0, 5.0)));
+        assert!((clamped - 0.15).abs() < 1e-6, "{}", clamped);
+    }
+
+    #[test]
+    fn closing_one_sensor_channel_keeps_the_others_served() {
+        let log = quiet_log();
+        let recorder = Recorder::new();
+        let commander = ActuatorCommander::new(HashMap::new(), HashMap::new(), log.clone()).with_recorder(Some(recorder.clone()));
+        let (tx_force, rx_force) = channel::unbounded::<SensorData>();
+        let (tx_pos, rx_pos) = channel::unbounded();
+        let (tx_temp, rx_temp) = channel::unbounded();
+        let running = thread::spawn(move || commander.run(rx_force, rx_pos, rx_temp));
+
+        drop(tx_force);
+        thread::sleep(Duration::from_millis(50)); // Let the commander see the disconnect first
+        for id in 0..3 {
+            tx_pos.send(sample(SensorType::Position, id, 0.0, false)).unwrap();
+            tx_temp.send(sample(SensorType::Temperature, id, 25.0, false)).unwrap();
+        }
+        drop((tx_pos, tx_temp));
+        running.join().unwrap();
+
+        let handled = handled_order(&recorder);
+        assert_eq!(handled.iter().filter(|&&s| s == SensorType::Position).count(), 3);
+        assert_eq!(handled.iter().filter(|&&s| s == SensorType::Temperature).count(), 3);
+        assert!(log.lock().unwrap().recent_entries().iter().any(|l| l.contains("disconnected, serving the remaining sensors")));
+    }
+
+    #[test]
+    fn rate_gate_catches_alternating_anomalies_the_streak_misses() {
+        let degraded_after_alternating = |gate| {
+            let mut commander = commander().with_anomaly_rate_gate(gate);
+            for id in 0..40 {
+                commander.fail_safe(sample(SensorType::Force, id, 30.0, id % 2 == 0));
+            }
+            commander.mode_transitions().iter().any(|t| t.to == SystemMode::Degraded)
+        };
+        assert!(!degraded_after_alternating(None)); // Every clean sample pays the streak back
+        assert!(degraded_after_alternating(Some(AnomalyRateGate::default())));
+    }
+
+    #[test]
+    fn time_in_mode_splits_the_run_by_dwell_time() {
+        let mut commander = commander().with_anomaly_rate_gate(None);
+        thread::sleep(Duration::from_millis(60));
+        for id in 0..3 {
+            commander.fail_safe(sample(SensorType::Force, id, 999.0, true)); // Third one degrades
+        }
+        thread::sleep(Duration::from_millis(60));
+        for id in 3..6 {
+            commander.fail_safe(sample(SensorType::Force, id, 30.0, false)); // Third one recovers
+        }
+        commander.close_mode_dwell();
+
+        let modes = commander.benchmark_stats.time_in_mode;
+        assert!((modes.percent(SystemMode::Normal) - 50.0).abs() < 10.0, "{:?}", modes);
+        assert!((modes.percent(SystemMode::Degraded) - 50.0).abs() < 10.0, "{:?}", modes);
+        assert_eq!(modes.emergency_stop, Duration::ZERO);
+    }
+
+    #[test]
+    fn degraded_mode_uses_the_degraded_gains() {
+        // Proportional only, so the effort is the gain times the error of 10
+        let mut commander = commander()
+            .with_anomaly_rate_gate(None)
+            .with_gains(SensorType::Force, 1.0, 0.0, 0.0)
+            .with_degraded_gains(SensorType::Force, 5.0, 0.0, 0.0);
+        let effort = |commander: &mut ActuatorCommander, id| {
+            commander.process_sample(sample(SensorType::Force, id, 20.0, false), Instant::now()).unwrap().value
+        };
+
+        assert!((effort(&mut commander, 0) - 10.0).abs() < 1e-9);
+        for id in 1..4 {
+            commander.fail_safe(sample(SensorType::Force, id, 999.0, true));
+        }
+        // The streak of 3 is paid back one clean sample at a time, so Degraded lasts two more samples
+        assert!((effort(&mut commander, 4) - 50.0).abs() < 1e-9);
+        assert!((effort(&mut commander, 5) - 50.0).abs() < 1e-9);
+        assert!((effort(&mut commander, 6) - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn slow_handler_counts_commander_overruns() {
+        let log = quiet_log();
+        let mut slow = InvariantChecker::new();
+        slow.add("slow", Box::new(|_| { thread::sleep(Duration::from_millis(5)); Ok(()) }));
+        let mut commander = ActuatorCommander::new(HashMap::new(), HashMap::new(), log.clone()).with_invariants(slow);
+        for id in 0..3 {
+            commander.handle_sensor_data(sample(SensorType::Force, id, 30.0, false));
+        }
+
+        assert_eq!(commander.benchmark_stats.commander_overruns, 3); // Against the default budget of 1ms
+        assert!(log.lock().unwrap().recent_entries().iter().any(|l| l.contains("Commander overran its budget")));
+    }
+
+    #[test]
+    fn dead_actuator_is_marked_faulted_and_the_others_still_commanded() {
+        let log = quiet_log();
+        let (force_tx, force_rx) = channel::unbounded();
+        let (pos_tx, pos_rx) = channel::unbounded();
+        drop(force_rx); // The Force actuator thread has died
+        let actuators = HashMap::from([(SensorType::Force, force_tx), (SensorType::Position, pos_tx)]);
+        let mut commander = ActuatorCommander::new(actuators, HashMap::new(), log.clone());
+        for id in 0..3 {
+            commander.handle_sensor_data(sample(SensorType::Force, id, 20.0 + id as f64, false));
+            commander.handle_sensor_data(sample(SensorType::Position, id, 0.1 * id as f64, false));
+        }
+
+        assert!(commander.benchmark_stats.faulted_actuators.contains(SensorType::Force));
+        assert!(!commander.benchmark_stats.faulted_actuators.contains(SensorType::Position));
+        assert_eq!(pos_rx.try_iter().count(), 3);
+        let faults = log.lock().unwrap().recent_entries().iter().filter(|l| l.contains("Force actuator faulted")).count();
+        assert_eq!(faults, 1); // Logged once, later commands are dropped quietly
+    }
+
+    #[test]
+    fn diverged_controller_latches_emergency_stop() {
+        let mut commander = commander().with_gains(SensorType::Force, 1e9, 0.0, 0.0);
+        assert!(commander.process_sample(sample(SensorType::Force, 0, 20.0, false), Instant::now()).is_none());
+        assert_eq!(commander.system_mode, SystemMode::EmergencyStop);
+    }
+
+    #[test]
+    fn constant_input_stops_producing_commands_once_settled() {
+        let (tx, rx) = channel::unbounded();
+        let mut commander = ActuatorCommander::new(HashMap::from([(SensorType::Temperature, tx)]), HashMap::new(), quiet_log())
+            .with_deadband(SensorType::Temperature, 0.5);
+        let feed = |commander: &mut ActuatorCommander, ids: std::ops::Range<i32>| {
+            for id in ids {
+                commander.handle_sensor_data(sample(SensorType::Temperature, id, 235.0, false));
+            }
+        };
+
+        feed(&mut commander, 0..10); // The derivative kick of the first sample settles here
+        let settling = rx.try_iter().count();
+        assert!(settling >= 1);
+        feed(&mut commander, 10..50);
+        assert_eq!(rx.try_iter().count(), 0);
+        assert_eq!(commander.benchmark_stats.suppressed_commands as usize, 50 - settling);
+    }
+
+    #[test]
+    fn commands_are_held_while_the_actuator_is_down() {
+        let dead_letters = DeadLetterLog::with_capacity(8);
+        let (commander, actuators, _feedback) = wired(None);
+        let mut commander = commander.with_dead_letters(dead_letters.clone());
+        let force = &actuators[SensorType::all().iter().position(|&t| t == SensorType::Force).unwrap()];
+
+        commander.handle_actuator_status(ActuatorStatus::HardwareFailure { sensor_type: SensorType::Force, msg: "test".to_string() });
+        commander.handle_sensor_data(sample(SensorType::Force, 1, 10.0, false));
+        assert!(force.try_recv().is_err());
+        assert_eq!(dead_letters.counts().get(&DropReason::ActuatorDown), Some(&1));
+
+        commander.handle_actuator_status(ActuatorStatus::Recovered { sensor_type: SensorType::Force });
+        commander.handle_sensor_data(sample(SensorType::Force, 2, 10.0, false));
+        assert_eq!(force.try_recv().map(|data| data.id), Ok(2));
+        assert!(commander.log.lock().unwrap().recent_entries().iter().any(|l| l.contains("recovered, resuming commands")));
+    }
+
+    #[test]
+    fn parallel_commanders_match_the_serial_one() {
+        // Clean samples from every sensor, then a Force streak long enough to degrade the system
+        let run = |parallel: bool| {
+            let commander = commander().with_anomaly_rate_gate(None);
+            let monitor = commander.snapshot_handle();
+            let (tx_force, rx_force) = channel::unbounded();
+            let (tx_pos, rx_pos) = channel::unbounded();
+            let (tx_temp, rx_temp) = channel::unbounded();
+            for id in 0..5 {
+                tx_force.send(sample(SensorType::Force, id, 30.0, false)).unwrap();
+                tx_pos.send(sample(SensorType::Position, id, 0.0, false)).unwrap();
+                tx_temp.send(sample(SensorType::Temperature, id, 25.0, false)).unwrap();
+            }
+            for id in 5..8 {
+                tx_force.send(sample(SensorType::Force, id, 999.0, true)).unwrap();
+            }
+            drop((tx_force, tx_pos, tx_temp));
+
+            if parallel { commander.run_parallel(rx_force, rx_pos, rx_temp); } else { commander.run(rx_force, rx_pos, rx_temp); }
+            let snapshot = monitor.snapshot();
+            (snapshot.samples_processed, snapshot.mode)
+        };
+
+        let serial = run(false);
+        assert_eq!(serial, (18, SystemMode::Degraded));
+        assert_eq!(run(true), serial);
+    }
+
+    #[test]
+    fn invalid_samples_never_reach_the_controller() {
+        let dead_letters = DeadLetterLog::with_capacity(8);
+        let (commander, actuators, _feedback) = wired(None);
+        let mut commander = commander.with_dead_letters(dead_letters.clone());
+        commander.handle_sensor_data(sample(SensorType::Force, 1, f64::NAN, false));
+        commander.handle_sensor_data(sample(SensorType::Force, -1, 10.0, false));
+        let mut future = sample(SensorType::Force, 2, 10.0, false);
+        future.capture_time = Some(Instant::now() + Duration::from_secs(1));
+        commander.handle_sensor_data(future);
+
+        assert_eq!(commander.benchmark_stats.invalid_samples, 3);
+        assert!(actuators.iter().all(|rx| rx.try_recv().is_err()));
+        let counts = dead_letters.counts();
+        assert_eq!((counts.get(&DropReason::NonFinite), counts.get(&DropReason::Invalid)), (Some(&1), Some(&2)));
+    }
+
+    #[test]
+    fn actuator_retries_run_out_only_after_max_retries_failures() {
+        // Each failure is followed by a command once the backoff is over, which is the retry
+        let run = |failures: u32| {
+            let (commander, actuators, _feedback) = wired(None);
+            let mut commander = commander
+                .with_anomaly_rate_gate(None)
+                .with_actuator_retry(Some(ActuatorRetry { max_retries: 2, backoff: Duration::from_millis(1) }));
+            let force = &actuators[SensorType::all().iter().position(|&t| t == SensorType::Force).unwrap()];
+            let mut delivered = 0;
+            for id in 0..failures as i32 {
+                commander.handle_actuator_status(ActuatorStatus::HardwareFailure { sensor_type: SensorType::Force, msg: "test".to_string() });
+                thread::sleep(Duration::from_millis(10)); // Past the longest backoff of 2ms
+                commander.handle_sensor_data(sample(SensorType::Force, id, 10.0, false));
+                delivered += force.try_iter().count();
+            }
+            (commander.system_mode, delivered, commander.lost_actuators.contains(SensorType::Force))
+        };
+
+        // Two failures are retried and the retried commands get through
+        assert_eq!(run(2), (SystemMode::Normal, 2, false));
+        // The third is one more than the retries allow
+        assert_eq!(run(3), (SystemMode::Degraded, 2, true));
+    }
+
+    #[test]
+    fn health_report_reflects_an_anomaly_burst() {
+        let mut commander = commander().with_anomaly_rate_gate(None);
+        let monitor = commander.snapshot_handle();
+        let healthy = monitor.health();
+        assert_eq!((healthy.mode, healthy.recent_anomalies), (SystemMode::Normal, 0));
+        assert!(healthy.last_seen.is_empty() && !healthy.missed_deadlines());
+
+        for id in 0..3 {
+            commander.handle_sensor_data(sample(SensorType::Force, id, 999.0, true));
+        }
+        monitor.record_deadline_miss();
+        let burst = monitor.health();
+        assert_eq!((burst.mode, burst.recent_anomalies), (SystemMode::Degraded, 3));
+        assert!(burst.last_seen.get(&SensorType::Force).is_some_and(|age| *age < Duration::from_secs(1)));
+        assert!(!burst.last_seen.contains_key(&SensorType::Position));
+        assert!(burst.missed_deadlines());
+    }
+}
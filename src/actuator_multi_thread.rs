@@ -1,18 +1,29 @@
-use std::ops::Deref;
 use std::sync::{Arc, Mutex};
-use crossbeam::channel::{Receiver, Sender};
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender, TrySendError};
 use std::thread;
 use std::time::{Duration, Instant};
 use rand::Rng;
-use crate::share::{BenchmarkStats, Feedback, SensorData,SensorType, SystemLog};
+use crate::share::{ActuatorStatus, BenchmarkStats, ComponentId, CycleTimeline, DeadlineHooks, DeadlinePolicy, Deadlines, FailureSchedule, Feedback, InfluxSink, LogLevel, Plant, SensorData, SensorType, ShutdownReason, Stage, SystemLog, Verbosity};
 
 pub struct Actuator{
-    name: String,
+    id: ComponentId,
     sensor_type: SensorType,
     operation_deadline:Duration,
+    operation_time: Duration,
+    e2e_deadline: Duration,
+    expected_interval: Duration, // Command period the jitter is measured against, the sensor cycle
+    deadline_policy: DeadlinePolicy,
     log:Arc<Mutex<SystemLog>>,
     benchmark_stats: BenchmarkStats,
     last_arrival_time: Option<Instant>,
+    deadline_hooks: DeadlineHooks,
+    limits: Option<(f64, f64)>,                 // (min, max) effort the hardware can apply
+    saturated: bool,
+    tx_status: Option<Sender<ActuatorStatus>>, // Saturation changes, for the commander's anti-windup
+    influx: Option<InfluxSink>,
+    failure: Option<FailureSchedule>,
+    failed: bool,
+    plant: Option<Plant>,
 }
 
 impl Actuator{
@@ -25,7 +36,109 @@ impl Actuator{
             SensorType::Temperature => Duration::from_micros(2000),
         };
 
-        Self{name, sensor_type, operation_deadline: deadline, log, benchmark_stats: BenchmarkStats::new(), last_arrival_time:None}
+        let id = ComponentId { sensor_type, index: 0, name };
+        Self{id, sensor_type, operation_deadline: deadline, operation_time: Duration::from_micros(100), e2e_deadline: Duration::from_millis(5), expected_interval: Duration::from_millis(5), deadline_policy: DeadlinePolicy::MarkAndContinue, log, benchmark_stats: BenchmarkStats::new(), last_arrival_time:None, deadline_hooks: DeadlineHooks::default(), limits: None, saturated: false, tx_status: None, influx: None, failure: None, failed: false, plant: None}
+    }
+
+    // Use the identity handed out by the ComponentRegistry
+    pub fn with_id(mut self, id: ComponentId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn with_deadlines(mut self, deadlines: Deadlines) -> Self {
+        self.operation_deadline = deadlines.actuation;
+        self.operation_time = deadlines.actuation_work;
+        self.e2e_deadline = deadlines.end_to_end;
+        self.expected_interval = deadlines.sensor_cycle;
+        self.deadline_policy = deadlines.policies.actuation;
+        self
+    }
+
+    pub fn with_deadline_hooks(mut self, hooks: DeadlineHooks) -> Self {
+        self.deadline_hooks = hooks;
+        self
+    }
+
+    // Clamp every command to [min, max]
+    pub fn with_limits(mut self, min: f64, max: f64) -> Self {
+        self.limits = Some((min, max));
+        self
+    }
+
+    // Report an `ActuatorStatus` whenever the actuator enters or leaves saturation
+    pub fn with_status(mut self, tx_status: Sender<ActuatorStatus>) -> Self {
+        self.tx_status = Some(tx_status);
+        self
+    }
+
+    // Fail once on this schedule, measured from the start of `run`; the commander
+    // hears about both ends of the outage over the status channel
+    pub fn with_failure_schedule(mut self, failure: Option<FailureSchedule>) -> Self {
+        self.failure = failure;
+        self
+    }
+
+    // Drive this plant with every applied effort; it gets none while the hardware is down
+    pub fn with_plant(mut self, plant: Option<Plant>) -> Self {
+        self.plant = plant;
+        self
+    }
+
+    // End-to-end latency of every command, as InfluxDB lines
+    pub fn with_influx(mut self, influx: Option<InfluxSink>) -> Self {
+        self.influx = influx;
+        self
+    }
+
+    fn saturate(&mut self, effort: f64) -> f64 {
+        let Some((min, max)) = self.limits else { return effort };
+        let applied = effort.clamp(min, max);
+        let saturated = applied != effort;
+        if saturated {
+            self.benchmark_stats.actuator_saturations += 1;
+        }
+
+        if saturated != self.saturated {
+            self.saturated = saturated;
+            if let Some(tx) = &self.tx_status {
+                let _ = tx.send(ActuatorStatus::ActionComplete { sensor_type: self.sensor_type, effort: applied, saturated });
+            }
+        }
+        applied
+    }
+
+    // When the failure schedule next changes state, None once it has run its course
+    fn next_transition(&self, started: Instant) -> Option<Instant> {
+        let failure = self.failure?;
+        let fail_at = started + failure.fail_at;
+        if self.failed {
+            Some(fail_at + failure.recover_after)
+        } else if Instant::now() < fail_at {
+            Some(fail_at)
+        } else {
+            None
+        }
+    }
+
+    // Enter or leave the failure window and tell the commander
+    fn update_failure(&mut self, started: Instant) {
+        let Some(failure) = self.failure else { return };
+        let failed = failure.is_failed(started.elapsed());
+        if failed == self.failed { return; }
+        self.failed = failed;
+        if let Some(plant) = &self.plant {
+            plant.apply(0.0);
+        }
+
+        let status = if failed {
+            ActuatorStatus::HardwareFailure { sensor_type: self.sensor_type, msg: format!("{} down for {:?}", self.id, failure.recover_after) }
+        } else {
+            ActuatorStatus::Recovered { sensor_type: self.sensor_type }
+        };
+        if let Some(tx) = &self.tx_status {
+            let _ = tx.send(status);
+        }
     }
 
     fn update_jitter(&mut self) {
@@ -36,15 +149,10 @@ impl Actuator{
         if let Some(last_time) = self.last_arrival_time{
             let interval = current_time.duration_since(last_time);
 
-            // Define expected rate (Consider making this a constant or struct field later)
-            let expected_interval = Duration::from_millis(5);
+            let expected_interval = self.expected_interval;
 
             // Calculate absolute difference (Jitter)
-            let jitter = if interval > expected_interval {
-                interval - expected_interval
-            } else {
-                expected_interval - interval
-            };
+            let jitter = interval.abs_diff(expected_interval);
 
             // Update Stats
             self.benchmark_stats.total_at_jitter += jitter;
@@ -55,7 +163,7 @@ impl Actuator{
         self.last_arrival_time = Some(current_time);
     }
 
-    fn generate_feedback(&self) -> Feedback {
+    fn generate_feedback(&self, origin: &SensorData) -> Feedback {
         let mut rng = rand::rng();
 
         // Roll dice (0.0 to 1.0)
@@ -68,14 +176,21 @@ impl Actuator{
                 error_msg: "no".to_string(),
                 recalibrate_offset: 0.0,
                 timestamp: Instant::now(),
+                origin: Some((origin.id, origin.captured_at())),
             }
         } else {
             // 5% Chance: Randomly request a sensor adjustment
-            let random_offset = rng.random_range(-0.5..0.5);
+            // Kept small against the sensor's range so a random drift alone
+            // does not walk the readings out of their anomaly band
+            let max_offset = match self.sensor_type {
+                SensorType::Position => 0.01,
+                SensorType::Force | SensorType::Temperature => 0.5,
+            };
+            let random_offset = rng.random_range(-max_offset..max_offset);
 
             // Log this command so you can trace it in the report
             if let Ok(mut guard) = self.log.lock() {
-                guard.write(format!("[Command] Actuator [{}] req offset: {:.4}", self.name, random_offset));
+                guard.write(format!("[Command] Actuator [{}] req offset: {:.4}", self.id, random_offset));
             }
 
             Feedback {
@@ -83,52 +198,226 @@ impl Actuator{
                 error_msg: "Random Drift Check".to_string(),
                 recalibrate_offset: random_offset,
                 timestamp: Instant::now(),
+                origin: Some((origin.id, origin.captured_at())),
             }
         }
     }
 
     pub fn run(&mut self, sensor_data:Receiver<SensorData>,tx_status: Sender<Feedback>,
     )-> BenchmarkStats{
+        self.benchmark_stats.e2e_deadline = self.e2e_deadline;
 
-        // 1. Receive Value from commander
-        while let Ok(data) = sensor_data.recv() {
+        let started = Instant::now();
+        loop {
+            // 1. Receive Value from commander, waking up for the next failure transition
+            let received = match self.next_transition(started) {
+                Some(at) => sensor_data.recv_deadline(at),
+                None => sensor_data.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            self.update_failure(started);
+            let mut data = match received {
+                Ok(data) => data,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            // Commands already in flight when the hardware failed are lost; any
+            // later one is answered with another failure report
+            if self.failed {
+                if let Some(tx) = &self.tx_status {
+                    let _ = tx.send(ActuatorStatus::HardwareFailure { sensor_type: self.sensor_type, msg: format!("{} still down", self.id) });
+                }
+                continue;
+            }
 
             self.update_jitter();
+            data.value = self.saturate(data.value);
 
             // 2. Start to record processing time
             let start = Instant::now();
 
             // 3. Simulate Actuation
-            println!("Actuator [{}] adjusting to effort {:.2}", self.name, data.value);
-            thread::sleep(Duration::from_micros(100));
+            if let Ok(mut guard) = self.log.lock() {
+                guard.print(Verbosity::Verbose, format!("Actuator [{}] adjusting to effort {:.2}", self.id, data.value));
+            }
+            thread::sleep(self.operation_time);
+            if let Some(plant) = &self.plant {
+                plant.apply(data.value);
+            }
 
             // 4. Check deadline for the
-            let operation_duration = start.elapsed();
-            if operation_duration > self.operation_deadline {
+            let actuated = Instant::now();
+            let operation_duration = actuated - start;
+            let missed = operation_duration > self.operation_deadline;
+            if missed {
                 if let Ok(mut log_guard) = self.log.lock() {
-                    log_guard.write(format!("[Deadline] Actuator [{}] missed deadline by {:?} ms", self.name, operation_duration-self.operation_deadline));
+                    log_guard.write_level(LogLevel::Warn, format!("[Deadline] Actuator [{}] missed deadline by {:?} ms", self.id, operation_duration-self.operation_deadline));
                 }
                 self.benchmark_stats.actuator_missed_deadlines += 1;
+                self.deadline_hooks.notify(Stage::Actuation, self.sensor_type, operation_duration - self.operation_deadline);
             }
 
             // 5. Generate feedback
-            let feedback = self.generate_feedback();
+            let feedback = self.generate_feedback(&data);
 
-            // 6. Send feedback
-            if feedback.recalibrate_offset != 0.0 {
-                let _ = tx_status.send(feedback);
+            // 6. Send feedback (a late actuation under the Drop policy sends none)
+            let dropped = missed && self.deadline_policy == DeadlinePolicy::Drop;
+            if feedback.recalibrate_offset != 0.0 && !dropped {
+                // Never block the actuator on a slow sensor; count what does not get through
+                if let Err(err) = tx_status.try_send(feedback) {
+                    self.benchmark_stats.feedback_drops += 1;
+                    if self.benchmark_stats.feedback_drops == 1 {
+                        let cause = match err {
+                            TrySendError::Full(_) => "channel full",
+                            TrySendError::Disconnected(_) => "sensor gone",
+                        };
+                        if let Ok(mut log_guard) = self.log.lock() {
+                            log_guard.write_level(LogLevel::Warn, format!("[Feedback] Actuator [{}] could not send feedback ({}); further drops are only counted", self.id, cause));
+                        }
+                    }
+                }
             }
             
             let duration = start.elapsed();
             let now = Instant::now();
             self.benchmark_stats.total_actuator_time += duration;
-            let e2e_latency = now.duration_since(data.timestamp);
+            let e2e_latency = data.age_since(now);
+            self.benchmark_stats.record_cycle(CycleTimeline::of(&data, actuated, now));
 
             self.benchmark_stats.total_latency += e2e_latency;
+            self.benchmark_stats.latency_histogram.record(e2e_latency);
+            if let Some(influx) = &self.influx {
+                influx.latency("e2e", e2e_latency);
+            }
+            if e2e_latency > self.e2e_deadline {
+                self.benchmark_stats.e2e_deadline_misses += 1;
+                if let Ok(mut log_guard) = self.log.lock() {
+                    log_guard.write_level(LogLevel::Warn, format!("[Deadline] Actuator [{}] end-to-end latency {:?} for sample {} (Limit: {:?})", self.id, e2e_latency, data.id, self.e2e_deadline));
+                }
+            }
             self.benchmark_stats.actuator_count += 1;
         }
+
+        // The commander hung up: this is how every actuator stops
+        if let Ok(mut log) = self.log.lock() {
+            log.write(format!("[Shutdown] Actuator [{}] stopped: {:?}", self.id, ShutdownReason::ChannelDisconnected));
+        }
         self.benchmark_stats
     }
 
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet_log() -> Arc<Mutex<SystemLog>> {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(Verbosity::Silent);
+        Arc::new(Mutex::new(log))
+    }
+
+    fn command(id: i32) -> SensorData {
+        SensorData {
+            id,
+            sensor_type: SensorType::Force,
+            value: 1.0,
+            anomaly: false,
+            timestamp: Instant::now(),
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: Default::default(),
+        }
+    }
+
+    #[test]
+    fn feedback_to_a_dropped_receiver_is_counted() {
+        let log = quiet_log();
+        let mut actuator = Actuator::new("test".to_string(), SensorType::Force, log.clone());
+        let (tx_data, rx_data) = crossbeam::channel::unbounded();
+        let (tx_feedback, rx_feedback) = crossbeam::channel::bounded(1);
+        drop(rx_feedback); // The sensor is gone
+        for id in 0..200 {
+            tx_data.send(command(id)).unwrap();
+        }
+        drop(tx_data);
+        let stats = actuator.run(rx_data, tx_feedback);
+
+        // Every drift check the actuator asked for was lost, and only the first one is logged
+        let lines = log.lock().unwrap().recent_entries();
+        let requested = lines.iter().filter(|l| l.contains("req offset")).count() as u32;
+        assert!(requested > 0, "200 commands without a single drift check");
+        assert_eq!(stats.feedback_drops, requested);
+        assert_eq!(lines.iter().filter(|l| l.contains("could not send feedback (sensor gone)")).count(), 1);
+    }
+
+    // End-to-end misses over five commands, each taking `work` to actuate
+    fn e2e_misses_with(work: Duration) -> (u32, f64) {
+        let deadlines = Deadlines { actuation_work: work, actuation: Duration::from_secs(1), end_to_end: Duration::from_millis(5), ..Deadlines::default() };
+        let mut actuator = Actuator::new("test".to_string(), SensorType::Force, quiet_log()).with_deadlines(deadlines);
+        let (tx_data, rx_data) = crossbeam::channel::unbounded();
+        let (tx_feedback, _rx_feedback) = crossbeam::channel::unbounded();
+        let acting = thread::spawn(move || actuator.run(rx_data, tx_feedback));
+        for id in 0..5 {
+            tx_data.send(command(id)).unwrap(); // Stamped now, so its age is the actuation alone
+            thread::sleep(Duration::from_millis(15)); // Never queued behind the previous one
+        }
+        drop(tx_data);
+        let stats = acting.join().unwrap();
+        (stats.e2e_deadline_misses, stats.e2e_deadline_rate())
+    }
+
+    #[test]
+    fn slow_actuation_misses_the_end_to_end_deadline() {
+        assert_eq!(e2e_misses_with(Duration::from_millis(10)), (5, 100.0));
+        assert_eq!(e2e_misses_with(Duration::ZERO), (0, 0.0));
+    }
+
+    #[test]
+    fn jitter_is_measured_against_the_sensor_cycle() {
+        let mean_jitter = |actuator: &mut Actuator| {
+            for _ in 0..10 {
+                actuator.update_jitter();
+                thread::sleep(Duration::from_millis(20)); // Commands arrive every 20ms
+            }
+            actuator.benchmark_stats.total_at_jitter / 9
+        };
+        let deadlines = Deadlines { sensor_cycle: Duration::from_millis(20), ..Deadlines::default() };
+        let mut matched = Actuator::new("test".to_string(), SensorType::Force, quiet_log()).with_deadlines(deadlines);
+        let mut fixed = Actuator::new("test".to_string(), SensorType::Force, quiet_log()); // Still expects 5ms
+
+        let (matched, fixed) = (mean_jitter(&mut matched), mean_jitter(&mut fixed));
+        assert!(matched < Duration::from_millis(5), "{:?}", matched);
+        assert!(fixed > Duration::from_millis(10), "{:?}", fixed);
+    }
+
+    #[test]
+    fn commands_inside_the_failure_window_are_not_applied() {
+        let failure = FailureSchedule { fail_at: Duration::from_millis(60), recover_after: Duration::from_millis(120) };
+        let (tx_status, rx_status) = crossbeam::channel::unbounded();
+        let mut actuator = Actuator::new("test".to_string(), SensorType::Force, quiet_log())
+            .with_status(tx_status)
+            .with_failure_schedule(Some(failure));
+        let (tx_data, rx_data) = crossbeam::channel::unbounded();
+        let (tx_feedback, _rx_feedback) = crossbeam::channel::unbounded();
+        let acting = thread::spawn(move || actuator.run(rx_data, tx_feedback));
+
+        // Sent at 0, 120 and 240ms: before, in the middle of and well after the 60-180ms outage
+        tx_data.send(command(0)).unwrap();
+        for id in 1..3 {
+            thread::sleep(Duration::from_millis(120));
+            tx_data.send(command(id)).unwrap();
+        }
+        drop(tx_data);
+        let stats = acting.join().unwrap();
+        assert_eq!(stats.actuator_count, 2);
+
+        let statuses: Vec<&str> = rx_status.try_iter().map(|status| match status {
+            ActuatorStatus::HardwareFailure { msg, .. } if msg.contains("still down") => "still down",
+            ActuatorStatus::HardwareFailure { .. } => "failed",
+            ActuatorStatus::Recovered { .. } => "recovered",
+            ActuatorStatus::ActionComplete { .. } => "complete",
+        }).collect();
+        assert_eq!(statuses, ["failed", "still down", "recovered"]);
+    }
+}
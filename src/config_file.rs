@@ -0,0 +1,394 @@
+// JSON form of `SimulationConfig`, durations in milliseconds:
+//
+// {
+//   "duration_ms": 5000,          // or "samples": 10000 to stop after that many samples
+//   "fault_rates": { "drop_rate": 0.0, "latency_rate": 0.0 },
+//   "gains": { "Force": [1.5, 0.1, 0.05] },
+//   "setpoints": { "Force": { "breakpoints": [[0, 30.0], [1000, 45.0]], "interpolation": "Step" } },
+//   "degraded_gains": { "Force": [0.75, 0.0, 0.05] }
+// }
+//
+// Every field is optional and defaults to `SimulationConfig::default()`.
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::share::{ActuatorRetry, BackpressurePolicy, ConfigError, DeadlinePolicies, Deadlines, FailureSchedule, FaultRates, FaultSchedule, Interpolation, LogLevel, PlantModel, SensorType, SetpointSchedule, SimulationConfig, StageFlags, StopCondition, TransmissionFault, Verbosity};
+
+fn ms(d: Duration) -> f64 { d.as_nanos() as f64 / 1e6 }
+fn from_ms(ms: f64) -> Duration { Duration::from_secs_f64(ms.max(0.0) / 1000.0) }
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DeadlinesFile {
+    sensor_cycle_ms: f64,
+    processing_ms: f64,
+    transmission_ms: HashMap<SensorType, f64>,
+    feedback_ms: f64,
+    actuation_ms: f64,
+    actuation_work_ms: f64,
+    feedback_starvation_ms: f64,
+    end_to_end_ms: f64,
+    commander_budget_ms: f64,
+    policies: DeadlinePolicies,
+}
+
+impl Default for DeadlinesFile {
+    fn default() -> Self { Self::from(&Deadlines::default()) }
+}
+
+impl From<&Deadlines> for DeadlinesFile {
+    fn from(d: &Deadlines) -> Self {
+        let transmission_ms = SensorType::all()
+            .iter().copied().map(|s| (s, ms(d.transmission.get(s)))).collect();
+        Self {
+            sensor_cycle_ms: ms(d.sensor_cycle),
+            processing_ms: ms(d.processing),
+            transmission_ms,
+            feedback_ms: ms(d.feedback),
+            actuation_ms: ms(d.actuation),
+            actuation_work_ms: ms(d.actuation_work),
+            feedback_starvation_ms: ms(d.feedback_starvation),
+            end_to_end_ms: ms(d.end_to_end),
+            commander_budget_ms: ms(d.commander_budget),
+            policies: d.policies,
+        }
+    }
+}
+
+impl DeadlinesFile {
+    fn into_deadlines(self) -> Deadlines {
+        let mut transmission = Deadlines::default().transmission;
+        for (s_type, limit) in self.transmission_ms {
+            *transmission.get_mut(s_type) = from_ms(limit);
+        }
+        Deadlines {
+            sensor_cycle: from_ms(self.sensor_cycle_ms),
+            processing: from_ms(self.processing_ms),
+            transmission,
+            feedback: from_ms(self.feedback_ms),
+            actuation: from_ms(self.actuation_ms),
+            actuation_work: from_ms(self.actuation_work_ms),
+            feedback_starvation: from_ms(self.feedback_starvation_ms),
+            end_to_end: from_ms(self.end_to_end_ms),
+            commander_budget: from_ms(self.commander_budget_ms),
+            policies: self.policies,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FaultRatesFile {
+    drop_rate: f64,
+    latency_rate: f64,
+    latency_ms: f64,
+}
+
+impl Default for FaultRatesFile {
+    fn default() -> Self {
+        let rates = FaultRates::default();
+        Self { drop_rate: rates.drop_rate, latency_rate: rates.latency_rate, latency_ms: ms(rates.latency) }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SetpointFile {
+    breakpoints: Vec<(f64, f64)>, // (time_ms, value)
+    #[serde(default = "step")]
+    interpolation: Interpolation,
+}
+
+fn step() -> Interpolation { Interpolation::Step }
+
+// One scheduled transmission fault; without `delay_ms` the sample is dropped
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduledFaultFile {
+    sensor: SensorType,
+    id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delay_ms: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PlantFile {
+    gain: f64,
+    time_constant_ms: f64,
+    initial: f64,
+    noise: f64,
+    disturbances: Vec<(f64, f64)>, // (time_ms, disturbance)
+}
+
+impl Default for PlantFile {
+    fn default() -> Self { Self::from(&PlantModel::default()) }
+}
+
+impl From<&PlantModel> for PlantFile {
+    fn from(p: &PlantModel) -> Self {
+        Self {
+            gain: p.gain,
+            time_constant_ms: ms(p.time_constant),
+            initial: p.initial,
+            noise: p.noise,
+            disturbances: p.disturbances.iter().map(|(t, d)| (ms(*t), *d)).collect(),
+        }
+    }
+}
+
+impl PlantFile {
+    fn into_model(self) -> PlantModel {
+        let model = PlantModel { gain: self.gain, time_constant: from_ms(self.time_constant_ms), initial: self.initial, noise: self.noise, disturbances: Vec::new() };
+        self.disturbances.into_iter().fold(model, |model, (t, d)| model.with_disturbance(from_ms(t), d))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    duration_ms: f64,
+    samples: Option<u64>, // Overrides duration_ms
+    time_scale: f64,
+    live_log: bool,
+    min_log_level: LogLevel,
+    verbosity: Verbosity,
+    heartbeat: bool,
+    recalibration_decay_ms: Option<f64>,
+    commander_tick_ms: f64,
+    pid_trace_output: Option<PathBuf>,
+    calibration_file: Option<PathBuf>,
+    invariant_effort_limit: Option<f64>,
+    influx_addr: Option<SocketAddr>,
+    worker_threads: Option<usize>,
+    anomaly_confirm: usize,
+    stages: HashMap<SensorType, StageFlags>,
+    record: bool,
+    shutdown_grace_ms: f64,
+    deadlines: DeadlinesFile,
+    fault_rates: FaultRatesFile,
+    fault_schedule: Vec<ScheduledFaultFile>,
+    gains: HashMap<SensorType, (f64, f64, f64)>,
+    actuator_limits: HashMap<SensorType, (f64, f64)>,
+    failure_schedules: HashMap<SensorType, (f64, f64)>, // (fail_at_ms, recover_after_ms)
+    actuator_retry: Option<(u32, f64)>, // (max_retries, backoff_ms)
+    plants: HashMap<SensorType, PlantFile>,
+    deadbands: HashMap<SensorType, f64>,
+    sensor_priority: Vec<SensorType>,
+    drift_rates: HashMap<SensorType, f64>,
+    drift_correction: Option<usize>,
+    batch_size: usize,
+    parallel_commanders: bool,
+    soak_check_interval_ms: Option<f64>,
+    settling_band: f64,
+    sensor_channel_capacity: Option<usize>,
+    backpressure: BackpressurePolicy,
+    maintain_decay: f64,
+    setpoints: HashMap<SensorType, SetpointFile>,
+    degraded_gains: HashMap<SensorType, (f64, f64, f64)>,
+    degraded_setpoints: HashMap<SensorType, SetpointFile>,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self { Self::from(&SimulationConfig::default()) }
+}
+
+impl From<&SimulationConfig> for ConfigFile {
+    fn from(c: &SimulationConfig) -> Self {
+
+        let (duration_ms, samples) = match c.stop_after {
+            StopCondition::Duration(d) => (ms(d), None),
+            StopCondition::Samples(n) => (0.0, Some(n)),
+        };
+        // Sorted so the same schedule always writes the same file
+        let mut fault_schedule: Vec<ScheduledFaultFile> = c.fault_schedule.faults().map(|(sensor, id, fault)| ScheduledFaultFile {
+            sensor,
+            id,
+            delay_ms: match fault { TransmissionFault::Delay(delay) => Some(ms(delay)), TransmissionFault::Drop => None },
+        }).collect();
+        fault_schedule.sort_by_key(|f| (f.sensor as u8, f.id));
+        Self {
+            duration_ms,
+            samples,
+            time_scale: c.time_scale,
+            live_log: c.live_log,
+            min_log_level: c.min_log_level,
+            verbosity: c.verbosity,
+            heartbeat: c.heartbeat,
+            recalibration_decay_ms: c.recalibration_decay.map(ms),
+            commander_tick_ms: ms(c.commander_tick),
+            pid_trace_output: c.pid_trace_output.clone(),
+            calibration_file: c.calibration_file.clone(),
+            invariant_effort_limit: c.invariant_effort_limit,
+            influx_addr: c.influx_addr,
+            worker_threads: c.worker_threads,
+            anomaly_confirm: c.anomaly_confirm,
+            stages: c.stages.clone(),
+            record: c.record,
+            shutdown_grace_ms: ms(c.shutdown_grace),
+            deadlines: DeadlinesFile::from(&c.deadlines),
+            fault_rates: FaultRatesFile {
+                drop_rate: c.fault_rates.drop_rate,
+                latency_rate: c.fault_rates.latency_rate,
+                latency_ms: ms(c.fault_rates.latency),
+            },
+            fault_schedule,
+            gains: c.gains.clone(),
+            actuator_limits: c.actuator_limits.clone(),
+            failure_schedules: c.failure_schedules.iter().map(|(s, f)| (*s, (ms(f.fail_at), ms(f.recover_after)))).collect(),
+            actuator_retry: c.actuator_retry.map(|r| (r.max_retries, ms(r.backoff))),
+            plants: c.plants.iter().map(|(s, p)| (*s, PlantFile::from(p))).collect(),
+            deadbands: c.deadbands.clone(),
+            sensor_priority: c.sensor_priority.clone(),
+            drift_rates: c.drift_rates.clone(),
+            drift_correction: c.drift_correction,
+            batch_size: c.batch_size,
+            parallel_commanders: c.parallel_commanders,
+            soak_check_interval_ms: c.soak_check_interval.map(ms),
+            settling_band: c.settling_band,
+            sensor_channel_capacity: c.sensor_channel_capacity,
+            backpressure: c.backpressure,
+            maintain_decay: c.maintain_decay,
+            setpoints: setpoint_files(&c.setpoints),
+            degraded_gains: c.degraded_gains.clone(),
+            degraded_setpoints: setpoint_files(&c.degraded_setpoints),
+        }
+    }
+}
+
+fn setpoint_files(setpoints: &HashMap<SensorType, SetpointSchedule>) -> HashMap<SensorType, SetpointFile> {
+    setpoints.iter().map(|(s_type, schedule)| {
+        let breakpoints = schedule.breakpoints().iter().map(|(t, v)| (ms(*t), *v)).collect();
+        (*s_type, SetpointFile { breakpoints, interpolation: schedule.interpolation() })
+    }).collect()
+}
+
+fn setpoint_schedules(files: HashMap<SensorType, SetpointFile>) -> HashMap<SensorType, SetpointSchedule> {
+    files.into_iter().map(|(s_type, file)| {
+        let breakpoints = file.breakpoints.into_iter().map(|(t, v)| (from_ms(t), v)).collect();
+        (s_type, SetpointSchedule::new(breakpoints, file.interpolation))
+    }).collect()
+}
+
+impl ConfigFile {
+    fn into_config(self) -> SimulationConfig {
+        let stop_after = match self.samples {
+            Some(n) => StopCondition::Samples(n),
+            None => StopCondition::Duration(from_ms(self.duration_ms)),
+        };
+        SimulationConfig {
+            stop_after,
+            live_log: self.live_log,
+            min_log_level: self.min_log_level,
+            deadlines: self.deadlines.into_deadlines(),
+            time_scale: self.time_scale,
+            pid_trace_output: self.pid_trace_output,
+            commander_tick: from_ms(self.commander_tick_ms),
+            heartbeat: self.heartbeat,
+            recalibration_decay: self.recalibration_decay_ms.map(from_ms),
+            invariant_effort_limit: self.invariant_effort_limit,
+            influx_addr: self.influx_addr,
+            worker_threads: self.worker_threads,
+            anomaly_confirm: self.anomaly_confirm,
+            stages: self.stages,
+            record: self.record,
+            shutdown_grace: from_ms(self.shutdown_grace_ms),
+            verbosity: self.verbosity,
+            calibration_file: self.calibration_file,
+            setpoints: setpoint_schedules(self.setpoints),
+            degraded_setpoints: setpoint_schedules(self.degraded_setpoints),
+            fault_rates: FaultRates {
+                drop_rate: self.fault_rates.drop_rate,
+                latency_rate: self.fault_rates.latency_rate,
+                latency: from_ms(self.fault_rates.latency_ms),
+            },
+            fault_schedule: self.fault_schedule.into_iter().fold(FaultSchedule::new(), |schedule, f| match f.delay_ms {
+                Some(delay) => schedule.delay(f.sensor, f.id, from_ms(delay)),
+                None => schedule.drop(f.sensor, f.id),
+            }),
+            gains: self.gains,
+            degraded_gains: self.degraded_gains,
+            actuator_limits: self.actuator_limits,
+            failure_schedules: self.failure_schedules.into_iter()
+                .map(|(s, (fail_at, recover_after))| (s, FailureSchedule { fail_at: from_ms(fail_at), recover_after: from_ms(recover_after) })).collect(),
+            actuator_retry: self.actuator_retry.map(|(max_retries, backoff)| ActuatorRetry { max_retries, backoff: from_ms(backoff) }),
+            plants: self.plants.into_iter().map(|(s, p)| (s, p.into_model())).collect(),
+            deadbands: self.deadbands,
+            sensor_priority: self.sensor_priority,
+            drift_rates: self.drift_rates,
+            drift_correction: self.drift_correction,
+            batch_size: self.batch_size,
+            parallel_commanders: self.parallel_commanders,
+            soak_check_interval: self.soak_check_interval_ms.map(from_ms),
+            settling_band: self.settling_band,
+            sensor_channel_capacity: self.sensor_channel_capacity,
+            backpressure: self.backpressure,
+            maintain_decay: self.maintain_decay,
+            ..SimulationConfig::default()
+        }
+    }
+}
+
+impl SimulationConfig {
+    pub fn from_json_str(json: &str) -> Result<Self, ConfigError> {
+        let file: ConfigFile = serde_json::from_str(json).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        let config = file.into_config();
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_json_file(path: &Path) -> Result<Self, ConfigError> {
+        let json = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_json_str(&json)
+    }
+
+    // Inverse of `from_json_str`; correlated faults, adaptive sampling, the feedback mode, the real-time setup and the anomaly rate gate are not part of the file format
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(&ConfigFile::from(self)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_survives_a_json_round_trip() {
+        let ramp = SetpointSchedule::new(vec![(Duration::ZERO, 30.0), (Duration::from_millis(1000), 45.0)], Interpolation::Linear);
+        let plant = PlantModel {
+            gain: 2.0,
+            time_constant: Duration::from_millis(150),
+            initial: 10.0,
+            noise: 0.5,
+            disturbances: vec![(Duration::from_millis(500), 3.0)],
+        };
+        let config = SimulationConfig {
+            stop_after: StopCondition::Duration(Duration::from_millis(2500)),
+            gains: HashMap::from([(SensorType::Force, (2.0, 0.2, 0.1))]),
+            setpoints: HashMap::from([(SensorType::Force, ramp)]),
+            sensor_priority: vec![SensorType::Temperature, SensorType::Force, SensorType::Position],
+            fault_schedule: FaultSchedule::new()
+                .drop(SensorType::Force, 3)
+                .delay(SensorType::Position, 7, Duration::from_millis(2)),
+            plants: HashMap::from([(SensorType::Temperature, plant)]),
+            recalibration_decay: Some(Duration::from_millis(40)),
+            ..SimulationConfig::default()
+        };
+
+        let json = config.to_json_string();
+        let parsed = SimulationConfig::from_json_str(&json).unwrap();
+
+        assert_eq!(parsed.stop_after, config.stop_after);
+        assert_eq!(parsed.gains, config.gains);
+        assert_eq!(parsed.setpoints, config.setpoints);
+        assert_eq!(parsed.sensor_priority, config.sensor_priority);
+        assert_eq!(parsed.fault_schedule, config.fault_schedule);
+        assert_eq!(parsed.plants, config.plants);
+        assert_eq!(parsed.recalibration_decay, config.recalibration_decay);
+        // Everything else in the file format comes back unchanged too
+        let value = |json: &str| serde_json::from_str::<serde_json::Value>(json).unwrap();
+        assert_eq!(value(&parsed.to_json_string()), value(&json));
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::ffi::c_long;
+    use std::time::Duration;
+
+    #[repr(C)]
+    struct Timespec {
+        tv_sec: c_long,
+        tv_nsec: c_long,
+    }
+
+    const CLOCK_THREAD_CPUTIME_ID: i32 = 3;
+
+    unsafe extern "C" {
+        fn clock_gettime(clock: i32, tp: *mut Timespec) -> i32;
+    }
+
+    pub fn thread_cpu_time() -> Option<Duration> {
+        let mut ts = Timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: `ts` is a valid timespec the call writes into
+        if unsafe { clock_gettime(CLOCK_THREAD_CPUTIME_ID, &mut ts) } != 0 { return None; }
+        Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::time::Duration;
+
+    pub fn thread_cpu_time() -> Option<Duration> {
+        None
+    }
+}
+
+// Whether `CpuTimer` measures CPU time here rather than falling back to wall time
+pub fn supported() -> bool {
+    platform::thread_cpu_time().is_some()
+}
+
+// CPU time the calling thread spends between `start` and `elapsed`; unlike `Instant`,
+// time the thread was descheduled does not count. Wall time where there is no thread clock.
+pub(crate) struct CpuTimer {
+    cpu: Option<Duration>,
+    wall: Instant,
+}
+
+impl CpuTimer {
+    pub fn start() -> Self {
+        Self { cpu: platform::thread_cpu_time(), wall: Instant::now() }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match (self.cpu, platform::thread_cpu_time()) {
+            (Some(start), Some(now)) => now.saturating_sub(start),
+            _ => self.wall.elapsed(),
+        }
+    }
+}
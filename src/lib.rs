@@ -1,32 +1,262 @@
+use std::any::Any;
 use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
-use crossbeam::channel::unbounded;
+use crossbeam::channel::{self, bounded, unbounded, Sender};
 
 pub mod share;
 pub mod sensor_multi_thread;
+pub mod sensor_fused;
 pub mod actuator_multi_thread;
 pub mod actuator_commander_multi_thread;
 pub mod sensor_async;
+pub mod sensor_tokio;
 pub mod actuator_commander_async;
 pub mod actuator_async;
+pub mod scenario;
+pub mod replay;
+pub mod soak;
+pub mod cpu_time;
+mod rt;
+#[cfg(feature = "serde")]
+pub mod config_file;
 
-pub use actuator_commander_multi_thread::ActuatorCommander;
-pub use share::{BenchmarkStats, SensorType, SystemLog};
+pub use actuator_commander_multi_thread::{ActuatorCommander, ControlCommand, LoopbackTransport};
+pub use share::{ActuatorRetry, AdaptiveSampling, AnomalyRateGate, BackpressurePolicy, BenchmarkStats, CalibrationStore, ComponentRegistry, ConfigError, CorrelatedFault, CycleTimeline, DataError, DeadLetter, DeadLetterLog, DropReason, FailureSchedule, Fault, FaultController, FaultRates, FaultSchedule, FeedbackMode, FilterState, HealthReport, HealthWeights, InfluxSink, Interpolation, Invariant, InvariantChecker, LatencyHistogram, LogEntry, LogLevel, ModeTimes, ModeTransition, PerSensor, PidTrace, Plant, PlantModel, RtConfig, Sample, SampleBudget, SchedPolicy, SensorFeedback, SensorProfile, SensorSet, SensorType, SetpointSchedule, ShutdownReason, SimError, SimulationConfig, SnapshotHandle, StageFlags, StageStamps, StageTimes, StepResponse, StepTracker, StopCondition, SystemEvent, SystemLog, SystemMode, SystemSnapshot, TransmissionFault, Verbosity};
 pub use sensor_multi_thread::Sensor;
+pub use sensor_fused::{FusedSensor, FusionFn};
 pub use actuator_multi_thread::Actuator;
+pub use scenario::{Scenario, ScenarioEvent};
+pub use replay::{verify_replay, verify_replay_with, Divergence, Recorder, Recording, ReplayReport};
+pub use soak::{SoakCallback, SoakCheck};
+use scenario::ScenarioTargets;
+use soak::{SoakHooks, SoakTargets};
+use share::{DeadlineHooks, Deadlines};
+use sensor_async::SensorAsync;
+use actuator_commander_async::ActuatorCommanderAsync;
+use actuator_async::ActuatorAsync;
 // use tokio::time::{self, Duration};
 
-pub fn run_simulation(duration: Duration) {
-    println!("--- Starting Real-Time Sensor Simulation ---");
+pub fn run_simulation(duration: Duration) -> (BenchmarkStats, ShutdownReason) {
+    run_simulation_with(SimulationConfig { stop_after: StopCondition::Duration(duration), ..SimulationConfig::default() })
+}
+
+pub fn run_simulation_with(config: SimulationConfig) -> (BenchmarkStats, ShutdownReason) {
+    let handle = match start_simulation(config) {
+        Ok(handle) => handle,
+        Err(reason) => return (BenchmarkStats::new(), reason),
+    };
+
+    handle.wait();
+    handle.finish()
+}
+
+// Same as `run_simulation_with` for embedding in a larger application: prints no
+// report, and setup failures, panicking threads and a poisoned log come back as an
+// error instead of unwinding into the caller. The log is handed over once the run is
+// done; later writers (e.g. a scenario thread still winding down) write to a blank one.
+pub fn try_run_simulation(config: SimulationConfig) -> Result<(BenchmarkStats, SystemLog), SimError> {
+    config.validate().map_err(SimError::Config)?;
+    let handle = match panic::catch_unwind(AssertUnwindSafe(|| start_simulation(config))) {
+        Ok(Ok(handle)) => handle,
+        Ok(Err(reason)) => return Err(reason.into()),
+        Err(payload) => return Err(SimError::SetupPanicked(panic_message(payload.as_ref()))),
+    };
+
+    handle.wait();
+    handle.try_join()
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(msg) => msg.to_string(),
+        None => payload.downcast_ref::<String>().cloned().unwrap_or_else(|| "unknown panic".to_string()),
+    }
+}
+
+// Tokio backend on a runtime owned by the crate, shaped by `config.worker_threads`:
+// - None: multi-threaded runtime with one worker per core (tokio's default)
+// - Some(1): current-thread runtime. Every task shares one thread, so a busy
+//   commander or actuator delays the sensor ticks and shows up directly as jitter.
+// - Some(n): multi-threaded runtime with n workers
+pub fn run_simulation_async(config: SimulationConfig) -> (BenchmarkStats, ShutdownReason) {
+    let runtime = match config.worker_threads {
+        Some(1) => tokio::runtime::Builder::new_current_thread().enable_all().build(),
+        Some(workers) => tokio::runtime::Builder::new_multi_thread().worker_threads(workers).enable_all().build(),
+        None => tokio::runtime::Builder::new_multi_thread().enable_all().build(),
+    };
+
+    match runtime {
+        Ok(runtime) => runtime.block_on(simulate_async(config)),
+        Err(e) => (BenchmarkStats::new(), ShutdownReason::RuntimeUnavailable(e.to_string())),
+    }
+}
+
+async fn simulate_async(config: SimulationConfig) -> (BenchmarkStats, ShutdownReason) {
+    use tokio::sync::mpsc;
+
+    if let Err(e) = config.validate() {
+        return (BenchmarkStats::new(), ShutdownReason::invalid_config(e));
+    }
+    if config.verbosity > Verbosity::Silent {
+        println!("=== Starting Async Real-Time Simulation (Tokio) ===");
+    }
+    let deadlines = config.deadlines.scaled(config.time_scale);
+
+    let mut log = SystemLog::new();
+    log.set_live_output(config.live_log);
+    log.set_min_level(config.min_log_level);
+    log.set_verbosity(config.verbosity);
+    let system_log = Arc::new(tokio::sync::Mutex::new(log));
+
+    // SENSORS -> COMMANDER -> ACTUATORS -> SENSORS
+    let (tx_force, rx_force) = mpsc::channel(32);
+    let (tx_pos, rx_pos) = mpsc::channel(32);
+    let (tx_temp, rx_temp) = mpsc::channel(32);
+    let (at_tx_force, at_rx_force) = mpsc::channel(32);
+    let (at_tx_pos, at_rx_pos) = mpsc::channel(32);
+    let (at_tx_temp, at_rx_temp) = mpsc::channel(32);
+    let (fb_tx_force, fb_rx_force) = mpsc::channel(32);
+    let (fb_tx_pos, fb_rx_pos) = mpsc::channel(32);
+    let (fb_tx_temp, fb_rx_temp) = mpsc::channel(32);
+
+    let mut actuator_tx_map = HashMap::new();
+    actuator_tx_map.insert(SensorType::Force, at_tx_force);
+    actuator_tx_map.insert(SensorType::Position, at_tx_pos);
+    actuator_tx_map.insert(SensorType::Temperature, at_tx_temp);
+
+    let commander = ActuatorCommanderAsync::new(actuator_tx_map, system_log.clone()).with_deadlines(deadlines);
+    let hooks = commander.deadline_hooks();
+    let sample_budget = config.sample_budget();
+    let calibration = match &config.calibration_file {
+        Some(path) => match CalibrationStore::load(path) {
+            Ok(store) => store,
+            Err(e) => {
+                system_log.lock().await.write_level(LogLevel::Warn, format!("[Calibration] Cannot load {:?}: {}", path, e));
+                CalibrationStore::new()
+            }
+        },
+        None => CalibrationStore::new(),
+    };
+    let sensor = |s_type| SensorAsync::new(s_type, system_log.clone())
+        .with_calibration(calibration.clone())
+        .with_fault_rates(config.fault_rates.scaled(config.time_scale))
+        .with_anomaly_confirm(config.anomaly_confirm)
+        .with_stages(config.stages.get(&s_type).copied().unwrap_or_default())
+        .with_recalibration_decay(config.recalibration_decay.map(|decay| decay.div_f64(config.time_scale)))
+        .with_sample_budget(sample_budget.clone())
+        .with_deadline_hooks(hooks.clone())
+        .with_deadlines(deadlines);
+    let actuator = |name: &str, s_type| ActuatorAsync::new(name.to_string(), s_type, system_log.clone())
+        .with_deadline_hooks(hooks.clone())
+        .with_deadlines(deadlines);
+    let (sensor_force, sensor_pos, sensor_temp) = (sensor(SensorType::Force), sensor(SensorType::Position), sensor(SensorType::Temperature));
+    let (gripper, stabiliser, motor) = (actuator("Gripper", SensorType::Force), actuator("Stabiliser", SensorType::Position), actuator("Motor", SensorType::Temperature));
+
+    let start_time = Instant::now();
+    let handles = vec![
+        ("ForceSensor", Some(SensorType::Force), tokio::spawn(sensor_force.run(tx_force, fb_rx_force))),
+        ("PositionSensor", Some(SensorType::Position), tokio::spawn(sensor_pos.run(tx_pos, fb_rx_pos))),
+        ("TemperatureSensor", Some(SensorType::Temperature), tokio::spawn(sensor_temp.run(tx_temp, fb_rx_temp))),
+        ("Commander", None, tokio::spawn(commander.run(rx_force, rx_pos, rx_temp))),
+        ("Gripper", Some(SensorType::Force), tokio::spawn(gripper.run(at_rx_force, fb_tx_force))),
+        ("Stabiliser", Some(SensorType::Position), tokio::spawn(stabiliser.run(at_rx_pos, fb_tx_pos))),
+        ("Motor", Some(SensorType::Temperature), tokio::spawn(motor.run(at_rx_temp, fb_tx_temp))),
+    ];
+
+    match config.stop_after {
+        StopCondition::Duration(duration) => {
+            tokio::time::sleep(duration).await;
+            system_log.lock().await.request_shutdown(ShutdownReason::DurationElapsed);
+        }
+        // The sensors request the shutdown themselves once the budget is used up
+        StopCondition::Samples(_) => while system_log.lock().await.active {
+            tokio::time::sleep(STOP_POLL).await;
+        },
+    }
+
+    // Sensors stop on the flag, the rest follow as their channels close. Same
+    // watchdog as `SimulationHandle::join`, except stuck tasks are aborted.
+    let mut benchmark_stats = BenchmarkStats::new();
+    let mut panicked = false;
+    let mut stuck = Vec::new();
+    let give_up_at = tokio::time::Instant::now() + config.shutdown_grace;
+    let mut per_sensor: HashMap<SensorType, BenchmarkStats> = HashMap::new();
+    for (name, sensor_type, mut handle) in handles {
+        match tokio::time::timeout_at(give_up_at, &mut handle).await {
+            Ok(Ok(stats)) => {
+                benchmark_stats.merge(&stats);
+                if let Some(s_type) = sensor_type {
+                    per_sensor.entry(s_type).or_default().merge(&stats);
+                }
+            }
+            Ok(Err(_)) => panicked = true,
+            Err(_) => {
+                handle.abort();
+                stuck.push(name.to_string());
+            }
+        }
+    }
+    let total_run_time = start_time.elapsed();
+
+    // Every sensor that stopped has written its final offset by now
+    if let Some(path) = &config.calibration_file {
+        if let Err(e) = calibration.save(path) {
+            system_log.lock().await.write_level(LogLevel::Warn, format!("[Calibration] Cannot save {:?}: {}", path, e));
+        }
+    }
+
+    if !stuck.is_empty() {
+        let mut log = system_log.lock().await;
+        log.alert(format!("Watchdog: shutdown did not complete within {:?}, still running: {}", config.shutdown_grace, stuck.join(", ")));
+    }
+
+    let shutdown_reason = if !stuck.is_empty() {
+        ShutdownReason::WatchdogTimeout(stuck)
+    } else if panicked {
+        ShutdownReason::ThreadPanicked
+    } else {
+        system_log.lock().await.shutdown_reason().unwrap_or(ShutdownReason::DurationElapsed)
+    };
+
+    {
+        let mut log = system_log.lock().await;
+        if let Err(e) = log.flush() {
+            log.write_level(LogLevel::Warn, format!("[Log] Cannot flush the system log: {}", e));
+        }
+    }
+
+    let benchmark_stats = benchmark_stats.to_simulated(config.time_scale);
+    let per_sensor = per_sensor.into_iter().map(|(s_type, stats)| (s_type, stats.to_simulated(config.time_scale))).collect();
+    if config.verbosity > Verbosity::Silent {
+        print_report(benchmark_stats, total_run_time.mul_f64(config.time_scale), &shutdown_reason);
+        print_sensor_comparison(&per_sensor);
+    }
+    (benchmark_stats, shutdown_reason)
+}
+
+// Spawns every thread and returns immediately; the caller decides when to stop
+pub fn start_simulation(config: SimulationConfig) -> Result<SimulationHandle, ShutdownReason> {
+    // A zero time scale or cycle would otherwise panic in the duration math below
+    config.validate().map_err(ShutdownReason::invalid_config)?;
+    if config.verbosity > Verbosity::Silent {
+        println!("--- Starting Real-Time Sensor Simulation ---");
+    }
+    // Sleeps and deadlines run in wall-clock time, compressed by the time scale
+    let deadlines = config.deadlines.scaled(config.time_scale);
 
     // 1. Setup Shared Resources
 
     // CHANNEL: Sensor -> Commander
-    let (tx_force, rx_force) = unbounded();
-    let (tx_pos, rx_pos) = unbounded();
-    let (tx_temp, rx_temp) = unbounded();
+    let sensor_channel = || match config.sensor_channel_capacity {
+        Some(capacity) => bounded(capacity),
+        None => unbounded(),
+    };
+    let (tx_force, rx_force) = sensor_channel();
+    let (tx_pos, rx_pos) = sensor_channel();
+    let (tx_temp, rx_temp) = sensor_channel();
 
     // CHANNEL: Actuator -> Commander
     let (fb_tx_force, fb_rx_force) = unbounded();
@@ -53,67 +283,278 @@ pub fn run_simulation(duration: Duration) {
     // let (fbs_tx_pos, fbs_rx_pos) = unbounded();
     // let (fbs_tx_temp, fbs_rx_temp) = unbounded();
 
-    // let mut feedback_tx_map = HashMap::new();
-    // feedback_tx_map.insert(SensorType::Force, fbs_tx_force);
-    // feedback_tx_map.insert(SensorType::Position, fbs_tx_pos);
-    // feedback_tx_map.insert(SensorType::Temperature, fbs_tx_temp);
-
-    // BenchMark Report
-    let mut benchmark_stats = BenchmarkStats::new();
-
-    // 2. Define the Sensors we want to run
-    let sensors = vec![
-        SensorType::Force,
-        SensorType::Position,
-        SensorType::Temperature,
-    ];
+    // The commander shares the actuators' feedback path into each sensor
+    let mut feedback_tx_map = HashMap::new();
+    feedback_tx_map.insert(SensorType::Force, fb_tx_force.clone());
+    feedback_tx_map.insert(SensorType::Position, fb_tx_pos.clone());
+    feedback_tx_map.insert(SensorType::Temperature, fb_tx_temp.clone());
 
-    let system_log = Arc::new(Mutex::new(SystemLog::new()));
+    let mut log = SystemLog::new();
+    log.set_live_output(config.live_log);
+    log.set_min_level(config.min_log_level);
+    log.set_verbosity(config.verbosity);
+    let system_log = Arc::new(Mutex::new(log));
     let sensor_log = system_log.clone();
     let commander_log = system_log.clone();
     let actuator_log = system_log.clone();
 
-    let sensor_temperature = Sensor::new(SensorType::Temperature,sensor_log.clone());
-    let sensor_position = Sensor::new(SensorType::Position,sensor_log.clone());
-    let sensor_force = Sensor::new(SensorType::Force,sensor_log.clone());
+    // Identities used in the log lines of every component
+    let mut registry = ComponentRegistry::new();
+    registry.register_sensor(SensorType::Force, "ForceSensor");
+    registry.register_sensor(SensorType::Position, "PositionSensor");
+    registry.register_sensor(SensorType::Temperature, "TemperatureSensor");
+    let motor_id = registry.register_actuator(SensorType::Temperature, "Motor");
+    let stabiliser_id = registry.register_actuator(SensorType::Position, "Stabiliser");
+    let gripper_id = registry.register_actuator(SensorType::Force, "Gripper");
+
+    // Every discarded sample ends up here, whoever dropped it
+    let dead_letters = DeadLetterLog::default();
+
+    // Optional metrics push, shared by the commander and the actuators
+    let influx = config.influx_addr.and_then(|addr| match InfluxSink::spawn(addr, Duration::from_millis(100)) {
+        Ok(sink) => Some(sink),
+        Err(e) => {
+            if let Ok(mut log) = system_log.lock() {
+                log.write_level(LogLevel::Warn, format!("[Influx] Cannot reach {}: {}", addr, e));
+            }
+            None
+        }
+    });
+
+    // Verify the channel wiring before any thread is started
+    let mut commander = ActuatorCommander::new(actuator_tx_map, feedback_tx_map, commander_log)
+        .with_deadlines(deadlines)
+        .with_registry(registry)
+        .with_tick(config.commander_tick, config.heartbeat)
+        .with_sensor_priority(&config.sensor_priority)
+        .with_dead_letters(dead_letters.clone())
+        .with_influx(influx.clone());
+
+    let recorder = config.record.then(Recorder::new);
+    commander = commander.with_recorder(recorder.clone());
+
+    // CHANNEL: Actuator -> Commander, saturation changes only
+    let (status_tx, status_rx) = unbounded();
+    commander = commander.with_actuator_status(status_rx);
+
+    commander = commander.with_anomaly_rate_gate(config.anomaly_rate_gate.map(|g| g.scaled(config.time_scale)));
+
+    for (s_type, (kp, ki, kd)) in &config.gains {
+        commander = commander.with_gains(*s_type, *kp, *ki, *kd);
+    }
+
+    for (s_type, schedule) in &config.setpoints {
+        commander = commander.with_setpoint_schedule(*s_type, schedule.scaled(config.time_scale));
+    }
+
+    commander = commander.with_drift_correction(config.drift_correction).with_settling_band(config.settling_band)
+        .with_actuator_retry(config.actuator_retry.map(|r| r.scaled(config.time_scale)));
+    for (s_type, deadband) in &config.deadbands {
+        commander = commander.with_deadband(*s_type, *deadband);
+    }
+    for (s_type, (kp, ki, kd)) in &config.degraded_gains {
+        commander = commander.with_degraded_gains(*s_type, *kp, *ki, *kd);
+    }
+
+    for (s_type, schedule) in &config.degraded_setpoints {
+        commander = commander.with_degraded_setpoint_schedule(*s_type, schedule.scaled(config.time_scale));
+    }
+
+    if let Some(limit) = config.invariant_effort_limit {
+        commander = commander.with_invariants(InvariantChecker::with_builtins(limit));
+    }
+
+    if let Some(path) = &config.pid_trace_output {
+        match PidTrace::create(path) {
+            Ok(trace) => commander = commander.with_pid_trace(trace),
+            Err(e) => if let Ok(mut log) = system_log.lock() {
+                log.write_level(LogLevel::Warn, format!("[PID Trace] Cannot create {:?}: {}", path, e));
+            },
+        }
+    }
+
+    if let Err(e) = commander.self_test() {
+        if let Ok(mut log) = system_log.lock() {
+            log.alert(format!("Self-test failed: {}", e));
+        }
+        return Err(ShutdownReason::SelfTestFailed(e));
+    }
+    commander = commander.release_unused_feedback();
+
+    // Every component reports deadline misses to the commander's callbacks
+    let deadline_hooks = commander.deadline_hooks();
+    let snapshot = commander.snapshot_handle();
+    let monitor = snapshot.clone();
+    deadline_hooks.register(Box::new(move |_, _, _| monitor.record_deadline_miss()));
+
+    // CHANNEL: Handle -> Commander
+    let (control_tx, control_rx) = unbounded();
+    let commander = commander.with_control(control_rx);
+
+    // CHANNEL: Handle -> Sensor (fault injection)
+    let (fault_tx_force, fault_rx_force) = unbounded();
+    let (fault_tx_pos, fault_rx_pos) = unbounded();
+    let (fault_tx_temp, fault_rx_temp) = unbounded();
+
+    let mut fault_tx_map = HashMap::new();
+    fault_tx_map.insert(SensorType::Force, fault_tx_force);
+    fault_tx_map.insert(SensorType::Position, fault_tx_pos);
+    fault_tx_map.insert(SensorType::Temperature, fault_tx_temp);
+
+    let fault_rates = config.fault_rates.scaled(config.time_scale);
+    let fault_schedule = config.fault_schedule.scaled(config.time_scale);
+
+    // Offsets learned by earlier runs
+    let calibration = match &config.calibration_file {
+        Some(path) => CalibrationStore::load(path).unwrap_or_else(|e| {
+            if let Ok(mut log) = system_log.lock() {
+                log.write_level(LogLevel::Warn, format!("[Calibration] Cannot load {:?}: {}", path, e));
+            }
+            CalibrationStore::new()
+        }),
+        None => CalibrationStore::new(),
+    };
+
+    let fault_controller = config.correlated_fault.map(FaultController::new);
+    let adaptive_sampling = config.adaptive_sampling.map(|a| a.scaled(config.time_scale));
+    let sample_budget = config.sample_budget();
+    let batch_tx = (config.batch_size > 1).then(|| commander.batch_sender());
+    // Sensor and actuator of a type share its plant, closing the loop
+    let plant = |s_type| config.plants.get(&s_type).map(|p| Plant::new(p.scaled(config.time_scale)));
+    let (plant_temp, plant_pos, plant_force) = (plant(SensorType::Temperature), plant(SensorType::Position), plant(SensorType::Force));
+
+    let sensor_temperature = Sensor::new(SensorType::Temperature,sensor_log.clone())
+        .with_calibration(calibration.clone())
+        .with_plant(plant_temp.clone())
+        .with_fault_rates(fault_rates)
+        .with_fault_schedule(fault_schedule.clone())
+        .with_fault_controller(fault_controller.clone())
+        .with_adaptive_sampling(adaptive_sampling)
+        .with_feedback_mode(config.feedback_mode.scaled(config.time_scale))
+        .with_anomaly_confirm(config.anomaly_confirm)
+        .with_stages(config.stages.get(&SensorType::Temperature).copied().unwrap_or_default())
+        .with_drift_rate(config.drift_rates.get(&SensorType::Temperature).copied().unwrap_or(0.0))
+        .with_batching(config.batch_size, batch_tx.clone())
+        .with_backpressure(config.backpressure)
+        .with_maintain_decay(config.maintain_decay)
+        .with_sample_budget(sample_budget.clone())
+        .with_monitor(snapshot.clone())
+        .with_fault_injection(fault_rx_temp)
+        .with_deadline_hooks(deadline_hooks.clone())
+        .with_dead_letters(dead_letters.clone())
+        .with_deadlines(deadlines);
+    let sensor_position = Sensor::new(SensorType::Position,sensor_log.clone())
+        .with_calibration(calibration.clone())
+        .with_plant(plant_pos.clone())
+        .with_fault_rates(fault_rates)
+        .with_fault_schedule(fault_schedule.clone())
+        .with_fault_controller(fault_controller.clone())
+        .with_adaptive_sampling(adaptive_sampling)
+        .with_feedback_mode(config.feedback_mode.scaled(config.time_scale))
+        .with_anomaly_confirm(config.anomaly_confirm)
+        .with_stages(config.stages.get(&SensorType::Position).copied().unwrap_or_default())
+        .with_drift_rate(config.drift_rates.get(&SensorType::Position).copied().unwrap_or(0.0))
+        .with_batching(config.batch_size, batch_tx.clone())
+        .with_backpressure(config.backpressure)
+        .with_maintain_decay(config.maintain_decay)
+        .with_sample_budget(sample_budget.clone())
+        .with_monitor(snapshot.clone())
+        .with_fault_injection(fault_rx_pos)
+        .with_deadline_hooks(deadline_hooks.clone())
+        .with_dead_letters(dead_letters.clone())
+        .with_deadlines(deadlines);
+    let sensor_force = Sensor::new(SensorType::Force,sensor_log.clone())
+        .with_calibration(calibration.clone())
+        .with_plant(plant_force.clone())
+        .with_fault_rates(fault_rates)
+        .with_fault_schedule(fault_schedule.clone())
+        .with_fault_controller(fault_controller.clone())
+        .with_adaptive_sampling(adaptive_sampling)
+        .with_feedback_mode(config.feedback_mode.scaled(config.time_scale))
+        .with_anomaly_confirm(config.anomaly_confirm)
+        .with_stages(config.stages.get(&SensorType::Force).copied().unwrap_or_default())
+        .with_drift_rate(config.drift_rates.get(&SensorType::Force).copied().unwrap_or(0.0))
+        .with_batching(config.batch_size, batch_tx.clone())
+        .with_backpressure(config.backpressure)
+        .with_maintain_decay(config.maintain_decay)
+        .with_sample_budget(sample_budget.clone())
+        .with_monitor(snapshot.clone())
+        .with_fault_injection(fault_rx_force)
+        .with_deadline_hooks(deadline_hooks.clone())
+        .with_dead_letters(dead_letters.clone())
+        .with_deadlines(deadlines);
 
     let start_time = Instant::now();
 
     // Spawn sensor threads and CAPTURE handles
-    let temp_handle = thread::spawn(move || {
+    let temp_handle = spawn_rt(&config.rt, 0, "TemperatureSensor", &system_log, move || {
         sensor_temperature.run(tx_temp, fb_rx_temp)
     });
 
-    let pos_handle = thread::spawn(move || {
+    let pos_handle = spawn_rt(&config.rt, 1, "PositionSensor", &system_log, move || {
         sensor_position.run(tx_pos, fb_rx_pos)
     });
 
-    let force_handle = thread::spawn(move || {
+    let force_handle = spawn_rt(&config.rt, 2, "ForceSensor", &system_log, move || {
         sensor_force.run(tx_force, fb_rx_force)
     });
 
-    let commander = ActuatorCommander::new(actuator_tx_map, commander_log);
-    let commander_handle = thread::spawn(move || {
-        commander.run(rx_force, rx_pos, rx_temp)
+    let parallel_commanders = config.parallel_commanders;
+    let commander_handle = spawn_rt(&config.rt, 3, "Commander", &system_log, move || {
+        if parallel_commanders {
+            commander.run_parallel(rx_force, rx_pos, rx_temp)
+        } else {
+            commander.run(rx_force, rx_pos, rx_temp)
+        }
     });
 
     let mut motor = Actuator::new(
         "Motor".to_string(),
         SensorType::Temperature,
         actuator_log.clone()
-    );
+    )
+    .with_id(motor_id)
+    .with_status(status_tx.clone())
+    .with_influx(influx.clone())
+    .with_failure_schedule(config.failure_schedules.get(&SensorType::Temperature).map(|f| f.scaled(config.time_scale)))
+    .with_plant(plant_temp)
+    .with_deadline_hooks(deadline_hooks.clone())
+    .with_deadlines(deadlines);
+    if let Some((min, max)) = config.actuator_limits.get(&SensorType::Temperature) {
+        motor = motor.with_limits(*min, *max);
+    }
 
     let mut stabiliser = Actuator::new(
         "Stabiliser".to_string(),
         SensorType::Position,
         actuator_log.clone()
-    );
+    )
+    .with_id(stabiliser_id)
+    .with_status(status_tx.clone())
+    .with_influx(influx.clone())
+    .with_failure_schedule(config.failure_schedules.get(&SensorType::Position).map(|f| f.scaled(config.time_scale)))
+    .with_plant(plant_pos)
+    .with_deadline_hooks(deadline_hooks.clone())
+    .with_deadlines(deadlines);
+    if let Some((min, max)) = config.actuator_limits.get(&SensorType::Position) {
+        stabiliser = stabiliser.with_limits(*min, *max);
+    }
 
     let mut gripper = Actuator::new(
         "Gripper".to_string(),
         SensorType::Force,
         actuator_log.clone()
-    );
+    )
+    .with_id(gripper_id)
+    .with_status(status_tx.clone())
+    .with_influx(influx.clone())
+    .with_failure_schedule(config.failure_schedules.get(&SensorType::Force).map(|f| f.scaled(config.time_scale)))
+    .with_plant(plant_force)
+    .with_deadline_hooks(deadline_hooks.clone())
+    .with_deadlines(deadlines);
+    if let Some((min, max)) = config.actuator_limits.get(&SensorType::Force) {
+        gripper = gripper.with_limits(*min, *max);
+    }
 
 
     let motor_handle = thread::spawn({
@@ -134,66 +575,753 @@ pub fn run_simulation(duration: Duration) {
         }
     });
 
-    thread::sleep(duration);
+    let soak_hooks = SoakHooks::default();
+    if let Some(interval) = config.soak_check_interval {
+        let targets = SoakTargets {
+            start_time,
+            interval,
+            log: system_log.clone(),
+            snapshot: snapshot.clone(),
+            dead_letters: dead_letters.clone(),
+            hooks: soak_hooks.clone(),
+        };
+        thread::spawn(move || soak::run(targets)); // Stops with the simulation
+    }
 
-    {
+    Ok(SimulationHandle {
+        config,
+        system_log,
+        start_time,
+        snapshot,
+        dead_letters,
+        calibration,
+        control_tx,
+        fault_tx_map,
+        deadline_hooks,
+        soak_hooks,
+        recorder,
+        handles: vec![
+            ("TemperatureSensor".to_string(), Some(SensorType::Temperature), temp_handle),
+            ("PositionSensor".to_string(), Some(SensorType::Position), pos_handle),
+            ("ForceSensor".to_string(), Some(SensorType::Force), force_handle),
+            ("Commander".to_string(), None, commander_handle),
+            ("Motor".to_string(), Some(SensorType::Temperature), motor_handle),
+            ("Stabiliser".to_string(), Some(SensorType::Position), stabiliser_handle),
+            ("Gripper".to_string(), Some(SensorType::Force), gripper_handle),
+        ],
+    })
+}
+
+// Log who is still running and what the finished threads collected
+fn watchdog_fired(log: &Mutex<SystemLog>, grace: Duration, stuck: &[String], collected: &BenchmarkStats) {
+    if let Ok(mut log) = log.lock() {
+        log.alert(format!("Watchdog: shutdown did not complete within {:?}, still running: {}", grace, stuck.join(", ")));
+        log.write_level(LogLevel::Critical, format!("[Watchdog] Stats of the finished threads only: {} sensor cycles, {} actuator cycles",
+            collected.sensor_count, collected.actuator_count));
+    }
+}
+
+// How often `SimulationHandle::wait` checks whether the simulation stopped
+const STOP_POLL: Duration = Duration::from_millis(10);
+
+// `thread::spawn`, with the thread applying `rt` to itself before running `work`
+fn spawn_rt(rt: &Option<RtConfig>, slot: usize, name: &'static str, log: &Arc<Mutex<SystemLog>>,
+            work: impl FnOnce() -> BenchmarkStats + Send + 'static) -> thread::JoinHandle<BenchmarkStats> {
+    let rt = rt.clone();
+    let log = log.clone();
+    thread::spawn(move || {
+        let applied = rt.as_ref().is_some_and(|rt| rt::apply(rt, slot, name, &log));
+        drop(log);
+        let mut stats = work();
+        if applied { stats.rt_threads += 1; }
+        stats
+    })
+}
+
+// Returned by `wait_for_steady_state` when the system did not settle in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout;
+
+// A running simulation, see `start_simulation`
+pub struct SimulationHandle {
+    config: SimulationConfig,
+    system_log: Arc<Mutex<SystemLog>>,
+    start_time: Instant,
+    snapshot: SnapshotHandle,
+    dead_letters: DeadLetterLog,
+    calibration: CalibrationStore,
+    control_tx: Sender<ControlCommand>,
+    fault_tx_map: HashMap<SensorType, Sender<Fault>>,
+    deadline_hooks: DeadlineHooks,
+    soak_hooks: SoakHooks,
+    recorder: Option<Recorder>,
+    handles: Vec<(String, Option<SensorType>, thread::JoinHandle<BenchmarkStats>)>, // Named for the watchdog; None for the commander
+}
+
+impl SimulationHandle {
+    pub fn snapshot(&self) -> SystemSnapshot {
+        self.snapshot.snapshot()
+    }
+
+    // Moving-average window of one sensor as last published
+    pub fn filter_state(&self, sensor: SensorType) -> Option<FilterState> {
+        self.snapshot.filter_state(sensor)
+    }
+
+    // Mode, sensor ages and the last second's anomalies and deadline misses
+    pub fn health(&self) -> HealthReport {
+        self.snapshot.health()
+    }
+
+    pub fn mode_transitions(&self) -> Vec<ModeTransition> {
+        self.snapshot.transitions()
+    }
+
+    // Force a fault onto the next samples of one sensor; false if that sensor is not running
+    pub fn inject_fault(&self, sensor: SensorType, fault: Fault) -> bool {
+        self.fault_tx_map.get(&sensor).is_some_and(|tx| tx.send(fault).is_ok())
+    }
+
+    // Replay `scenario` on a timer thread; the handle yields how many events were delivered
+    pub fn run_scenario(&self, scenario: Scenario) -> thread::JoinHandle<usize> {
+        let targets = ScenarioTargets {
+            start_time: self.start_time,
+            time_scale: self.config.time_scale,
+            log: self.system_log.clone(),
+            control: self.control_tx.clone(),
+            faults: self.fault_tx_map.clone(),
+        };
+        thread::spawn(move || scenario.run(targets))
+    }
+
+    // Called after every soak check; nothing is checked unless `soak_check_interval` is set
+    pub fn on_soak_check(&self, callback: SoakCallback) {
+        self.soak_hooks.register(callback);
+    }
+
+    // Offsets as loaded, updated by each sensor when it stops
+    pub fn calibration(&self) -> CalibrationStore {
+        self.calibration.clone()
+    }
+
+    // Samples discarded so far, and why
+    pub fn dead_letters(&self) -> DeadLetterLog {
+        self.dead_letters.clone()
+    }
+
+    // Blocks until `config.stop_after` is met, or until the simulation stopped for
+    // another reason (E-STOP, invariant violation, ...). Never stops it early itself.
+    pub fn wait(&self) {
+        let deadline = match self.config.stop_after {
+            StopCondition::Duration(duration) => Some(self.start_time + duration),
+            StopCondition::Samples(_) => None,
+        };
+
+        loop {
+            let active = self.system_log.lock().map(|log| log.active).unwrap_or(false);
+            if !active { return; }
+            let now = Instant::now();
+            match deadline {
+                Some(deadline) if now >= deadline => return self.stop(ShutdownReason::DurationElapsed),
+                Some(deadline) => thread::sleep((deadline - now).min(STOP_POLL)),
+                None => thread::sleep(STOP_POLL),
+            }
+        }
+    }
+
+    // Start another sensor of `sensor_type` that reads with `profile`, e.g. a redundant
+    // temperature probe. The commander controls its samples together with those of the
+    // built-in sensor of that type. It gets no actuator feedback, so it never recalibrates.
+    // Only the built-in types can be added: a new type such as Pressure would also need
+    // its own PID, actuator and report rows, which this pipeline does not have.
+    // False if the commander is gone, or the invariants are on: their sample id check
+    // cannot tell two sensors of one type apart.
+    pub fn add_sensor(&mut self, sensor_type: SensorType, profile: SensorProfile) -> bool {
+        if self.config.invariant_effort_limit.is_some() { return false; }
+
+        let (reply_tx, reply_rx) = bounded(1);
+        if self.control_tx.send(ControlCommand::AddSensor { sensor_type, reply: reply_tx }).is_err() {
+            return false;
+        }
+        let Ok(sender) = reply_rx.recv_timeout(Duration::from_secs(1)) else { return false; };
+
+        let deadlines = self.config.deadlines.scaled(self.config.time_scale);
+        let sensor = Sensor::new(sensor_type, self.system_log.clone())
+            .with_profile(profile)
+            .with_fault_rates(self.config.fault_rates.scaled(self.config.time_scale))
+            .with_anomaly_confirm(self.config.anomaly_confirm)
+            .with_stages(self.config.stages.get(&sensor_type).copied().unwrap_or_default())
+            .with_deadline_hooks(self.deadline_hooks.clone())
+            .with_dead_letters(self.dead_letters.clone())
+            .with_deadlines(Deadlines { feedback_starvation: Duration::MAX, ..deadlines }); // No feedback is expected
+        let name = format!("{:?}Sensor (added)", sensor_type);
+        self.handles.push((name, Some(sensor_type), thread::spawn(move || sensor.run(sender, channel::never()))));
+        true
+    }
+
+    pub fn stop(&self, reason: ShutdownReason) {
         // Use the copy of system_log kept by main to signal shutdown
-        if let Ok(mut log) = system_log.lock() {
-            log.active = false; // This tells sensors to break their loop
+        if let Ok(mut log) = self.system_log.lock() {
+            log.request_shutdown(reason); // This tells sensors to break their loop
         }
     }
 
-    // Optional: Wait a tiny bit for threads to see the flag and clean up
-    thread::sleep(Duration::from_millis(1000));
+    // Blocks until every effort moved less than `tolerance` for `consecutive`
+    // cycles in a row; returns the settling time measured from start-up
+    pub fn wait_for_steady_state(&self, tolerance: f64, consecutive: u32, timeout: Duration) -> Result<Duration, Timeout> {
+        let (reply_tx, reply_rx) = bounded(1);
+        let watch = ControlCommand::WatchSteadyState { tolerance, consecutive, reply: reply_tx };
+        if self.control_tx.send(watch).is_err() {
+            return Err(Timeout);
+        }
 
-    println!("--- Simulation Finished ---");
+        match reply_rx.recv_timeout(timeout) {
+            Ok(settled_at) => Ok(settled_at.duration_since(self.start_time)),
+            Err(_) => Err(Timeout),
+        }
+    }
 
-    let total_run_time = start_time.elapsed();
+    // Stops the simulation (if not already stopped) and waits for every thread; prints nothing.
+    // Threads still running after `shutdown_grace` are left behind and reported as a
+    // `WatchdogTimeout` instead of hanging the caller.
+    pub fn join(self) -> SimulationResult {
+        self.collect().0
+    }
 
-    let temp_stats = temp_handle.join().unwrap_or_else(|_| BenchmarkStats::new());
-    let pos_stats = pos_handle.join().unwrap_or_else(|_| BenchmarkStats::new());
-    let force_stats = force_handle.join().unwrap_or_else(|_| BenchmarkStats::new());
+    // Same as `join` for `try_run_simulation`: a stuck or panicking thread and a
+    // poisoned log are errors. The log is handed over, later writers get a blank one.
+    pub fn try_join(self) -> Result<(BenchmarkStats, SystemLog), SimError> {
+        let (result, panicked, system_log) = self.collect();
+        if let ShutdownReason::WatchdogTimeout(stuck) = result.shutdown_reason {
+            return Err(SimError::WatchdogTimeout(stuck));
+        }
+        if let Some(thread) = panicked.first() {
+            return Err(thread.map_or(SimError::CommanderPanicked, SimError::ThreadPanicked));
+        }
+
+        let mut log = system_log.lock().map_err(|_| SimError::PoisonedLog)?;
+        Ok((result.stats, std::mem::replace(&mut *log, SystemLog::in_memory())))
+    }
+
+    // `join`, also returning the threads that panicked (None for the commander) and the log
+    fn collect(self) -> (SimulationResult, Vec<Option<SensorType>>, Arc<Mutex<SystemLog>>) {
+        self.stop(ShutdownReason::DurationElapsed);
+        let total_run_time = self.start_time.elapsed();
+
+        let mut benchmark_stats = BenchmarkStats::new();
+        let mut panicked = Vec::new();
+
+        // --- Watchdog ---
+        let give_up_at = Instant::now() + self.config.shutdown_grace;
+        while !self.handles.iter().all(|(_, _, handle)| handle.is_finished()) && Instant::now() < give_up_at {
+            thread::sleep(STOP_POLL);
+        }
 
-    let commander_stats = commander_handle.join().unwrap_or_else(|_| BenchmarkStats::new());
+        let mut stuck = Vec::new();
+        let mut per_sensor: HashMap<SensorType, BenchmarkStats> = HashMap::new();
+        for (name, sensor_type, handle) in self.handles {
+            if !handle.is_finished() {
+                stuck.push(name); // Detached: it keeps running, but nobody waits for it
+                continue;
+            }
+            match handle.join() {
+                Ok(stats) => {
+                    benchmark_stats.merge(&stats);
+                    if let Some(s_type) = sensor_type {
+                        per_sensor.entry(s_type).or_default().merge(&stats);
+                    }
+                }
+                Err(_) => panicked.push(sensor_type),
+            }
+        }
+        if !stuck.is_empty() {
+            watchdog_fired(&self.system_log, self.config.shutdown_grace, &stuck, &benchmark_stats);
+        }
+
+        // Every sensor has written its final offset by now
+        if let Some(path) = &self.config.calibration_file {
+            if let Err(e) = self.calibration.save(path) {
+                if let Ok(mut log) = self.system_log.lock() {
+                    log.write_level(LogLevel::Warn, format!("[Calibration] Cannot save {:?}: {}", path, e));
+                }
+            }
+        }
 
-    let motor_stats = motor_handle.join().unwrap_or_else(|_| BenchmarkStats::new());
-    let stabiliser_stats = stabiliser_handle.join().unwrap_or_else(|_| BenchmarkStats::new());
-    let gripper_stats = gripper_handle.join().unwrap_or_else(|_| BenchmarkStats::new());
+        if let Ok(mut log) = self.system_log.lock() {
+            if let Err(e) = log.flush() {
+                log.write_level(LogLevel::Warn, format!("[Log] Cannot flush the system log: {}", e));
+            }
+        }
+
+        let (shutdown_reason, log) = match self.system_log.lock() {
+            Ok(log) => (log.shutdown_reason().unwrap_or(ShutdownReason::DurationElapsed), log.recent_entries()),
+            Err(_) => (ShutdownReason::DurationElapsed, Vec::new()),
+        };
+        let shutdown_reason = if !stuck.is_empty() {
+            ShutdownReason::WatchdogTimeout(stuck)
+        } else if !panicked.is_empty() {
+            ShutdownReason::ThreadPanicked
+        } else {
+            shutdown_reason
+        };
 
-    benchmark_stats.merge(&temp_stats);
-    benchmark_stats.merge(&pos_stats);
-    benchmark_stats.merge(&force_stats);
-    benchmark_stats.merge(&commander_stats);
-    benchmark_stats.merge(&motor_stats);
-    benchmark_stats.merge(&stabiliser_stats);
-    benchmark_stats.merge(&gripper_stats);
+        // Report everything in simulated time
+        let step_responses: Vec<StepResponse> = self.snapshot.step_responses().into_iter().map(|r| StepResponse {
+            at: r.at.mul_f64(self.config.time_scale),
+            settling_time: r.settling_time.map(|t| t.mul_f64(self.config.time_scale)),
+            ..r
+        }).collect();
+        let mut per_sensor: HashMap<SensorType, BenchmarkStats> = per_sensor.into_iter()
+            .map(|(s_type, stats)| (s_type, stats.to_simulated(self.config.time_scale))).collect();
+        let mut stats = benchmark_stats.to_simulated(self.config.time_scale);
+        for response in &step_responses {
+            stats.record_step(response);
+            if let Some(sensor_stats) = per_sensor.get_mut(&response.sensor_type) {
+                sensor_stats.record_step(response);
+            }
+        }
 
-    print_report(benchmark_stats, total_run_time);
+        let result = SimulationResult {
+            stats,
+            per_sensor,
+            shutdown_reason,
+            total_run_time: total_run_time.mul_f64(self.config.time_scale),
+            log,
+            dead_letters: self.dead_letters,
+            recording: self.recorder.map(|r| r.recording()),
+            step_responses,
+        };
+        (result, panicked, self.system_log)
+    }
 
+    // Stops the simulation (if not already stopped), joins every thread and prints the report
+    pub fn finish(self) -> (BenchmarkStats, ShutdownReason) {
+        self.stop(ShutdownReason::DurationElapsed);
+
+        // Optional: Wait a tiny bit for threads to see the flag and clean up
+        thread::sleep(Duration::from_millis(1000));
+
+        if self.config.verbosity > Verbosity::Silent {
+            println!("--- Simulation Finished ---");
+        }
+
+        let verbose = self.config.verbosity > Verbosity::Silent;
+        let result = self.join();
+        if verbose {
+            print_report(result.stats, result.total_run_time, &result.shutdown_reason);
+            print_sensor_comparison(&result.per_sensor);
+            print_step_responses(&result.step_responses);
+            print_dead_letters(&result.dead_letters);
+        }
+
+        (result.stats, result.shutdown_reason)
+    }
+}
+
+// Everything `SimulationHandle::join` collects once the threads are done
+pub struct SimulationResult {
+    pub stats: BenchmarkStats,
+    pub per_sensor: HashMap<SensorType, BenchmarkStats>, // Each sensor merged with its actuator; the commander's stats are only in `stats`
+    pub shutdown_reason: ShutdownReason,
+    pub total_run_time: Duration,
+    pub log: Vec<String>, // Final system log entries, oldest first
+    pub dead_letters: DeadLetterLog,
+    pub recording: Option<Recording>, // Commander inputs and commands when `SimulationConfig::record` is set
+    pub step_responses: Vec<StepResponse>, // One per setpoint step, oldest first
 }
 
-pub fn print_report(benchmark_stats: BenchmarkStats, total_run_time: Duration){
-    println!("\n  Total Run Time:    {:.2?}", total_run_time);
+pub fn print_report(benchmark_stats: BenchmarkStats, total_run_time: Duration, shutdown_reason: &ShutdownReason){
+    println!("\n  Health Score:      {:.1} / 100", benchmark_stats.health_score());
+    println!("  Total Run Time:    {:.2?}", total_run_time);
+    println!("  Shutdown Reason:   {:?}", shutdown_reason);
+    let modes = &benchmark_stats.time_in_mode;
+    println!("  Time in Mode:      Normal {:.1}%, Degraded {:.1}%, EmergencyStop {:.1}%",
+             modes.percent(SystemMode::Normal), modes.percent(SystemMode::Degraded), modes.percent(SystemMode::EmergencyStop));
+    println!("  Log Lock Wait:     {:.2?} total, {:.2?} max", benchmark_stats.total_lock_wait, benchmark_stats.max_lock_wait);
+    if benchmark_stats.dropped_logs > 0 {
+        println!("  Dropped Log Lines: {} (log busy)", benchmark_stats.dropped_logs);
+    }
+    if benchmark_stats.rt_threads > 0 {
+        println!("  RT Scheduling:     applied to {} threads, compare the jitter below with a run without it", benchmark_stats.rt_threads);
+    }
     println!("\n===== Sensor Summary =====");
     println!("  Total Cycles:      {}", benchmark_stats.sensor_count);
     println!("  Throughput:        {:.2} pkts/sec", benchmark_stats.throughput(total_run_time));
     println!("  Missed Deadlines:  {} ({:.2}%)", benchmark_stats.sensor_missed_deadlines, benchmark_stats.sensor_deadline_rate());
+    println!("    by Transmission: Force {}, Position {}, Temperature {}",
+             benchmark_stats.transmission_misses.force, benchmark_stats.transmission_misses.position, benchmark_stats.transmission_misses.temperature);
     println!("  Total Generation:  {:.2?}", benchmark_stats.total_gen_time);
     println!("  Total Processing:  {:.2?}", benchmark_stats.total_proc_time);
+    if cpu_time::supported() {
+        println!("    on the CPU:      {:.2?}, the rest descheduled", benchmark_stats.cpu_proc_time);
+    } else {
+        println!("    on the CPU:      no thread CPU clock here, same as wall time");
+    }
     println!("  Total Transmit:    {:.2?}", benchmark_stats.total_trans_time);
     println!("  Avg Generation:    {:.2?}", benchmark_stats.avg_gen());
     println!("  Avg Processing:    {:.2?}", benchmark_stats.avg_proc());
     println!("  Avg Transmit:      {:.2?}", benchmark_stats.avg_trans());
     println!("  Avg Jitter:        {:.2?} (Max: {:?})", benchmark_stats.avg_jitter(), benchmark_stats.max_jitter);
+    let period = benchmark_stats.avg_sample_period();
+    let rate = if period.is_zero() { 0.0 } else { 1.0 / period.as_secs_f64() };
+    println!("  Avg Sample Period: {:.2?} ({:.1} Hz per sensor)", period, rate);
+    if benchmark_stats.backpressure_slowdowns > 0 {
+        println!("  Backpressure:      {} slowdowns, cycle up to {:.2?}", benchmark_stats.backpressure_slowdowns, benchmark_stats.max_sample_period);
+    }
+    println!("  Max Feedback Gap:  Force {:.2?}, Position {:.2?}, Temperature {:.2?}",
+             benchmark_stats.max_feedback_gap.force, benchmark_stats.max_feedback_gap.position, benchmark_stats.max_feedback_gap.temperature);
+    if benchmark_stats.roundtrip_count > 0 {
+        println!("  Avg Round Trip:    {:.2?} (Max: {:.2?}, {} feedbacks)", benchmark_stats.avg_roundtrip(), benchmark_stats.max_roundtrip, benchmark_stats.roundtrip_count);
+    }
+
+    println!("\n===== Cycle Budget =====");
+    println!("  Legend:       G generation, P processing, T transmission, . idle");
+    for &s_type in SensorType::all() {
+        let stages = benchmark_stats.stage_times.get(s_type);
+        if stages.budget.as_nanos() == 0 { continue; }
+        let shares = [stages.generation, stages.processing, stages.transmission].map(|t| stages.percent(t));
+        println!("  {:<12} [{}] gen {:.1}%, proc {:.1}%, trans {:.1}%, idle {:.1}%",
+                 format!("{:?}", s_type), budget_bar(shares, 40), shares[0], shares[1], shares[2], stages.percent(stages.idle()));
+    }
 
     println!("\n===== Actuator Summary =====");
     println!("  Total Cycles:         {}", benchmark_stats.actuator_count);
     println!("  Throughput:           {:.2} pkts/sec", benchmark_stats.actuator_count as f64 / total_run_time.as_secs_f64());
     println!("  Missed Deadlines:     {} ({:.2}%)", benchmark_stats.actuator_missed_deadlines, benchmark_stats.actuator_deadline_rate());
+    println!("  Feedback Drops:       {}", benchmark_stats.feedback_drops);
+    println!("  Drift Corrections:    {}", benchmark_stats.drift_corrections);
+    println!("  Saturated Commands:   {}", benchmark_stats.actuator_saturations);
     println!("  Total Execution Time: {:.2?}", benchmark_stats.total_actuator_time);
     println!("  Avg Execution Time:   {:.2?}", benchmark_stats.avg_actuator());
+    println!("  Commander Overruns:   {}", benchmark_stats.commander_overruns);
+    println!("  Suppressed Commands:  {}", benchmark_stats.suppressed_commands);
+    if benchmark_stats.invalid_samples > 0 {
+        println!("  Invalid Samples:      {} (rejected before the PID)", benchmark_stats.invalid_samples);
+    }
     println!("  Total E2E Latency:    {:.2?}", benchmark_stats.total_latency);
     println!("  Avg E2E Latency:      {:.2?}", benchmark_stats.avg_latency());
+    println!("  E2E Deadline Misses:  {} ({:.2}%)", benchmark_stats.e2e_deadline_misses, benchmark_stats.e2e_deadline_rate());
     println!("  Avg Jitter:           {:.2?} (Max: {:?})", benchmark_stats.avg_at_jitter(),benchmark_stats.max_at_jitter);
 
-}
\ No newline at end of file
+    if let Some(worst) = &benchmark_stats.worst_cycle {
+        println!("\n===== Worst Cycle =====");
+        println!("  {:?} sample {}: {:.2?} end to end", worst.sensor_type, worst.id, worst.total);
+        for (stage, spent) in worst.stages() {
+            let share = if worst.total.is_zero() { 0.0 } else { spent.as_secs_f64() / worst.total.as_secs_f64() * 100.0 };
+            println!("  {:<12} {:>10.2?} ({:.1}%)", stage, spent, share);
+        }
+    }
+
+    if !benchmark_stats.stability_warning.is_empty() {
+        println!("\n===== Stability Warnings =====");
+        for &s_type in SensorType::all() {
+            if benchmark_stats.stability_warning.contains(s_type) {
+                println!("  {:?}: effort oscillation detected, consider lowering the gains", s_type);
+            }
+        }
+    }
+
+    if !benchmark_stats.faulted_actuators.is_empty() {
+        println!("\n===== Faulted Actuators =====");
+        for &s_type in SensorType::all() {
+            if benchmark_stats.faulted_actuators.contains(s_type) {
+                println!("  {:?}: stopped accepting commands, later commands were dropped", s_type);
+            }
+        }
+    }
+
+    if !benchmark_stats.feedback_starved.is_empty() {
+        println!("\n===== Feedback Starvation =====");
+        for &s_type in SensorType::all() {
+            if benchmark_stats.feedback_starved.contains(s_type) {
+                println!("  {:?}: no feedback for {:.2?}, calibration loop is effectively dead", s_type, benchmark_stats.max_feedback_gap.get(s_type));
+            }
+        }
+    }
+}
+
+// ASCII bar for the generation/processing/transmission shares, idle shown as '.'.
+// Any stage that ran at all gets at least one cell so it stays visible.
+fn budget_bar(shares: [f64; 3], width: usize) -> String {
+    let mut cells: Vec<usize> = shares.iter().map(|p| if *p > 0.0 { ((p / 100.0 * width as f64).round() as usize).max(1) } else { 0 }).collect();
+    while cells.iter().sum::<usize>() > width {
+        if let Some(widest) = cells.iter_mut().max() { *widest -= 1; }
+    }
+    let mut bar = String::with_capacity(width);
+    for (symbol, count) in ['G', 'P', 'T'].into_iter().zip(&cells) {
+        bar.extend(std::iter::repeat_n(symbol, *count));
+    }
+    let used = bar.len();
+    bar.extend(std::iter::repeat_n('.', width - used));
+    bar
+}
+
+// One row per sensor type, for comparing them side by side
+pub fn print_sensor_comparison(per_sensor: &HashMap<SensorType, BenchmarkStats>) {
+    if per_sensor.is_empty() { return; }
+
+    println!("\n===== Per-Sensor Comparison =====");
+    for line in sensor_comparison_rows(per_sensor) {
+        println!("{}", line);
+    }
+}
+
+// Header line, then one line per sensor type that reported stats
+fn sensor_comparison_rows(per_sensor: &HashMap<SensorType, BenchmarkStats>) -> Vec<String> {
+    let mut lines = vec![format!("  {:<12} {:>8} {:>10} {:>10} {:>10} {:>10} {:>10} {:>13} {:>13} {:>12} {:>8} {:>10} {:>10}", "Sensor", "Samples", "Active", "Rate/s", "Min", "Max", "Anomalies", "Mean Latency", "P99 Latency", "Jitter SD", "Misses", "Settling", "Overshoot")];
+    for s_type in SensorType::all() {
+        let Some(stats) = per_sensor.get(s_type) else { continue; };
+        let (min, max) = stats.value_range.map_or(("-".to_string(), "-".to_string()), |(min, max)| (format!("{:.3}", min), format!("{:.3}", max)));
+        // Worst step response; "-" without setpoint steps
+        let settling = stats.step_settling.map_or("-".to_string(), |t| format!("{:.2?}", t));
+        let overshoot = stats.step_overshoot.map_or("-".to_string(), |o| format!("{:.1}%", o));
+        lines.push(format!("  {:<12} {:>8} {:>10.2?} {:>10.1} {:>10} {:>10} {:>10} {:>13.2?} {:>13.2?} {:>12.2?} {:>8} {:>10} {:>10}",
+                 format!("{:?}", s_type), stats.sensor_count, stats.active_duration, stats.sample_rate(), min, max, stats.anomaly_count, stats.avg_latency(), stats.p99_latency(),
+                 stats.jitter_std_dev(), stats.sensor_missed_deadlines + stats.actuator_missed_deadlines + stats.e2e_deadline_misses, settling, overshoot));
+    }
+    lines
+}
+
+// Nothing is printed for a run without setpoint steps
+pub fn print_step_responses(responses: &[StepResponse]) {
+    if responses.is_empty() { return; }
+    println!("\n===== Step Responses =====");
+    println!("  {:<12} {:>9} {:>17} {:>10} {:>10} {:>11}", "Sensor", "At", "Step", "Overshoot", "Settling", "SS Error");
+    for r in responses {
+        let settling = r.settling_time.map_or("never".to_string(), |t| format!("{:.2?}", t));
+        println!("  {:<12} {:>9.2?} {:>17} {:>9.1}% {:>10} {:>11.3}",
+                 format!("{:?}", r.sensor_type), r.at, format!("{} -> {}", r.from, r.to), r.overshoot, settling, r.steady_state_error);
+    }
+}
+
+pub fn print_dead_letters(dead_letters: &DeadLetterLog) {
+    let counts = dead_letters.counts();
+    if counts.is_empty() { return; }
+
+    println!("\n===== Dropped Samples =====");
+    println!("  Total:             {}", dead_letters.total());
+    let mut by_reason: Vec<_> = counts.into_iter().collect();
+    by_reason.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    for (reason, count) in by_reason {
+        println!("  {:<19}{}", format!("{:?}:", reason), count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet(stop_after: StopCondition) -> SimulationConfig {
+        SimulationConfig { stop_after, verbosity: Verbosity::Silent, ..SimulationConfig::default() }
+    }
+
+    #[test]
+    fn invalid_config_is_rejected_before_anything_starts() {
+        let config = SimulationConfig { time_scale: 0.0, ..quiet(StopCondition::Duration(Duration::from_millis(100))) };
+        match start_simulation(config.clone()) {
+            Err(ShutdownReason::InvalidConfig(msg)) => assert!(msg.contains("time_scale"), "{}", msg),
+            Err(other) => panic!("rejected for the wrong reason: {:?}", other),
+            Ok(_) => panic!("started with a zero time scale"),
+        }
+        assert!(matches!(run_simulation_async(config).1, ShutdownReason::InvalidConfig(_)));
+    }
+
+    #[test]
+    fn start_failures_keep_their_own_error() {
+        assert!(matches!(SimError::from(ShutdownReason::SelfTestFailed("x".to_string())), SimError::SelfTest(_)));
+        assert!(matches!(SimError::from(ShutdownReason::RuntimeUnavailable("x".to_string())), SimError::RuntimeUnavailable(_)));
+        assert!(matches!(SimError::from(ShutdownReason::ChannelDisconnected), SimError::ChannelDisconnected));
+        assert!(matches!(SimError::from(ShutdownReason::InvariantViolation("x".to_string())), SimError::InvariantViolation(_)));
+        match SimError::from(ShutdownReason::InvalidConfig("time_scale must be a positive number".to_string())) {
+            SimError::Config(ConfigError::Invalid(msg)) => assert_eq!(msg, "time_scale must be a positive number"),
+            other => panic!("mislabelled: {:?}", other),
+        }
+
+        let config = SimulationConfig { time_scale: -1.0, ..quiet(StopCondition::Duration(Duration::from_millis(100))) };
+        assert!(matches!(try_run_simulation(config), Err(SimError::Config(ConfigError::Invalid(_)))));
+    }
+
+    #[test]
+    fn panicking_thread_comes_back_as_an_error() {
+        let mut handle = start_simulation(quiet(StopCondition::Duration(Duration::from_secs(5)))).expect("started");
+        // An empty range panics the added sensor's thread on its first reading
+        let broken = SensorProfile { range: (10.0, 0.0), ..share::default_profile(SensorType::Position) };
+        assert!(handle.add_sensor(SensorType::Position, broken));
+        thread::sleep(Duration::from_millis(50));
+
+        match handle.try_join() {
+            Err(SimError::ThreadPanicked(SensorType::Position)) => {}
+            other => panic!("expected a Position thread panic, got {:?}", other.map(|(stats, _)| stats.sensor_count)),
+        }
+    }
+
+    #[test]
+    fn injected_anomalies_escalate_to_emergency_stop() {
+        let config = SimulationConfig { fault_rates: FaultRates::none(), ..quiet(StopCondition::Duration(Duration::from_secs(5))) };
+        let handle = start_simulation(config).unwrap();
+        assert!(handle.inject_fault(SensorType::Force, Fault::Anomaly(10)));
+        handle.wait(); // Returns early once the E-STOP latches
+
+        let transitions = handle.mode_transitions();
+        let result = handle.join();
+        assert_eq!(result.shutdown_reason, ShutdownReason::EmergencyStop);
+        let last = transitions.last().expect("no mode transition");
+        assert_eq!((last.to, last.triggering_sensor), (SystemMode::EmergencyStop, SensorType::Force));
+        assert!(result.total_run_time < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn learned_offsets_seed_the_next_run() {
+        let path = std::env::temp_dir().join(format!("rts_calibration_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = SimulationConfig {
+            calibration_file: Some(path.clone()),
+            fault_rates: FaultRates::none(),
+            ..quiet(StopCondition::Duration(Duration::from_millis(500)))
+        };
+        let (_, reason) = run_simulation_with(config);
+        assert_eq!(reason, ShutdownReason::DurationElapsed);
+
+        // About 100 actuations per sensor, each with a 5% chance of a drift correction
+        let saved = CalibrationStore::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let offsets: Vec<f64> = SensorType::all().iter().map(|s| saved.get(*s).expect("offset not saved")).collect();
+        assert!(offsets.iter().any(|o| *o != 0.0), "nothing was learned: {:?}", offsets);
+
+        // The next run's sensors start where this one stopped
+        for (s_type, offset) in SensorType::all().iter().zip(offsets) {
+            let sensor = Sensor::new(*s_type, Arc::new(Mutex::new(SystemLog::in_memory()))).with_calibration(saved.clone());
+            assert_eq!(sensor.calibration_offset(), offset);
+        }
+    }
+
+    #[test]
+    fn async_sensors_save_and_reload_their_offsets() {
+        let path = std::env::temp_dir().join(format!("rts_calibration_async_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = SimulationConfig {
+            calibration_file: Some(path.clone()),
+            fault_rates: FaultRates::none(),
+            ..quiet(StopCondition::Duration(Duration::from_millis(300)))
+        };
+        let (_, reason) = run_simulation_async(config);
+        assert_eq!(reason, ShutdownReason::DurationElapsed);
+
+        let saved = CalibrationStore::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for s_type in SensorType::all() {
+            let offset = saved.get(*s_type).expect("offset not saved");
+            let sensor = SensorAsync::new(*s_type, Arc::new(tokio::sync::Mutex::new(SystemLog::in_memory()))).with_calibration(saved.clone());
+            assert_eq!(sensor.calibration_offset(), offset);
+        }
+    }
+
+    #[test]
+    fn join_after_stop_returns_the_merged_stats_and_log() {
+        let handle = start_simulation(quiet(StopCondition::Duration(Duration::from_secs(30)))).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        handle.stop(ShutdownReason::DurationElapsed);
+        let result = handle.join();
+
+        assert!(result.total_run_time < Duration::from_secs(10), "join waited for the configured duration");
+        assert!(result.stats.sensor_count > 0 && result.stats.actuator_count > 0);
+        assert_eq!(result.per_sensor.len(), 3);
+        assert!(result.per_sensor.values().all(|s| s.sensor_count > 0));
+        assert!(result.log.iter().any(|l| l.contains("[Shutdown]")));
+    }
+
+    #[test]
+    fn sample_limit_stops_both_backends_near_the_target() {
+        let config = SimulationConfig { fault_rates: FaultRates::none(), ..quiet(StopCondition::Samples(100)) };
+        for (stats, reason) in [run_simulation_with(config.clone()), run_simulation_async(config)] {
+            assert_eq!(reason, ShutdownReason::SampleLimitReached);
+            assert!((95..=100).contains(&stats.sensor_count), "{} samples", stats.sensor_count);
+        }
+    }
+
+    #[test]
+    fn added_sensor_reaches_the_commander() {
+        let config = SimulationConfig { fault_rates: FaultRates::none(), ..quiet(StopCondition::Duration(Duration::from_secs(5))) };
+        let mut handle = start_simulation(config).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        // A second Position probe, since no new type can be added (see `add_sensor`); it is
+        // told apart by readings the built-in Position sensor, at most 0.2, never produces
+        let probe = SensorProfile { range: (0.4, 0.45), ..share::default_profile(SensorType::Position) };
+        assert!(handle.add_sensor(SensorType::Position, probe));
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut seen = false;
+        while !seen && Instant::now() < deadline {
+            seen = handle.snapshot().last_values.get(&SensorType::Position).is_some_and(|v| *v >= 0.4);
+            thread::sleep(Duration::from_millis(1));
+        }
+        handle.stop(ShutdownReason::DurationElapsed);
+        handle.join();
+        assert!(seen, "no sample of the added sensor reached the commander");
+    }
+
+    #[test]
+    fn watchdog_gives_up_on_a_stuck_actuator() {
+        // Every command keeps its actuator busy far past the end of the run
+        let deadlines = Deadlines { actuation_work: Duration::from_secs(3), actuation: Duration::from_secs(5), ..Deadlines::default() };
+        let config = SimulationConfig {
+            deadlines,
+            shutdown_grace: Duration::from_millis(200),
+            ..quiet(StopCondition::Duration(Duration::from_millis(100)))
+        };
+        let started = Instant::now();
+        let handle = start_simulation(config).unwrap();
+        handle.wait(); // Let commands reach the actuators before stopping
+        let result = handle.join();
+
+        assert!(started.elapsed() < Duration::from_secs(2), "join waited for the stuck actuators");
+        match &result.shutdown_reason {
+            ShutdownReason::WatchdogTimeout(stuck) => {
+                for actuator in ["Motor", "Stabiliser", "Gripper"] {
+                    assert!(stuck.iter().any(|name| name == actuator), "{} not reported in {:?}", actuator, stuck);
+                }
+                assert!(!stuck.iter().any(|name| name.contains("Sensor")), "{:?}", stuck);
+            }
+            other => panic!("expected a watchdog timeout, got {:?}", other),
+        }
+        assert!(result.log.iter().any(|l| l.contains("Watchdog: shutdown did not complete")));
+    }
+
+    #[test]
+    fn comparison_table_has_one_row_per_sensor() {
+        let handle = start_simulation(quiet(StopCondition::Duration(Duration::from_millis(100)))).unwrap();
+        handle.wait();
+        let mut per_sensor = handle.join().per_sensor;
+        let rows = sensor_comparison_rows(&per_sensor);
+        assert_eq!(rows.len(), 1 + 3);
+        for (row, s_type) in rows[1..].iter().zip(SensorType::all()) {
+            assert!(row.trim_start().starts_with(&format!("{:?}", s_type)), "{}", row);
+        }
+
+        per_sensor.remove(&SensorType::Position); // A sensor that reported nothing gets no row
+        assert_eq!(sensor_comparison_rows(&per_sensor).len(), 1 + 2);
+    }
+
+    #[test]
+    fn batched_and_unbatched_runs_process_the_same_samples() {
+        let processed = |batch_size| {
+            // A loaded machine must not drop a late sample either
+            let mut deadlines = Deadlines::default();
+            deadlines.policies.processing = share::DeadlinePolicy::MarkAndContinue;
+            let config = SimulationConfig {
+                batch_size,
+                record: true,
+                deadlines,
+                fault_rates: FaultRates::none(),
+                ..quiet(StopCondition::Samples(300))
+            };
+            let handle = start_simulation(config).unwrap();
+            handle.wait();
+            let recording = handle.join().recording.expect("recorded");
+            recording.steps.len()
+        };
+        // 300 is not a multiple of 7, so the last batch goes out half full at shutdown
+        assert_eq!(processed(1), 300);
+        assert_eq!(processed(7), 300);
+    }
+}
@@ -1,166 +1,156 @@
-
-use std::collections::HashMap;
-use tokio::sync::Mutex;
-use std::sync::Arc;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::time::Duration;
-use tokio::sync::mpsc;
-
-use rts_assignment::share::{BenchmarkStats, SensorType, SystemLog};
-use rts_assignment::sensor_async::SensorAsync;
-use rts_assignment::actuator_async::ActuatorAsync;
-use rts_assignment::actuator_commander_async::ActuatorCommanderAsync;
-use rts_assignment::print_report;
-
-// fn main() {
-//
-//     // Run for the full 2 seconds required for the demo
-//     run_simulation(Duration::from_secs(2));
-//
-// }
-
-
-
-
-//===============================Tokio Main===========================
-#[tokio::main]
-async fn main() {
-    println!("=== Starting Async Real-Time Simulation (Tokio) ===");
-
-    // 1. Setup Shared Logging
-    let system_log = Arc::new(Mutex::new(SystemLog::new()));
-    let mut benchmark_stats = BenchmarkStats::new();
-
-    // 2. Create Channels (Buffer size 32 is sufficient for real-time)
-    // SENSORS -> COMMANDER
-    let (tx_force, rx_force) = mpsc::channel(32);
-    let (tx_pos, rx_pos) = mpsc::channel(32);
-    let (tx_temp, rx_temp) = mpsc::channel(32);
-
-    // COMMANDER -> ACTUATORS
-    let (at_tx_force, at_rx_force) = mpsc::channel(32);
-    let (at_tx_pos, at_rx_pos) = mpsc::channel(32);
-    let (at_tx_temp, at_rx_temp) = mpsc::channel(32);
-
-    // ACTUATORS -> SENSORS (Feedback Loop)
-    let (fb_tx_force, fb_rx_force) = mpsc::channel(32);
-    let (fb_tx_pos, fb_rx_pos) = mpsc::channel(32);
-    let (fb_tx_temp, fb_rx_temp) = mpsc::channel(32);
-
-    // Map needed for Commander initialization
-    let mut actuator_tx_map = HashMap::new();
-    actuator_tx_map.insert(SensorType::Force, at_tx_force);
-    actuator_tx_map.insert(SensorType::Position, at_tx_pos);
-    actuator_tx_map.insert(SensorType::Temperature, at_tx_temp);
-
-    // 3. Initialize Components
-    let sensor_force = SensorAsync::new(SensorType::Force, system_log.clone());
-    let sensor_pos = SensorAsync::new(SensorType::Position, system_log.clone());
-    let sensor_temp = SensorAsync::new(SensorType::Temperature, system_log.clone());
-
-    let commander = ActuatorCommanderAsync::new(actuator_tx_map, system_log.clone());
-
-    let actuator_force = ActuatorAsync::new("Gripper".to_string(), SensorType::Force, system_log.clone());
-    let actuator_pos = ActuatorAsync::new("Stabiliser".to_string(), SensorType::Position, system_log.clone());
-    let actuator_temp = ActuatorAsync::new("Cooling".to_string(), SensorType::Temperature, system_log.clone());
-
-    let start_time = std::time::Instant::now();
-
-    // 4. Spawn Async Tasks
-    // We use join handles to collect the stats at the end
-    let h_s_force = tokio::spawn(async move { sensor_force.run(tx_force, fb_rx_force).await });
-    let h_s_pos = tokio::spawn(async move { sensor_pos.run(tx_pos, fb_rx_pos).await });
-    let h_s_temp = tokio::spawn(async move { sensor_temp.run(tx_temp, fb_rx_temp).await });
-
-    let mut h_commander = tokio::spawn(async move { commander.run(rx_force, rx_pos, rx_temp).await });
-
-    let h_a_force = tokio::spawn(async move { actuator_force.run(at_rx_force, fb_tx_force).await });
-    let h_a_pos = tokio::spawn(async move { actuator_pos.run(at_rx_pos, fb_tx_pos).await });
-    let h_a_temp = tokio::spawn(async move { actuator_temp.run(at_rx_temp, fb_tx_temp).await });
-
-    // 5. Run Simulation Duration
-    println!("Simulation Running...");
-    tokio::time::sleep(Duration::from_secs(2)).await;
-    {
-        let mut log = system_log.lock().await;
-        log.active = false; // This tells everyone to break their loops
-        log.write("--- Stopping Simulation ---".to_string());
+
+use rts_assignment::{print_dead_letters, print_report, print_sensor_comparison, print_step_responses, run_simulation_async, start_simulation, verify_replay, Recording, ShutdownReason, SimulationConfig, StopCondition};
+
+const USAGE: &str = "\
+usage:
+  rts_assignment run [--duration <secs> | --samples <n>] [--backend threaded|async] [--config <file.json>] [--record <file.csv>]
+  rts_assignment bench [--runs <n>] [--duration <secs> | --samples <n>] [--config <file.json>]
+  rts_assignment replay <file.csv>
+exit status: 1 on bad arguments, 2 if an invariant was violated, 3 if a replay diverged,
+             4 if threads were still running after the shutdown grace period";
+
+enum Backend { Threaded, Async }
+
+// Options shared by `run` and `bench`
+struct Options {
+    config: SimulationConfig,
+    backend: Backend,
+    record: Option<PathBuf>,
+    runs: usize,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("run") => parse_options(&args[1..]).and_then(run),
+        Some("bench") => parse_options(&args[1..]).and_then(bench),
+        Some("replay") => match &args[1..] {
+            [file] => replay(Path::new(file)),
+            _ => Err("replay takes exactly one recording file".to_string()),
+        },
+        None => parse_options(&[]).and_then(run), // Plain `cargo run` keeps running the demo
+        Some(other) => Err(format!("unknown subcommand {:?}", other)),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(msg) => {
+            eprintln!("error: {}\n{}", msg, USAGE);
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options { config: SimulationConfig::default(), backend: Backend::Threaded, record: None, runs: 5 };
+    let mut stop_after = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", flag));
+        match flag.as_str() {
+            "--duration" => {
+                let secs: f64 = value()?.parse().map_err(|_| "--duration takes seconds, e.g. 2 or 0.5".to_string())?;
+                let duration = Duration::try_from_secs_f64(secs).map_err(|_| "--duration must be a positive number".to_string())?;
+                stop_after = Some(StopCondition::Duration(duration));
+            }
+            "--samples" => stop_after = Some(StopCondition::Samples(value()?.parse().map_err(|_| "--samples takes a number".to_string())?)),
+            "--backend" => options.backend = match value()?.as_str() {
+                "threaded" => Backend::Threaded,
+                "async" => Backend::Async,
+                other => return Err(format!("unknown backend {:?}", other)),
+            },
+            "--config" => options.config = load_config(Path::new(value()?))?,
+            "--record" => options.record = Some(PathBuf::from(value()?)),
+            "--runs" => options.runs = value()?.parse().map_err(|_| "--runs takes a number".to_string())?,
+            other => return Err(format!("unknown option {:?}", other)),
+        }
+    }
+
+    // Flags override the config file regardless of their order
+    if let Some(stop_after) = stop_after {
+        options.config.stop_after = stop_after;
+    }
+    options.config.record = options.record.is_some();
+    options.config.validate().map_err(|e| e.to_string())?;
+    Ok(options)
+}
+
+#[cfg(feature = "serde")]
+fn load_config(path: &Path) -> Result<SimulationConfig, String> {
+    SimulationConfig::from_json_file(path).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_config(_path: &Path) -> Result<SimulationConfig, String> {
+    Err("config files need the `serde` feature".to_string())
+}
+
+// Exit status of a finished run: 2 when an invariant was violated, 4 when the watchdog fired
+fn exit_code(reason: &ShutdownReason) -> ExitCode {
+    match reason {
+        ShutdownReason::InvariantViolation(_) => ExitCode::from(2),
+        ShutdownReason::WatchdogTimeout(_) => ExitCode::from(4),
+        _ => ExitCode::SUCCESS,
     }
-    tokio::time::sleep(Duration::from_secs(2)).await;
-
-    let total_run_time = start_time.elapsed();
-
-    let temp_stats = h_s_force.await.unwrap();
-    let pos_stats = h_s_pos.await.unwrap();
-    let force_stats = h_s_temp.await.unwrap();
-
-    let commander_stats = h_commander.await.unwrap();
-    let motor_stats = h_a_force.await.unwrap();
-    let stabiliser_stats = h_a_pos.await.unwrap();
-    let gripper_stats = h_a_temp.await.unwrap();
-
-    benchmark_stats.merge(&temp_stats);
-    benchmark_stats.merge(&pos_stats);
-    benchmark_stats.merge(&force_stats);
-    benchmark_stats.merge(&commander_stats);
-    benchmark_stats.merge(&motor_stats);
-    benchmark_stats.merge(&stabiliser_stats);
-    benchmark_stats.merge(&gripper_stats);
-
-    println!("--- Simulation Finished (Async) ---");
-    // (In a real implementation, you would signal cancellation here to let threads return)
-    print_report(benchmark_stats, total_run_time);
 }
 
-// #[tokio::main]
-// async fn main() {
-//     println!("=== System Start ===");
-//     let log = Arc::new(Mutex::new(SystemLog::new()));
-//
-//     // 1. Create THREE distinct channels (one for each sensor type)
-//     let (tx_force, rx_force) = mpsc::channel(100);
-//     let (tx_pos, rx_pos) = mpsc::channel(100);
-//     let (tx_temp, rx_temp) = mpsc::channel(100);
-//
-//     // 2. Feedback Channel (Broadcast)
-//     let (tx_feedback, _) = broadcast::channel(100);
-//
-//     // 3. Spawn Sensors (Each gets its own specific TX channel)
-//
-//     // --- Sensor 1: Force ---
-//     let s1_fb = tx_feedback.subscribe();
-//     let s1_log = log.clone();
-//     tokio::spawn(async move {
-//         run_sensor(SensorType::Force, tx_force, s1_fb, s1_log).await;
-//     });
-//
-//     // --- Sensor 2: Position ---
-//     let s2_fb = tx_feedback.subscribe();
-//     let s2_log = log.clone();
-//     tokio::spawn(async move {
-//         run_sensor(SensorType::Position, tx_pos, s2_fb, s2_log).await;
-//     });
-//
-//     // --- Sensor 3: Temperature ---
-//     let s3_fb = tx_feedback.subscribe();
-//     let s3_log = log.clone();
-//     tokio::spawn(async move {
-//         run_sensor(SensorType::Temperature, tx_temp, s3_fb, s3_log).await;
-//     });
-//
-//     // 4. Spawn Actuator Manager
-//     // Pass the 3 distinct receivers to the manager
-//     let act_log = log.clone();
-//     tokio::spawn(async move {
-//         run_actuator(rx_force, rx_pos, rx_temp, tx_feedback, act_log).await;
-//     });
-//
-//     // 5. Run Simulation
-//     time::sleep(Duration::from_secs(2)).await;
-//     println!("=== Test End ===");
-//
-//     // 6. Dump Logs
-//     let log_guard = log.lock().await;
-//     for entry in &log_guard.entries {
-//         println!("LOG: {}", entry);
-//     }
-// }
+fn run(options: Options) -> Result<ExitCode, String> {
+    if let Backend::Async = options.backend {
+        if options.record.is_some() {
+            return Err("--record needs the threaded backend".to_string());
+        }
+        let (_, reason) = run_simulation_async(options.config);
+        return Ok(exit_code(&reason));
+    }
+
+    let handle = start_simulation(options.config).map_err(|reason| format!("simulation did not start: {:?}", reason))?;
+    handle.wait();
+    let result = handle.join();
+    print_report(result.stats, result.total_run_time, &result.shutdown_reason);
+    print_sensor_comparison(&result.per_sensor);
+    print_step_responses(&result.step_responses);
+    print_dead_letters(&result.dead_letters);
+
+    if let (Some(path), Some(recording)) = (&options.record, &result.recording) {
+        recording.save(path).map_err(|e| format!("cannot write {:?}: {}", path, e))?;
+        println!("Recorded {} samples to {:?}", recording.steps.len(), path);
+    }
+    Ok(exit_code(&result.shutdown_reason))
+}
+
+// Repeated threaded runs, one summary line each
+fn bench(options: Options) -> Result<ExitCode, String> {
+    let mut worst = ExitCode::SUCCESS;
+    println!("{:>4} {:>10} {:>14} {:>14} {:>10}  shutdown", "run", "samples", "avg latency", "max jitter", "misses");
+
+    for run in 1..=options.runs.max(1) {
+        let handle = start_simulation(options.config.clone()).map_err(|reason| format!("simulation did not start: {:?}", reason))?;
+        handle.wait();
+        let result = handle.join();
+        let stats = result.stats;
+        println!("{:>4} {:>10} {:>14.2?} {:>14.2?} {:>10}  {:?}",
+                 run, stats.sensor_count, stats.avg_latency(), stats.max_jitter,
+                 stats.sensor_missed_deadlines + stats.actuator_missed_deadlines, result.shutdown_reason);
+        if let ShutdownReason::InvariantViolation(_) | ShutdownReason::WatchdogTimeout(_) = result.shutdown_reason {
+            worst = exit_code(&result.shutdown_reason);
+        }
+    }
+    Ok(worst)
+}
+
+fn replay(path: &Path) -> Result<ExitCode, String> {
+    let recording = Recording::load(path).map_err(|e| format!("cannot read {:?}: {}", path, e))?;
+    let report = verify_replay(&recording);
+
+    println!("Replayed {} samples, {} divergent", report.steps, report.divergences.len());
+    for divergence in report.divergences.iter().take(10) {
+        println!("  #{} {:?} ID {}: recorded {:?}, replayed {:?}",
+                 divergence.index, divergence.sensor_type, divergence.id, divergence.recorded, divergence.replayed);
+    }
+    Ok(if report.is_identical() { ExitCode::SUCCESS } else { ExitCode::from(3) })
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::actuator_commander_multi_thread::ActuatorCommander;
+use crate::share::{SensorData, SensorType, StageStamps, SystemLog, Verbosity};
+
+// One sample as the commander saw it, and the command it produced
+#[derive(Debug, Clone)]
+pub struct RecordedStep {
+    pub arrival: Instant,
+    pub offset: Duration, // Arrival since the commander started
+    pub input: SensorData,
+    pub effort: Option<f64>, // None when the sample was dropped
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub steps: Vec<RecordedStep>,
+}
+
+const RECORDING_HEADER: &str = "offset_ns,sensor,id,value,anomaly,age_ns,transit_ns,capture_ns,effort";
+
+impl Recording {
+    // CSV with every instant stored relative to the step's arrival; empty cells are None
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", RECORDING_HEADER)?;
+        let before = |t: Option<Instant>, arrival: Instant| t.map(|t| arrival.saturating_duration_since(t).as_nanos().to_string()).unwrap_or_default();
+        for step in &self.steps {
+            let input = &step.input;
+            writeln!(out, "{},{:?},{},{},{},{},{},{},{}",
+                step.offset.as_nanos(), input.sensor_type, input.id, input.value, input.anomaly,
+                before(Some(input.timestamp), step.arrival),
+                before(input.processed_timestamp, step.arrival),
+                before(input.capture_time, step.arrival),
+                step.effort.map(|e| e.to_string()).unwrap_or_default())?;
+        }
+        out.flush()
+    }
+
+    // Inverse of `save`; the steps are placed on a timeline starting now
+    pub fn load(path: &Path) -> io::Result<Recording> {
+        let invalid = |line: usize| io::Error::new(io::ErrorKind::InvalidData, format!("malformed recording line {}", line));
+        let base = Instant::now();
+        let mut steps = Vec::new();
+
+        for (index, line) in BufReader::new(File::open(path)?).lines().enumerate().skip(1) {
+            let line = line?;
+            let fields: Vec<&str> = line.split(',').collect();
+            if fields.len() != 9 { return Err(invalid(index + 1)); }
+
+            let nanos = |s: &str| s.parse::<u64>().map(Duration::from_nanos).map_err(|_| invalid(index + 1));
+            let optional = |s: &str| if s.is_empty() { Ok(None) } else { nanos(s).map(Some) };
+            let offset = nanos(fields[0])?;
+            let arrival = base + offset;
+            let earlier = |d: Duration| arrival.checked_sub(d).unwrap_or(arrival);
+            let sensor_type = match fields[1] {
+                "Force" => SensorType::Force,
+                "Position" => SensorType::Position,
+                "Temperature" => SensorType::Temperature,
+                _ => return Err(invalid(index + 1)),
+            };
+
+            let input = SensorData {
+                id: fields[2].parse().map_err(|_| invalid(index + 1))?,
+                sensor_type,
+                value: fields[3].parse().map_err(|_| invalid(index + 1))?,
+                anomaly: fields[4].parse().map_err(|_| invalid(index + 1))?,
+                timestamp: earlier(nanos(fields[5])?),
+                processed_timestamp: optional(fields[6])?.map(earlier),
+                capture_time: optional(fields[7])?.map(earlier),
+                stamps: StageStamps::default(),
+            };
+            let effort = if fields[8].is_empty() { None } else { Some(fields[8].parse().map_err(|_| invalid(index + 1))?) };
+            steps.push(RecordedStep { arrival, offset, input, effort });
+        }
+        Ok(Recording { steps })
+    }
+}
+
+// Shared sink the commander appends to in record mode. Unbounded, so meant for
+// short runs.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    steps: Arc<Mutex<Vec<RecordedStep>>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn record(&self, step: RecordedStep) {
+        if let Ok(mut steps) = self.steps.lock() {
+            steps.push(step);
+        }
+    }
+
+    // Copy of everything recorded so far
+    pub fn recording(&self) -> Recording {
+        Recording { steps: self.steps.lock().map(|s| s.clone()).unwrap_or_default() }
+    }
+}
+
+// A step whose replayed command differs from the recorded one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub sensor_type: SensorType,
+    pub id: i32,
+    pub recorded: Option<f64>,
+    pub replayed: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    pub steps: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    pub fn is_identical(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+// Feed the recorded inputs through a fresh default commander and diff its commands
+// against the recorded ones. Any divergence means the control path is not deterministic.
+// Actuator status and control commands are not recorded, so runs using actuator limits
+// or setpoint changes are expected to diverge.
+pub fn verify_replay(recording: &Recording) -> ReplayReport {
+    let mut log = SystemLog::in_memory();
+    log.set_verbosity(Verbosity::Silent);
+    let commander = ActuatorCommander::new(HashMap::new(), HashMap::new(), Arc::new(Mutex::new(log)));
+    verify_replay_with(recording, commander)
+}
+
+// Same, against a commander built with the settings the recording was made with
+pub fn verify_replay_with(recording: &Recording, mut commander: ActuatorCommander) -> ReplayReport {
+    let start = commander.started_at();
+    let mut report = ReplayReport { steps: recording.steps.len(), divergences: Vec::new() };
+
+    for (index, step) in recording.steps.iter().enumerate() {
+        // Move every instant of the step into the replay's timeline so deadline
+        // checks and schedules see the same offsets as in the recorded run
+        let arrival = start + step.offset;
+        let shift = arrival.saturating_duration_since(step.arrival);
+        let mut input = step.input.clone();
+        input.timestamp += shift;
+        input.processed_timestamp = input.processed_timestamp.map(|t| t + shift);
+        input.capture_time = input.capture_time.map(|t| t + shift);
+
+        let replayed = commander.process_sample(input, arrival).map(|command| command.value);
+        if replayed != step.effort {
+            report.divergences.push(Divergence {
+                index,
+                sensor_type: step.input.sensor_type,
+                id: step.input.id,
+                recorded: step.effort,
+                replayed,
+            });
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(sensor_type: SensorType, id: i32, value: f64, anomaly: bool) -> SensorData {
+        SensorData {
+            id,
+            sensor_type,
+            value,
+            anomaly,
+            timestamp: Instant::now(),
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: StageStamps::default(),
+        }
+    }
+
+    fn recorded_run() -> Recording {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(Verbosity::Silent);
+        let recorder = Recorder::new();
+        let mut commander = ActuatorCommander::new(HashMap::new(), HashMap::new(), Arc::new(Mutex::new(log)))
+            .with_recorder(Some(recorder.clone()));
+        for id in 0..30 {
+            commander.process_sample(sample(SensorType::Force, id, 25.0 + (id % 7) as f64, id % 10 == 9), Instant::now());
+            commander.process_sample(sample(SensorType::Temperature, id, 100.0 + id as f64, false), Instant::now());
+        }
+        recorder.recording()
+    }
+
+    #[test]
+    fn clean_recording_replays_identically() {
+        let recording = recorded_run();
+        let report = verify_replay(&recording);
+        assert_eq!(report.steps, 60);
+        assert!(report.is_identical(), "{:?}", report.divergences);
+    }
+
+    #[test]
+    fn altered_effort_is_reported_as_a_divergence() {
+        let mut recording = recorded_run();
+        let recorded = recording.steps[4].effort.map(|e| e + 1.0);
+        recording.steps[4].effort = recorded;
+
+        let report = verify_replay(&recording);
+        assert_eq!(report.divergences.len(), 1);
+        assert_eq!(report.divergences[0].index, 4);
+        assert_eq!(report.divergences[0].recorded, recorded);
+    }
+}
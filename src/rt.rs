@@ -0,0 +1,77 @@
+use std::io;
+use std::sync::Mutex;
+use crate::share::{LogLevel, RtConfig, SchedPolicy, SystemLog};
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::io;
+
+    #[repr(C)]
+    struct SchedParam {
+        sched_priority: i32,
+    }
+
+    const SCHED_FIFO: i32 = 1;
+    const CPU_SET_WORDS: usize = 16; // cpu_set_t holds 1024 cores
+
+    unsafe extern "C" {
+        fn sched_setscheduler(pid: i32, policy: i32, param: *const SchedParam) -> i32;
+        fn sched_setaffinity(pid: i32, cpusetsize: usize, mask: *const u64) -> i32;
+    }
+
+    // Pid 0 is the calling thread, not the whole process
+    pub fn set_fifo(priority: i32) -> io::Result<()> {
+        let param = SchedParam { sched_priority: priority };
+        // SAFETY: `param` is a valid sched_param that outlives the call
+        if unsafe { sched_setscheduler(0, SCHED_FIFO, &param) } == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+    }
+
+    pub fn pin_to(core: usize) -> io::Result<()> {
+        if core >= CPU_SET_WORDS * 64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("core {} out of range", core)));
+        }
+        let mut mask = [0u64; CPU_SET_WORDS];
+        mask[core / 64] |= 1 << (core % 64);
+        // SAFETY: `mask` is a cpu_set_t of exactly the size passed
+        if unsafe { sched_setaffinity(0, std::mem::size_of_val(&mask), mask.as_ptr()) } == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::io;
+
+    pub fn set_fifo(_priority: i32) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "SCHED_FIFO is only supported on Linux"))
+    }
+
+    pub fn pin_to(_core: usize) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "core affinity is only supported on Linux"))
+    }
+}
+
+// Apply `config` to the calling thread; the `slot`-th thread takes the `slot`-th core
+// of the affinity list, wrapping around. False if anything could not be applied, which
+// is logged as a warning: SCHED_FIFO usually needs root or CAP_SYS_NICE.
+pub(crate) fn apply(config: &RtConfig, slot: usize, thread: &str, log: &Mutex<SystemLog>) -> bool {
+    let mut failures: Vec<(String, io::Error)> = Vec::new();
+
+    if let SchedPolicy::Fifo { priority } = config.policy {
+        if let Err(e) = platform::set_fifo(priority as i32) {
+            failures.push((format!("SCHED_FIFO priority {}", priority), e));
+        }
+    }
+    if let Some(cores) = config.core_affinity.as_ref().filter(|c| !c.is_empty()) {
+        let core = cores[slot % cores.len()];
+        if let Err(e) = platform::pin_to(core) {
+            failures.push((format!("pinning to core {}", core), e));
+        }
+    }
+
+    if let Ok(mut log) = log.lock() {
+        for (what, e) in &failures {
+            log.write_level(LogLevel::Warn, format!("[RT] {}: {} failed: {}", thread, what, e));
+        }
+    }
+    failures.is_empty()
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crossbeam::channel::Sender;
+use crate::actuator_commander_multi_thread::ControlCommand;
+use crate::share::{Fault, LogLevel, SensorType, SystemLog};
+
+// Channels of a running simulation a scenario can reach
+pub(crate) struct ScenarioTargets {
+    pub start_time: Instant,
+    pub time_scale: f64,
+    pub log: Arc<Mutex<SystemLog>>,
+    pub control: Sender<ControlCommand>,
+    pub faults: HashMap<SensorType, Sender<Fault>>,
+}
+
+// One step of a scenario, delivered over the existing control and fault channels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScenarioEvent {
+    InjectFault(SensorType, Fault), // Same as `SimulationHandle::inject_fault`
+    SetSetpoint(SensorType, f64),   // Replaces the schedule with a constant setpoint
+    Pause(SensorType, u32),         // The sensor skips this many cycles
+}
+
+// Timeline of events replayed against a running simulation, e.g.
+//
+// Scenario::new()
+//     .at(Duration::from_millis(500), ScenarioEvent::InjectFault(SensorType::Force, Fault::Anomaly(20)))
+//     .at(Duration::from_secs(1), ScenarioEvent::SetSetpoint(SensorType::Temperature, 260.0))
+//     .at(Duration::from_millis(1500), ScenarioEvent::Pause(SensorType::Position, 50))
+//
+// Times are simulated time since start-up; events sharing a time keep their insertion order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scenario {
+    events: Vec<(Duration, ScenarioEvent)>,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn at(mut self, time: Duration, event: ScenarioEvent) -> Self {
+        self.events.push((time, event));
+        self.events.sort_by_key(|(t, _)| *t); // Stable, so ties stay in order
+        self
+    }
+
+    pub fn events(&self) -> &[(Duration, ScenarioEvent)] {
+        &self.events
+    }
+
+    // Sleeps until each event is due and dispatches it; gives up on the rest once
+    // the simulation stops. Returns how many events were delivered.
+    pub(crate) fn run(self, targets: ScenarioTargets) -> usize {
+        let mut delivered = 0;
+
+        for (time, event) in self.events {
+            let due = targets.start_time + time.div_f64(targets.time_scale);
+
+            // Sleep in short steps so a stopped simulation is noticed quickly
+            loop {
+                let active = targets.log.lock().map(|log| log.active).unwrap_or(false);
+                if !active { return delivered; }
+                let now = Instant::now();
+                if now >= due { break; }
+                thread::sleep((due - now).min(Duration::from_millis(50)));
+            }
+
+            let sent = match event {
+                ScenarioEvent::InjectFault(s_type, fault) => targets.faults.get(&s_type).is_some_and(|tx| tx.send(fault).is_ok()),
+                ScenarioEvent::Pause(s_type, cycles) => targets.faults.get(&s_type).is_some_and(|tx| tx.send(Fault::Pause(cycles)).is_ok()),
+                ScenarioEvent::SetSetpoint(sensor_type, value) => targets.control.send(ControlCommand::SetSetpoint { sensor_type, value }).is_ok(),
+            };
+
+            if let Ok(mut log) = targets.log.lock() {
+                if sent {
+                    log.write(format!("[Scenario] {:?} at {:?}", event, time));
+                } else {
+                    log.write_level(LogLevel::Warn, format!("[Scenario] Cannot deliver {:?}, target not running", event));
+                }
+            }
+            if sent { delivered += 1; }
+        }
+        delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam::channel;
+
+    #[test]
+    fn each_event_reaches_its_target_when_due() {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(crate::share::Verbosity::Silent);
+        let (control_tx, control_rx) = channel::unbounded();
+        let (force_tx, force_rx) = channel::unbounded();
+        let (position_tx, position_rx) = channel::unbounded();
+        let start_time = Instant::now();
+        let targets = ScenarioTargets {
+            start_time,
+            time_scale: 1.0,
+            log: Arc::new(Mutex::new(log)),
+            control: control_tx,
+            faults: HashMap::from([(SensorType::Force, force_tx), (SensorType::Position, position_tx)]),
+        };
+        let ms = Duration::from_millis;
+        let scenario = Scenario::new()
+            .at(ms(60), ScenarioEvent::Pause(SensorType::Position, 50))
+            .at(ms(20), ScenarioEvent::InjectFault(SensorType::Force, Fault::Anomaly(20)))
+            .at(ms(40), ScenarioEvent::SetSetpoint(SensorType::Temperature, 260.0));
+
+        assert_eq!(scenario.run(targets), 3);
+        assert_eq!(force_rx.try_recv(), Ok(Fault::Anomaly(20)));
+        assert!(matches!(control_rx.try_recv(), Ok(ControlCommand::SetSetpoint { sensor_type: SensorType::Temperature, value }) if value == 260.0));
+        assert_eq!(position_rx.try_recv(), Ok(Fault::Pause(50)));
+        assert!(start_time.elapsed() >= ms(60)); // The last one was not sent early
+    }
+}
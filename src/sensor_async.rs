@@ -3,9 +3,9 @@ use std::collections::VecDeque;
 use tokio::sync::Mutex;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::{self, Instant};
+use tokio::time::{self, Instant, MissedTickBehavior};
 use rand::Rng;
-use crate::share::{BenchmarkStats, Feedback, SensorData, SensorType, SystemLog};
+use crate::share::{default_profile, moving_average, BenchmarkStats, CalibrationStore, DeadlineHooks, DeadlinePolicy, Deadlines, FaultRates, Feedback, FILTER_WINDOW, LogLevel, SampleBudget, SensorData, SensorType, ShutdownReason, Stage, StageFlags, StageStamps, SystemLog, Verbosity};
 
 pub struct SensorAsync {
     id_counter: i32,
@@ -14,29 +14,121 @@ pub struct SensorAsync {
     calibration_offset: f64,
     log: Arc<Mutex<SystemLog>>,
     benchmark_stats: BenchmarkStats,
+    deadline_hooks: DeadlineHooks,
+    missed_tick_behavior: MissedTickBehavior,
+    deadlines: Deadlines,
+    escalate_next: bool, // Late feedback under the Escalate policy flags the next sample
+    anomaly_confirm: usize,     // Consecutive out-of-range readings before a sample is flagged
+    out_of_range_streak: usize,
+    stages: StageFlags,
+    fault_rates: FaultRates,
+    recalibration_decay: Option<Duration>, // None applies every offset in full
+    sample_budget: Option<SampleBudget>,
+    calibration: Option<CalibrationStore>,
 }
 
 impl SensorAsync {
     pub fn new(sensor_type: SensorType, log: Arc<Mutex<SystemLog>>) -> Self {
         Self {
             id_counter: 0,
-            history_buffer: VecDeque::new(),
+            history_buffer: VecDeque::with_capacity(FILTER_WINDOW),
             sensor_type,
             calibration_offset: 0.0,
             log,
             benchmark_stats: BenchmarkStats::new(),
+            deadline_hooks: DeadlineHooks::default(),
+            missed_tick_behavior: MissedTickBehavior::Skip,
+            deadlines: Deadlines::default(),
+            escalate_next: false,
+            anomaly_confirm: 1,
+            out_of_range_streak: 0,
+            stages: StageFlags::default(),
+            fault_rates: FaultRates::default(),
+            recalibration_decay: None,
+            sample_budget: None,
+            calibration: None,
         }
     }
 
+    // Start from the stored offset and write the learned one back on shutdown
+    pub fn with_calibration(mut self, store: CalibrationStore) -> Self {
+        if let Some(offset) = store.get(self.sensor_type) {
+            self.calibration_offset = offset;
+        }
+        self.calibration = Some(store);
+        self
+    }
+
+    pub fn calibration_offset(&self) -> f64 {
+        self.calibration_offset
+    }
+
+    // Debounce noise spikes; 1 flags every out-of-range reading
+    pub fn with_anomaly_confirm(mut self, readings: usize) -> Self {
+        self.anomaly_confirm = readings.max(1);
+        self
+    }
+
+    // Switch off the filter or anomaly detection of this sensor
+    pub fn with_stages(mut self, stages: StageFlags) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    pub fn with_deadlines(mut self, deadlines: Deadlines) -> Self {
+        self.deadlines = deadlines;
+        self
+    }
+
+    // How the sampling ticker reacts when the task is stalled past a tick:
+    // - Skip (default): missed samples are dropped like a real sensor would; the stall
+    //   shows up once in `max_jitter` and the schedule stays on the 5ms grid.
+    // - Delay: the schedule restarts from the late tick; the stall shows up once and
+    //   every later sample is shifted by it.
+    // - Burst: catch-up ticks fire back to back; the stall shows up once, then the
+    //   burst samples are recorded with near-zero jitter even though they are late.
+    pub fn with_missed_tick_behavior(mut self, behavior: MissedTickBehavior) -> Self {
+        self.missed_tick_behavior = behavior;
+        self
+    }
+
+    // Scale each recalibration by exp(-latency / decay): feedback that took `decay`
+    // to arrive is applied at ~37%, so stale corrections cannot over-correct
+    pub fn with_recalibration_decay(mut self, decay: Option<Duration>) -> Self {
+        self.recalibration_decay = decay;
+        self
+    }
+
+    // Share of a recalibration offset to apply for feedback of this age
+    fn staleness_weight(&self, latency: Duration) -> f64 {
+        match self.recalibration_decay {
+            Some(decay) if !decay.is_zero() => (-latency.as_secs_f64() / decay.as_secs_f64()).exp(),
+            _ => 1.0,
+        }
+    }
+
+    // Stop generating once the shared budget is used up, see `StopCondition::Samples`
+    pub fn with_sample_budget(mut self, budget: Option<SampleBudget>) -> Self {
+        self.sample_budget = budget;
+        self
+    }
+
+    pub fn with_fault_rates(mut self, fault_rates: FaultRates) -> Self {
+        self.fault_rates = fault_rates;
+        self
+    }
+
+    pub fn with_deadline_hooks(mut self, hooks: DeadlineHooks) -> Self {
+        self.deadline_hooks = hooks;
+        self
+    }
+
     fn generate_data(&mut self) -> SensorData {
         let mut rng = rand::rng(); // rand::rng() is thread-local, safe in async tasks
         self.id_counter += 1;
 
-        let value = match self.sensor_type {
-            SensorType::Force => rng.random_range(10.0..55.0),
-            SensorType::Position => rng.random_range(-0.1..0.2),
-            SensorType::Temperature => rng.random_range(20.0..130.0),
-        };
+        let (min, max) = default_profile(self.sensor_type).range;
+        let value = rng.random_range(min..max);
 
         SensorData {
             id: self.id_counter,
@@ -45,73 +137,89 @@ impl SensorAsync {
             anomaly: false,
             timestamp: std::time::Instant::now(),
             processed_timestamp: None,
+            capture_time: None,
+            stamps: StageStamps::default(),
         }
     }
 
     async fn process_data(&mut self, mut data: SensorData) -> Option<SensorData> {
         let start = Instant::now();
 
+        // 0. Reject non-finite readings before they reach the filter or the PID
+        if !data.value.is_finite() {
+            self.benchmark_stats.try_log(&self.log, LogLevel::Warn, format!("[Sensor {:?}] Non-finite value {} (ID: {}). Skipping.", data.sensor_type, data.value, data.id));
+            return None;
+        }
+
         // 1. Detect Anomaly
-        match data.sensor_type {
-            SensorType::Force => if data.value < 5.0 || data.value > 60.0 { data.anomaly = true; },
-            SensorType::Position => if data.value.abs() > 0.5 { data.anomaly = true; },
-            SensorType::Temperature => if data.value > 120.0 { data.anomaly = true; },
+        if self.stages.anomaly {
+            let out_of_range = default_profile(data.sensor_type).out_of_range(data.value);
+            // Only flag once `anomaly_confirm` readings in a row were out of range
+            self.out_of_range_streak = if out_of_range { self.out_of_range_streak + 1 } else { 0 };
+            if self.out_of_range_streak >= self.anomaly_confirm {
+                data.anomaly = true;
+            }
         }
 
+        // Anomalous readings are forwarded raw and never enter the filter window
         if data.anomaly { return Some(data); }
 
-        // 2. Moving Average
-        if self.history_buffer.len() >= 5 { self.history_buffer.pop_front(); }
-        self.history_buffer.push_back(data.value);
-        let total: f64 = self.history_buffer.iter().sum();
-        data.value = total / self.history_buffer.len() as f64;
+        // 2. Moving Average; until the window is full it averages over fewer
+        if self.stages.filter {
+            if self.history_buffer.len() >= FILTER_WINDOW { self.history_buffer.pop_front(); }
+            self.history_buffer.push_back(data.value);
+            debug_assert!(self.history_buffer.len() <= FILTER_WINDOW);
+            data.value = moving_average(&self.history_buffer);
+        }
 
         data.processed_timestamp = Some(std::time::Instant::now());
 
         // 3. Deadline Check
-        if start.elapsed() > Duration::from_micros(200) {
+        let deadline_process = self.deadlines.processing;
+        let elapsed = start.elapsed();
+        if elapsed > deadline_process {
             self.benchmark_stats.sensor_missed_deadlines += 1;
-            let mut log = self.log.lock().await;
-            log.write(format!("[Sensor {:?}] Processing Deadline Missed! Dropping.", data.sensor_type));
-            return None;
+            let policy = self.deadlines.policies.processing;
+            self.benchmark_stats.try_log(&self.log, LogLevel::Warn, format!("[Sensor {:?}] Processing Deadline Missed! Policy: {:?}", data.sensor_type, policy));
+            self.deadline_hooks.notify(Stage::Processing, data.sensor_type, elapsed - deadline_process);
+            match policy {
+                DeadlinePolicy::Drop => return None,
+                DeadlinePolicy::MarkAndContinue => {}
+                DeadlinePolicy::Escalate => data.anomaly = true,
+            }
         }
 
         Some(data)
     }
 
-    async fn transmit_data(&self, sender: &Sender<SensorData>, data: SensorData) -> bool {
+    async fn transmit_data(&mut self, sender: &Sender<SensorData>, data: SensorData) -> bool {
 
         let fault_roll: f64 = {
             let mut rng = rand::rng();
             rng.random_range(0.00..1.00)
         };
 
-        // FAULT 1: Packet Drop (5% chance)
-        if fault_roll < 0.05 {
-            let start_lock = Instant::now();
-
-            // ASYNC MUTEX LOCK: No "if let Ok", just .await
-            let mut guard = self.log.lock().await;
-
-            let contention = start_lock.elapsed();
-            guard.write(format!("[FAULT] Dropping packet ID {} for {:?} (Lock Wait: {:?})",
-                                data.id, self.sensor_type, contention));
+        // FAULT 1: Packet Drop (5% chance by default)
+        if fault_roll < self.fault_rates.drop_rate {
+            // Never wait for the log here: under a fault burst the contention would cost
+            // more than the fault itself
+            self.benchmark_stats.try_log(&self.log, LogLevel::Warn, format!("[FAULT] Dropping packet ID {} for {:?}", data.id, self.sensor_type));
 
             // Return true because we "successfully" handled the logic (by dropping it intentionally)
             return true;
         }
 
-        // FAULT 2: Network Latency Delay (5% chance)
-        if fault_roll > 0.95 {
+        // FAULT 2: Network Latency Delay (5% chance by default)
+        if fault_roll >= 1.0 - self.fault_rates.latency_rate {
             // IMPORTANT: Use tokio::time::sleep, NOT std::thread::sleep
-            time::sleep(Duration::from_micros(30)).await;
+            time::sleep(self.fault_rates.latency).await;
         }
 
         // 2. Transmit data (Async)
         match sender.send(data).await {
             Ok(_) => true,
             Err(_) => {
-                println!("[Sensor {:?}] Receiver disconnected. Stopping.", self.sensor_type);
+                self.log.lock().await.print(Verbosity::Normal, format!("[Sensor {:?}] Receiver disconnected. Stopping.", self.sensor_type));
                 false
             }
         }
@@ -123,18 +231,22 @@ impl SensorAsync {
         mut rx_feedback: Receiver<Feedback>,
     ) -> BenchmarkStats {
         // Fixed interval ticker (Alternative to thread::sleep)
-        let mut interval = time::interval(Duration::from_millis(5));
-
-        // Define simulation end condition (optional, or rely on channel close)
-        let mut active = true;
+        let mut interval = time::interval(self.deadlines.sensor_cycle);
+        interval.set_missed_tick_behavior(self.missed_tick_behavior);
 
-        let cycle_time = Duration::from_millis(5);
+        let cycle_time = self.deadlines.sensor_cycle;
         let mut next_deadline = Instant::now();
+        let start_time = next_deadline;
+
+        let stop_reason;
 
         loop {
             {
                 let log = self.log.lock().await;
-                if !log.active { break; }
+                if !log.active {
+                    stop_reason = log.shutdown_reason().unwrap_or(ShutdownReason::DurationElapsed);
+                    break;
+                }
             }
 
             tokio::select! {
@@ -149,30 +261,53 @@ impl SensorAsync {
 
                         // C. Update Stats
                         self.benchmark_stats.total_jitter += jitter;
+                        self.benchmark_stats.total_jitter_sq += jitter.as_secs_f64().powi(2);
                         if jitter > self.benchmark_stats.max_jitter {
                             self.benchmark_stats.max_jitter = jitter;
                         }
                     }
-                    // D. Advance the deadline for the NEXT loop (Fixed 5ms steps)
-                    next_deadline += cycle_time;
+                    // D. Advance the deadline for the NEXT loop, following the ticker's schedule
+                    next_deadline = next_deadline_after(self.missed_tick_behavior, next_deadline, now, cycle_time);
+
+                    if let Some(budget) = &self.sample_budget {
+                        if !budget.try_take() {
+                            // Every sample is taken; the loop stops at the flag check
+                            self.log.lock().await.request_shutdown(ShutdownReason::SampleLimitReached);
+                            continue;
+                        }
+                    }
 
                     self.benchmark_stats.sensor_count += 1;
+                    self.benchmark_stats.stage_times.get_mut(self.sensor_type).budget += cycle_time;
 
                     let start_gen = Instant::now();
                     let raw_data = self.generate_data();
-                    self.benchmark_stats.total_gen_time += start_gen.elapsed();
+                    let gen_time = start_gen.elapsed();
+                    self.benchmark_stats.record_value(raw_data.value);
+                    self.benchmark_stats.total_gen_time += gen_time;
+                    self.benchmark_stats.stage_times.get_mut(self.sensor_type).generation += gen_time;
 
                     let start_proc = Instant::now();
-                    if let Some(processed_data) = self.process_data(raw_data).await {
-                        self.benchmark_stats.total_proc_time += start_proc.elapsed();
+                    if let Some(mut processed_data) = self.process_data(raw_data).await {
+                        let proc_time = start_proc.elapsed();
+                        self.benchmark_stats.total_proc_time += proc_time;
+                        self.benchmark_stats.stage_times.get_mut(self.sensor_type).processing += proc_time;
+                        if self.escalate_next {
+                            processed_data.anomaly = true;
+                            self.escalate_next = false;
+                        }
 
                         if processed_data.anomaly {
-                            let mut log = self.log.lock().await;
-                                log.write(format!("[ANOMALY] {:?} ID: {}", self.sensor_type, processed_data.id));
+                            self.benchmark_stats.anomaly_count += 1;
+                            self.benchmark_stats.try_log(&self.log, LogLevel::Warn, format!("[ANOMALY] {:?} ID: {}", self.sensor_type, processed_data.id));
                         }
                         // Async Send (Wait if buffer full)
-                        if !self.transmit_data(&tx, processed_data).await {
+                        let start_trans = Instant::now();
+                        let sent = self.transmit_data(&tx, processed_data).await;
+                        self.benchmark_stats.stage_times.get_mut(self.sensor_type).transmission += start_trans.elapsed();
+                        if !sent {
                             // If transmit returns false (channel closed), stop the loop
+                            stop_reason = ShutdownReason::ChannelDisconnected;
                             break;
                         }
                     }
@@ -181,29 +316,202 @@ impl SensorAsync {
                 // EVENT 2: Received Feedback from Actuator
                 Some(fb) = rx_feedback.recv() => {
                      // Check Feedback Latency
-                     let latency = std::time::Instant::now().duration_since(fb.timestamp);
-                     if latency > Duration::from_micros(500) {
+                     let arrival_time = std::time::Instant::now();
+                     let latency = arrival_time.duration_since(fb.timestamp);
+                     if let Some(round_trip) = fb.round_trip(arrival_time) {
+                         self.benchmark_stats.record_roundtrip(round_trip);
+                     }
+                     let deadline_feedback = self.deadlines.feedback;
+                     if latency > deadline_feedback {
                         self.benchmark_stats.actuator_missed_deadlines += 1;
-                        let mut log = self.log.lock().await;
-                         log.write(format!("[DEADLINE] Feedback for Sensor {:?} arrived late! Latency: {:?} (Limit: 500µs)",self.sensor_type, latency));
+                        self.benchmark_stats.try_log(&self.log, LogLevel::Warn, format!("[DEADLINE] Feedback for Sensor {:?} arrived late! Latency: {:?} (Limit: {:?})",self.sensor_type, latency, deadline_feedback));
+                        self.deadline_hooks.notify(Stage::Feedback, self.sensor_type, latency - deadline_feedback);
+
+                        match self.deadlines.policies.feedback {
+                            DeadlinePolicy::Drop => continue,
+                            DeadlinePolicy::MarkAndContinue => {}
+                            DeadlinePolicy::Escalate => self.escalate_next = true,
+                        }
                      }
 
                      // Handle Recalibration
                      if fb.recalibrate_offset != 0.0 {
-                         self.calibration_offset += fb.recalibrate_offset;
-                         let mut log = self.log.lock().await;
-                         log.write(format!("[ASYNC Feedback] Recalibrated {:?} by {:.2}", self.sensor_type, fb.recalibrate_offset));
+                         let applied = fb.recalibrate_offset * self.staleness_weight(latency);
+                         self.calibration_offset += applied;
+                         self.benchmark_stats.try_log(&self.log, LogLevel::Info, format!("[ASYNC Feedback] Recalibrated {:?} by {:.2} (requested {:.2}, latency {:?})", self.sensor_type, applied, fb.recalibrate_offset, latency));
                      }
 
                     // ACTION 2: Error / Alert Logging
                     // If the message is not "no", it means there is a specific warning (e.g., "Drift Detected")
                     if fb.error_msg != "no" {
-                        let mut log = self.log.lock().await;
-                        log.write(format!("[Feedback] Alert for {:?}: {}", self.sensor_type, fb.error_msg));
+                        self.benchmark_stats.try_log(&self.log, LogLevel::Warn, format!("[Feedback] Alert for {:?}: {}", self.sensor_type, fb.error_msg));
                     }
                 }
             }
         }
+        self.benchmark_stats.active_duration += start_time.elapsed();
+        if let Some(store) = &self.calibration {
+            store.set(self.sensor_type, self.calibration_offset);
+        }
+        self.log.lock().await.write(format!("[Shutdown] Sensor {:?} stopped: {:?}", self.sensor_type, stop_reason));
         self.benchmark_stats
     }
-}
\ No newline at end of file
+}
+
+// When the ticker fires next after a tick expected at `expected` ran at `now`
+fn next_deadline_after(behavior: MissedTickBehavior, expected: Instant, now: Instant, cycle: Duration) -> Instant {
+    match behavior {
+        MissedTickBehavior::Delay => now.max(expected) + cycle,
+        MissedTickBehavior::Skip => {
+            let mut next = expected + cycle;
+            while next <= now { next += cycle; }
+            next
+        }
+        _ => expected + cycle, // Burst: fixed steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet_log() -> Arc<Mutex<SystemLog>> {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(Verbosity::Silent);
+        Arc::new(Mutex::new(log))
+    }
+
+    // Lenient processing deadline, so a slow test machine never drops a sample
+    fn sensor(sensor_type: SensorType) -> SensorAsync {
+        SensorAsync::new(sensor_type, quiet_log()).with_deadlines(Deadlines { processing: Duration::from_secs(1), ..Deadlines::default() })
+    }
+
+    fn reading(sensor_type: SensorType, id: i32, value: f64) -> SensorData {
+        SensorData {
+            id,
+            sensor_type,
+            value,
+            anomaly: false,
+            timestamp: std::time::Instant::now(),
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: StageStamps::default(),
+        }
+    }
+
+    async fn filtered(sensor: &mut SensorAsync, id: i32, value: f64) -> SensorData {
+        sensor.process_data(reading(sensor.sensor_type, id, value)).await.expect("sample kept")
+    }
+
+    #[tokio::test]
+    async fn first_sample_averages_to_itself() {
+        let mut sensor = sensor(SensorType::Temperature);
+        assert_eq!(filtered(&mut sensor, 1, 42.0).await.value, 42.0);
+    }
+
+    #[tokio::test]
+    async fn sixth_sample_evicts_the_first() {
+        let mut sensor = sensor(SensorType::Temperature);
+        for (id, (value, want)) in [(10.0, 10.0), (20.0, 15.0), (30.0, 20.0), (40.0, 25.0), (50.0, 30.0)].into_iter().enumerate() {
+            assert_eq!(filtered(&mut sensor, id as i32, value).await.value, want);
+        }
+        assert_eq!(filtered(&mut sensor, 5, 40.0).await.value, 36.0);
+        assert_eq!(sensor.history_buffer, [20.0, 30.0, 40.0, 50.0, 40.0]);
+    }
+
+    #[tokio::test]
+    async fn anomaly_does_not_enter_the_window() {
+        let mut sensor = sensor(SensorType::Temperature);
+        filtered(&mut sensor, 1, 20.0).await;
+        filtered(&mut sensor, 2, 30.0).await;
+
+        let spike = filtered(&mut sensor, 3, 500.0).await; // Above the 120 limit
+        assert!(spike.anomaly);
+        assert_eq!(spike.value, 500.0);
+        assert_eq!(sensor.history_buffer, [20.0, 30.0]);
+
+        assert_eq!(filtered(&mut sensor, 4, 40.0).await.value, 30.0);
+    }
+
+    const TICK: Duration = Duration::from_millis(20);
+
+    // Blocks the runtime for 3.5 cycles right after the first tick, then returns when
+    // the next three ticks were scheduled, checking the sensor's expected deadline
+    // against each. The half-cycle tolerance only absorbs how late the runtime polls
+    // the ticker; a wrong deadline is off by at least a whole cycle.
+    async fn ticks_after_stall(behavior: MissedTickBehavior) -> Vec<Duration> {
+        let mut interval = time::interval(TICK);
+        interval.set_missed_tick_behavior(behavior);
+        let start = interval.tick().await;
+        let mut expected = start + TICK;
+        std::thread::sleep(TICK * 7 / 2); // The stall
+
+        let mut scheduled = Vec::new();
+        for _ in 0..3 {
+            let tick = interval.tick().await;
+            let off = if tick > expected { tick - expected } else { expected - tick };
+            assert!(off < TICK / 2, "{:?}: ticked {:?} off the expected deadline", behavior, off);
+            scheduled.push(tick - start);
+            expected = next_deadline_after(behavior, expected, Instant::now(), TICK);
+        }
+        scheduled
+    }
+
+    #[tokio::test]
+    async fn deadlines_follow_the_missed_tick_behavior() {
+        let on_grid = |d: Duration| d.as_nanos().is_multiple_of(TICK.as_nanos());
+
+        // Burst catches up back to back, still on the grid
+        let burst = ticks_after_stall(MissedTickBehavior::Burst).await;
+        assert_eq!(burst, vec![TICK, TICK * 2, TICK * 3]);
+
+        // Skip drops the missed ticks and returns to the grid after the stall
+        let skip = ticks_after_stall(MissedTickBehavior::Skip).await;
+        assert_eq!(skip[0], TICK);
+        assert!(skip[1] > TICK * 7 / 2 && on_grid(skip[1]), "{:?}", skip);
+        assert_eq!(skip[2] - skip[1], TICK);
+
+        // Delay restarts a full cycle after the late tick
+        let delay = ticks_after_stall(MissedTickBehavior::Delay).await;
+        assert_eq!(delay[0], TICK);
+        assert!(delay[1] >= TICK * 9 / 2, "{:?}", delay);
+        assert!(delay[2] - delay[1] >= TICK, "{:?}", delay);
+    }
+
+    #[tokio::test]
+    async fn lone_spike_is_not_confirmed_as_an_anomaly() {
+        let mut sensor = sensor(SensorType::Force).with_anomaly_confirm(3);
+        let mut flags = Vec::new();
+        for (id, value) in [30.0, 999.0, 30.0, 999.0, 999.0, 999.0].into_iter().enumerate() {
+            flags.push(filtered(&mut sensor, id as i32, value).await.anomaly);
+        }
+        assert_eq!(flags, [false, false, false, false, false, true]);
+    }
+
+    // Offset the sensor ends up with after one recalibration of 1.0 that is `age` old on arrival
+    async fn offset_after_feedback(age: Duration) -> f64 {
+        let log = quiet_log();
+        let store = CalibrationStore::new();
+        let sensor = SensorAsync::new(SensorType::Force, log.clone())
+            .with_recalibration_decay(Some(Duration::from_millis(100)))
+            .with_calibration(store.clone());
+        let (tx, _rx) = tokio::sync::mpsc::channel(1000);
+        let (fb_tx, fb_rx) = tokio::sync::mpsc::channel(1);
+        let running = tokio::spawn(sensor.run(tx, fb_rx));
+
+        let sent_at = std::time::Instant::now() - age;
+        fb_tx.send(Feedback { is_ack: true, error_msg: "no".to_string(), recalibrate_offset: 1.0, timestamp: sent_at, origin: None }).await.unwrap();
+        time::sleep(Duration::from_millis(30)).await;
+        log.lock().await.request_shutdown(ShutdownReason::DurationElapsed);
+        running.await.unwrap();
+        store.get(SensorType::Force).expect("offset saved")
+    }
+
+    #[tokio::test]
+    async fn stale_feedback_shifts_the_offset_less_than_fresh_feedback() {
+        let fresh = offset_after_feedback(Duration::ZERO).await;
+        let stale = offset_after_feedback(Duration::from_millis(200)).await; // Two decay constants old
+        assert!(fresh > 0.8, "fresh feedback applied at {}", fresh);
+        assert!(stale < 0.2, "stale feedback applied at {}", stale);
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crossbeam::select;
+use crossbeam::channel::{Receiver, Sender};
+use crate::share::{BenchmarkStats, SensorData, SensorType, ShutdownReason, StageStamps, SystemLog};
+
+// Combines the latest value of each source into one reading, e.g. |force, pos| 0.7 * force + 0.3 * pos
+pub type FusionFn = Box<dyn Fn(f64, f64) -> f64 + Send>;
+
+// Virtual sensor fed by two other sensors instead of its own hardware.
+// Sources may run at different rates: the last value of each is held, and a
+// fused reading is emitted on every arrival once both have reported at least once.
+pub struct FusedSensor {
+    name: String,
+    output_type: SensorType, // Type the fused readings are labelled with downstream
+    fuse: FusionFn,
+    id_counter: i32,
+    last_a: Option<SensorData>,
+    last_b: Option<SensorData>,
+    log: Arc<Mutex<SystemLog>>,
+    benchmark_stats: BenchmarkStats,
+}
+
+impl FusedSensor {
+    pub fn new(name: String, output_type: SensorType, fuse: FusionFn, log: Arc<Mutex<SystemLog>>) -> Self {
+        Self {
+            name,
+            output_type,
+            fuse,
+            id_counter: 0,
+            last_a: None,
+            last_b: None,
+            log,
+            benchmark_stats: BenchmarkStats::new(),
+        }
+    }
+
+    // FUNCTION 1: Fuse the held values
+    fn fuse_latest(&mut self) -> Option<SensorData> {
+        let (a, b) = (self.last_a.as_ref()?, self.last_b.as_ref()?);
+        let start = Instant::now();
+
+        let value = (self.fuse)(a.value, b.value);
+        self.id_counter += 1;
+        let data = SensorData {
+            id: self.id_counter,
+            sensor_type: self.output_type,
+            value,
+            // Anomalous if either input is, or if the fusion itself produced nonsense
+            anomaly: a.anomaly || b.anomaly || !value.is_finite(),
+            // Latency is measured from the older of the two readings
+            timestamp: a.timestamp.min(b.timestamp),
+            processed_timestamp: Some(Instant::now()),
+            capture_time: (a.capture_time.is_some() || b.capture_time.is_some()).then(|| a.captured_at().min(b.captured_at())),
+            stamps: StageStamps::default(),
+        };
+
+        self.benchmark_stats.total_proc_time += start.elapsed();
+        Some(data)
+    }
+
+    // ACTUAL RUN
+    pub fn run(mut self,
+               rx_a: Receiver<SensorData>,
+               rx_b: Receiver<SensorData>,
+               sender: Sender<SensorData>, ) -> BenchmarkStats
+    {
+        let stop_reason;
+
+        loop {
+            // Only a new reading is fused, a quiet spell must not repeat the last output
+            let arrived = select! {
+                recv(rx_a) -> msg => match msg {
+                    Ok(data) => { self.last_a = Some(data); true }
+                    Err(_) => { stop_reason = ShutdownReason::ChannelDisconnected; break; }
+                },
+                recv(rx_b) -> msg => match msg {
+                    Ok(data) => { self.last_b = Some(data); true }
+                    Err(_) => { stop_reason = ShutdownReason::ChannelDisconnected; break; }
+                },
+                // Re-check the shutdown flag even when both sources are quiet
+                default(Duration::from_millis(100)) => false,
+            };
+
+            if let Ok(guard) = self.log.lock() {
+                if !guard.active {
+                    stop_reason = guard.shutdown_reason().unwrap_or(ShutdownReason::DurationElapsed);
+                    break;
+                }
+            }
+
+            if !arrived { continue; }
+            if let Some(fused) = self.fuse_latest() {
+                self.benchmark_stats.sensor_count += 1;
+                if sender.send(fused).is_err() {
+                    stop_reason = ShutdownReason::ChannelDisconnected;
+                    break;
+                }
+            }
+        }
+
+        if let Ok(mut log) = self.log.lock() {
+            log.write(format!("[Shutdown] Fused sensor {} stopped: {:?}", self.name, stop_reason));
+        }
+        self.benchmark_stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use crossbeam::channel::unbounded;
+    use crate::share::Verbosity;
+
+    fn quiet_log() -> Arc<Mutex<SystemLog>> {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(Verbosity::Silent);
+        Arc::new(Mutex::new(log))
+    }
+
+    fn reading(sensor_type: SensorType, value: f64) -> SensorData {
+        SensorData {
+            id: 0,
+            sensor_type,
+            value,
+            anomaly: false,
+            timestamp: Instant::now(),
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: StageStamps::default(),
+        }
+    }
+
+    #[test]
+    fn slower_source_is_held_while_the_faster_one_reports() {
+        let (tx_a, rx_a) = unbounded();
+        let (tx_b, rx_b) = unbounded();
+        let (tx_out, rx_out) = unbounded();
+        let fused = FusedSensor::new("test".to_string(), SensorType::Force, Box::new(|a, b| a + 100.0 * b), quiet_log());
+        let running = thread::spawn(move || fused.run(rx_a, rx_b, tx_out));
+        let next = || rx_out.recv_timeout(Duration::from_secs(1)).map(|data| data.value);
+
+        tx_a.send(reading(SensorType::Force, 1.0)).unwrap();
+        assert!(rx_out.recv_timeout(Duration::from_millis(50)).is_err(), "fused before both sources reported");
+        tx_b.send(reading(SensorType::Position, 2.0)).unwrap();
+        assert_eq!(next(), Ok(201.0));
+        // Force reports three times as often as Position: its new values meet the held one
+        tx_a.send(reading(SensorType::Force, 3.0)).unwrap();
+        assert_eq!(next(), Ok(203.0));
+        tx_a.send(reading(SensorType::Force, 4.0)).unwrap();
+        assert_eq!(next(), Ok(204.0));
+        tx_b.send(reading(SensorType::Position, 5.0)).unwrap();
+        assert_eq!(next(), Ok(504.0));
+
+        drop((tx_a, tx_b));
+        assert_eq!(running.join().unwrap().sensor_count, 4);
+    }
+
+    #[test]
+    fn quiet_sources_produce_no_output() {
+        let (tx_a, rx_a) = unbounded();
+        let (tx_b, rx_b) = unbounded();
+        let (tx_out, rx_out) = unbounded();
+        let fused = FusedSensor::new("test".to_string(), SensorType::Force, Box::new(|a, b| a + b), quiet_log());
+        let running = thread::spawn(move || fused.run(rx_a, rx_b, tx_out));
+
+        tx_a.send(reading(SensorType::Force, 1.0)).unwrap();
+        tx_b.send(reading(SensorType::Position, 2.0)).unwrap();
+        assert!(rx_out.recv_timeout(Duration::from_secs(1)).is_ok());
+        // Several idle 100ms timeouts pass without anything new to fuse
+        thread::sleep(Duration::from_millis(350));
+        assert!(rx_out.try_recv().is_err(), "re-sent a stale fused value");
+
+        drop((tx_a, tx_b));
+        running.join().unwrap();
+    }
+}
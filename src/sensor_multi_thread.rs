@@ -1,43 +1,247 @@
 use std::collections::VecDeque;
 use rand::Rng;
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
-use chrono::format::Pad::Zero;
-use crate::share::{BenchmarkStats, Feedback, SensorData, SensorType, SystemLog, SystemMode};
-use crossbeam::channel::{Receiver,Sender};
+use crate::cpu_time::CpuTimer;
+use crate::share::{default_profile, moving_average, AdaptiveSampling, BackpressurePolicy, BenchmarkStats, CalibrationStore, DeadLetterLog, DeadlineHooks, DeadlinePolicy, Deadlines, DropReason, Fault, FaultController, FaultRates, FaultSchedule, Feedback, FeedbackMode, FILTER_WINDOW, LogLevel, Plant, SampleBudget, SensorData, SensorFeedback, SensorProfile, SensorType, ShutdownReason, SnapshotHandle, Stage, StageFlags, StageStamps, SystemLog, TransmissionFault, Verbosity};
+use crossbeam::channel::{self, Receiver,Sender, TrySendError};
+
+const MAX_SLOWDOWN: u32 = 8; // Longest cycle under backpressure, in sensor cycles
 
 pub struct Sensor {
     id_counter: i32,
     history_buffer: VecDeque<f64>,
     sensor_type: SensorType,
+    profile: SensorProfile,
     calibration_offset: f64,
+    drift_rate: f64, // Bias added to the readings on every sample
+    maintain_decay: f64, // Share of the offset dropped on every `Maintain`
+    bias: f64,
     log:Arc<Mutex<SystemLog>>,
     benchmark_stats: BenchmarkStats,
+    deadline_hooks: DeadlineHooks,
+    deadlines: Deadlines,
+    escalate_next: bool, // Late feedback under the Escalate policy flags the next sample
+    anomaly_confirm: usize,     // Consecutive out-of-range readings before a sample is flagged
+    out_of_range_streak: usize,
+    stages: StageFlags,
+    fault_rates: FaultRates,
+    fault_schedule: FaultSchedule,
+    dead_letters: DeadLetterLog,
+    faults: Receiver<Fault>,
+    active_fault: Option<(Fault, u32)>, // Injected fault and samples left
+    last_value: Option<f64>,
+    calibration: Option<CalibrationStore>,
+    monitor: Option<SnapshotHandle>,
+    fault_controller: Option<FaultController>,
+    adaptive: Option<AdaptiveSampling>,
+    feedback_mode: FeedbackMode,
+    pending_offsets: (f64, u32), // Sum and count of the offsets not applied yet
+    batch_started: Option<Instant>,
+    sample_budget: Option<SampleBudget>,
+    batch_size: usize,
+    batch_tx: Option<Sender<Vec<SensorData>>>, // None sends every sample on its own
+    batch: Vec<SensorData>,
+    backpressure: BackpressurePolicy,
+    backpressured: bool, // The last send found the channel full
+    plant: Option<Plant>, // Read instead of generating random values
 }
 
 impl Sensor {
     pub fn new(sensor_type: SensorType,log:Arc<Mutex<SystemLog>>,) -> Self {
         Self {
             id_counter: 0,
-            history_buffer: VecDeque::new(),
+            history_buffer: VecDeque::with_capacity(FILTER_WINDOW), // Never grows past the window, so never reallocates
             sensor_type,
+            profile: default_profile(sensor_type),
             calibration_offset: 0.0,
+            drift_rate: 0.0,
+            maintain_decay: 0.0,
+            bias: 0.0,
             log,
-            benchmark_stats: BenchmarkStats::new()
+            benchmark_stats: BenchmarkStats::new(),
+            deadline_hooks: DeadlineHooks::default(),
+            deadlines: Deadlines::default(),
+            escalate_next: false,
+            anomaly_confirm: 1,
+            out_of_range_streak: 0,
+            stages: StageFlags::default(),
+            fault_rates: FaultRates::default(),
+            fault_schedule: FaultSchedule::default(),
+            dead_letters: DeadLetterLog::default(),
+            faults: channel::never(),
+            active_fault: None,
+            last_value: None,
+            calibration: None,
+            monitor: None,
+            fault_controller: None,
+            adaptive: None,
+            feedback_mode: FeedbackMode::Immediate,
+            pending_offsets: (0.0, 0),
+            batch_started: None,
+            sample_budget: None,
+            batch_size: 1,
+            batch_tx: None,
+            batch: Vec::new(),
+            backpressure: BackpressurePolicy::Drop,
+            backpressured: false,
+            plant: None,
         }
     }
 
+    // Generated range and anomaly bounds, instead of the defaults of the sensor type
+    pub fn with_profile(mut self, profile: SensorProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    // Debounce noise spikes; 1 flags every out-of-range reading
+    pub fn with_anomaly_confirm(mut self, readings: usize) -> Self {
+        self.anomaly_confirm = readings.max(1);
+        self
+    }
+
+    // Start with `count` copies of `value` in the moving-average window (at most
+    // `FILTER_WINDOW`), so the filter is warm from the first sample
+    pub fn with_preload(mut self, value: f64, count: usize) -> Self {
+        self.history_buffer.clear();
+        self.history_buffer.extend(std::iter::repeat_n(value, count.min(FILTER_WINDOW)));
+        self
+    }
+
+    // Room reserved up front in the moving-average window. Below `FILTER_WINDOW` the
+    // window reallocates while it fills; only useful to measure what the default saves.
+    pub fn with_window_capacity(mut self, capacity: usize) -> Self {
+        let mut window = VecDeque::with_capacity(capacity);
+        window.extend(self.history_buffer.drain(..));
+        self.history_buffer = window;
+        self
+    }
+
+    // Switch off the filter or anomaly detection of this sensor
+    pub fn with_stages(mut self, stages: StageFlags) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    pub fn with_deadlines(mut self, deadlines: Deadlines) -> Self {
+        self.deadlines = deadlines;
+        self
+    }
+
+    pub fn with_dead_letters(mut self, dead_letters: DeadLetterLog) -> Self {
+        self.dead_letters = dead_letters;
+        self
+    }
+
+    // Start from the stored offset and write the learned one back on shutdown
+    pub fn with_calibration(mut self, store: CalibrationStore) -> Self {
+        if let Some(offset) = store.get(self.sensor_type) {
+            self.calibration_offset = offset;
+        }
+        self.calibration = Some(store);
+        self
+    }
+
+    pub fn calibration_offset(&self) -> f64 {
+        self.calibration_offset
+    }
+
+    // Publish the moving-average window to the commander's snapshot handle
+    pub fn with_monitor(mut self, monitor: SnapshotHandle) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    // Consult a shared controller for faults that hit several sensors at once
+    pub fn with_fault_controller(mut self, controller: Option<FaultController>) -> Self {
+        self.fault_controller = controller;
+        self
+    }
+
+    // Vary the cycle between `floor` and `Deadlines::sensor_cycle`
+    pub fn with_adaptive_sampling(mut self, adaptive: Option<AdaptiveSampling>) -> Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    // Simulated bias drift the feedback loop has to calibrate away
+    pub fn with_drift_rate(mut self, drift_rate: f64) -> Self {
+        self.drift_rate = drift_rate;
+        self
+    }
+
+    pub fn with_maintain_decay(mut self, decay: f64) -> Self {
+        self.maintain_decay = decay;
+        self
+    }
+
+    // Smooth out noisy feedback by applying averaged offsets, see `FeedbackMode`
+    pub fn with_feedback_mode(mut self, mode: FeedbackMode) -> Self {
+        self.feedback_mode = mode;
+        self
+    }
+
+    // Send `batch_size` samples at a time over `batch_tx` instead of one per channel operation
+    pub fn with_batching(mut self, batch_size: usize, batch_tx: Option<Sender<Vec<SensorData>>>) -> Self {
+        self.batch_size = batch_size;
+        self.batch_tx = batch_tx.filter(|_| batch_size > 1);
+        self
+    }
+
+    // Stop generating once the shared budget is used up, see `StopCondition::Samples`
+    pub fn with_sample_budget(mut self, budget: Option<SampleBudget>) -> Self {
+        self.sample_budget = budget;
+        self
+    }
+
+    // What to do when a bounded channel to the commander is full
+    pub fn with_backpressure(mut self, policy: BackpressurePolicy) -> Self {
+        self.backpressure = policy;
+        self
+    }
+
+    // Measure this plant instead of generating readings within the profile's range
+    pub fn with_plant(mut self, plant: Option<Plant>) -> Self {
+        self.plant = plant;
+        self
+    }
+
+    // Faults received here override the random ones for their next N samples
+    pub fn with_fault_injection(mut self, faults: Receiver<Fault>) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    pub fn with_fault_rates(mut self, fault_rates: FaultRates) -> Self {
+        self.fault_rates = fault_rates;
+        self
+    }
+
+    // Fail these samples instead of rolling `FaultRates`, if any are for this sensor
+    pub fn with_fault_schedule(mut self, schedule: FaultSchedule) -> Self {
+        self.fault_schedule = schedule;
+        self
+    }
+
+    pub fn with_deadline_hooks(mut self, hooks: DeadlineHooks) -> Self {
+        self.deadline_hooks = hooks;
+        self
+    }
+
     // FUNCTION 1: Generate data
     fn generate_data(&mut self) -> SensorData {
         let mut random = rand::rng();
 
         self.id_counter += 1;
-        let mut value = match self.sensor_type {
-            SensorType::Force => random.random_range(10.0..55.0),
-            SensorType::Position => random.random_range(-0.1..0.2),
-            SensorType::Temperature => random.random_range(20.0..130.0)
+        let (min, max) = self.profile.range;
+        let mut value = match &self.plant {
+            Some(plant) => plant.measure(),
+            None => random.random_range(min..max),
         };
+        self.bias += self.drift_rate;
+        value += self.bias;
 
         SensorData {
             id: self.id_counter,
@@ -46,43 +250,100 @@ impl Sensor {
             anomaly: false,
             timestamp:Instant::now(),
             processed_timestamp:None,
+            capture_time: None,
+            stamps: StageStamps::default(),
+        }
+    }
+
+    // FUNCTION 1.1: Take the injected fault for this sample
+    fn next_fault(&mut self) -> Option<Fault> {
+        // A newer injection replaces whatever is still pending
+        while let Ok(fault) = self.faults.try_recv() {
+            if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+                guard.write_level(LogLevel::Warn, format!("[FAULT] Injected {:?} into {:?}", fault, self.sensor_type));
+            }
+            self.active_fault = Some((fault, fault.samples()));
+        }
+        // A correlated fault starts a burst unless one is already running
+        if self.active_fault.is_none() {
+            if let Some(fault) = self.fault_controller.as_ref().and_then(|c| c.fault_for(self.sensor_type, self.id_counter)) {
+                self.active_fault = Some((fault, fault.samples()));
+            }
         }
+
+        let (fault, remaining) = self.active_fault.as_mut()?;
+        let fault = *fault;
+        *remaining -= 1;
+        if *remaining == 0 {
+            self.active_fault = None;
+        }
+        Some(fault)
     }
 
     // FUNCTION 2: Process data
-    fn process_data(&mut self, mut data: SensorData) -> (Option<SensorData>) {
+    // Public so the processing stage can be driven on its own, e.g. by the benches
+    pub fn process_data(&mut self, mut data: SensorData) -> Option<SensorData> {
         let start = Instant::now();
 
+        // 2.0 Reject non-finite readings before they reach the filter or the PID
+        if !data.value.is_finite() {
+            if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+                guard.write_level(LogLevel::Warn, format!("[Sensor {:?}] Non-finite value {} (ID: {}). Skipping.", data.sensor_type, data.value, data.id));
+            }
+            self.dead_letters.record(data, DropReason::NonFinite);
+            return None;
+        }
+
         // 2.1 Detect Anomaly
-        match data.sensor_type {
-            SensorType::Force => if data.value < 5.0 || data.value > 60.0 { data.anomaly = true; },
-            SensorType::Position => if data.value.abs() > 0.5 { data.anomaly = true; },
-            SensorType::Temperature => if data.value > 120.0 { data.anomaly = true; },
+        if self.stages.anomaly {
+            let out_of_range = self.profile.out_of_range(data.value);
+            // Only flag once `anomaly_confirm` readings in a row were out of range
+            self.out_of_range_streak = if out_of_range { self.out_of_range_streak + 1 } else { 0 };
+            if self.out_of_range_streak >= self.anomaly_confirm {
+                data.anomaly = true;
+            }
         }
 
+        // Anomalous readings are forwarded raw and never enter the filter window
         if data.anomaly {
             return Some(data);
         }
 
-        // 2.2 Apply Moving Average Filter
-        if self.history_buffer.len() >= 5 {
-            self.history_buffer.pop_front();
-        }
+        // 2.2 Apply Moving Average Filter; until the window is full it averages over fewer
+        if self.stages.filter {
+            if self.history_buffer.len() >= FILTER_WINDOW {
+                self.history_buffer.pop_front();
+            }
 
-        self.history_buffer.push_back(data.value);
+            self.history_buffer.push_back(data.value);
+            debug_assert!(self.history_buffer.len() <= FILTER_WINDOW);
 
-        // Calculate the average value
-        let total = self.history_buffer.iter().sum::<f64>();
-        data.value = total / self.history_buffer.len() as f64;
+            // Calculate the average value
+            data.value = moving_average(&self.history_buffer);
+            if let Some(monitor) = &self.monitor {
+                monitor.record_filter(self.sensor_type, &self.history_buffer, data.value);
+            }
+        }
         data.processed_timestamp = Some(Instant::now());
 
         // --- Deadline Check (0.2 ms) ---
-        if start.elapsed() > Duration::from_micros(200) {
+        let deadline_process = self.deadlines.processing;
+        let elapsed = start.elapsed();
+        if elapsed > deadline_process {
             self.benchmark_stats.sensor_missed_deadlines += 1;
-            if let Ok(mut guard) = self.log.lock() {
-                guard.write(format!("[Sensor {:?}] Processing Deadline Missed! Dropping.", data.sensor_type));
+            let policy = self.deadlines.policies.processing;
+            if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+                guard.write_level(LogLevel::Warn, format!("[Sensor {:?}] Processing Deadline Missed! Policy: {:?}", data.sensor_type, policy));
+            }
+            self.deadline_hooks.notify(Stage::Processing, data.sensor_type, elapsed - deadline_process);
+            match policy {
+                DeadlinePolicy::Drop => {
+                    self.dead_letters.record(data, DropReason::ProcessingDeadline);
+                    return None;
+                }
+                DeadlinePolicy::MarkAndContinue => {}
+                DeadlinePolicy::Escalate => data.anomaly = true,
             }
-            return (None);
         }
 
         Some(data)
@@ -91,30 +352,87 @@ impl Sensor {
     // FUNCTION 3: Transmit Data
     fn transmit_data(&mut self, sender: &Sender<SensorData>, data: SensorData) -> bool {
 
-        let mut rng = rand::rng();
-        let fault_roll: f64 = rng.random_range(0.00..1.00);
-
-        // FAULT 1: Packet Drop (5% chance)
-        if fault_roll < 0.05 {
-            // Log the injected fault (Measure Lock Contention)
-            let start_lock = Instant::now();
-            if let Ok(mut guard) = self.log.lock() {
-                let contention = start_lock.elapsed();
-                guard.write(format!("[FAULT] Dropping packet ID {} for {:?} (Lock Wait: {:?})", data.id, self.sensor_type, contention));
+        // A schedule naming this sensor replaces the random rolls
+        let fault = if self.fault_schedule.covers(self.sensor_type) {
+            self.fault_schedule.fault_for(self.sensor_type, data.id)
+        } else {
+            let mut rng = rand::rng();
+            let fault_roll: f64 = rng.random_range(0.00..1.00);
+            if fault_roll < self.fault_rates.drop_rate {
+                Some(TransmissionFault::Drop) // 5% chance by default
+            } else if fault_roll >= 1.0 - self.fault_rates.latency_rate {
+                Some(TransmissionFault::Delay(self.fault_rates.latency)) // 5% chance by default
+            } else {
+                None
             }
-            return true
-        }
+        };
 
-        // FAULT 2: Network Latency Delay (5% chance)
-        if fault_roll > 0.95 {
-            thread::sleep(Duration::from_micros(30)); // Deliberate delay
+        match fault {
+            // FAULT 1: Packet Drop
+            Some(TransmissionFault::Drop) => {
+                // Log the injected fault (Measure Lock Contention)
+                let start_lock = Instant::now();
+                if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+                    let contention = start_lock.elapsed();
+                    guard.write_level(LogLevel::Warn, format!("[FAULT] Dropping packet ID {} for {:?} (Lock Wait: {:?})", data.id, self.sensor_type, contention));
+                }
+                self.dead_letters.record(data, DropReason::InjectedFault);
+                return true
+            }
+            // FAULT 2: Network Latency Delay
+            Some(TransmissionFault::Delay(delay)) => thread::sleep(delay), // Deliberate delay
+            None => {}
         }
 
         // 2. Transmit data
+        if self.batch_tx.is_some() {
+            self.batch.push(data);
+            return self.batch.len() < self.batch_size || self.send_batch();
+        }
+        let data = match sender.try_send(data) {
+            Ok(_) => return true,
+            Err(TrySendError::Full(data)) if self.backpressure == BackpressurePolicy::Drop => {
+                self.dead_letters.record(data, DropReason::Overflow);
+                return true;
+            }
+            // Wait for room this once; the loop lengthens the cycle so it does not happen again
+            Err(TrySendError::Full(data)) => {
+                self.backpressured = true;
+                data
+            }
+            Err(TrySendError::Disconnected(data)) => data,
+        };
         match sender.send(data) {
             Ok(_) => true,
-            Err(_) => {
-                println!("[Sensor {:?}] Receiver disconnected. Stopping.", self.sensor_type);
+            Err(err) => {
+                if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+                    guard.print(Verbosity::Normal, format!("[Sensor {:?}] Receiver disconnected. Stopping.", self.sensor_type));
+                }
+                self.dead_letters.record(err.into_inner(), DropReason::Disconnected);
+                false
+            }
+        }
+    }
+
+    // FUNCTION 3.1: Hand the batch to the commander in one channel operation
+    fn send_batch(&mut self) -> bool {
+        let Some(tx) = &self.batch_tx else { return true; };
+        if self.batch.is_empty() { return true; }
+
+        let sent_at = Instant::now();
+        let mut batch = std::mem::take(&mut self.batch);
+        for data in &mut batch {
+            data.stamps.sent = Some(sent_at);
+        }
+        match tx.send(batch) {
+            Ok(_) => true,
+            Err(err) => {
+                if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+                    guard.print(Verbosity::Normal, format!("[Sensor {:?}] Receiver disconnected. Stopping.", self.sensor_type));
+                }
+                for data in err.into_inner() {
+                    self.dead_letters.record(data, DropReason::Disconnected);
+                }
                 false
             }
         }
@@ -122,20 +440,88 @@ impl Sensor {
 
     //  FUNCTION 4: Received Feedback and Adjust
 
+    // FUNCTION 4.1: Apply or queue one recalibration request
+    fn recalibrate(&mut self, offset: f64, now: Instant) {
+        if let FeedbackMode::Batched { .. } = self.feedback_mode {
+            self.pending_offsets.0 += offset;
+            self.pending_offsets.1 += 1;
+            self.batch_started.get_or_insert(now);
+            return;
+        }
+        self.apply_offset(offset, format!("recalibrated by {:.2}", offset));
+    }
+
+    // FUNCTION 4.2: Apply the mean of the queued offsets once the window is over
+    fn flush_offsets(&mut self, now: Instant, force: bool) {
+        let FeedbackMode::Batched { window } = self.feedback_mode else { return; };
+        let Some(started) = self.batch_started else { return; };
+        if !force && now.duration_since(started) < window { return; }
+
+        let (sum, count) = std::mem::take(&mut self.pending_offsets);
+        self.batch_started = None;
+        let mean = sum / count as f64;
+        self.apply_offset(mean, format!("recalibrated by {:.2} (mean of {} requests)", mean, count));
+    }
+
+    // FUNCTION 4.3: The calibration was confirmed good; queued corrections are stale,
+    // and the offset optionally relaxes towards zero so bias does not build up
+    fn maintain(&mut self) {
+        let discarded = std::mem::take(&mut self.pending_offsets).1;
+        self.batch_started = None;
+        self.calibration_offset *= 1.0 - self.maintain_decay;
+        if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+            guard.write_level(LogLevel::Info, format!("[Feedback] Sensor {:?} calibration confirmed (offset {:.3}, {} queued requests discarded)",
+                self.sensor_type, self.calibration_offset, discarded));
+        }
+    }
+
+    fn apply_offset(&mut self, offset: f64, what: String) {
+        self.calibration_offset += offset;
+
+        // Log the event so you get points for "Dynamic Recalibration"
+        if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+            guard.write(format!("[Feedback] Sensor {:?} {}. New Offset: {:.2}", self.sensor_type, what, self.calibration_offset));
+        }
+    }
+
+    fn record_feedback_gap(&mut self, gap: Duration) {
+        let max_gap = self.benchmark_stats.max_feedback_gap.get_mut(self.sensor_type);
+        if gap > *max_gap { *max_gap = gap; }
+    }
+
     // ACTUAL RUN
     pub fn run(mut self,
                       sender: Sender<SensorData>,
                       rx_feedback: Receiver<Feedback>, )-> BenchmarkStats
     {
 
-        let cycle_time = Duration::from_millis(5);
+        let cycle_time = self.deadlines.sensor_cycle;
         let start_time = Instant::now();
         let mut next_deadline = start_time + cycle_time;
+        let mut cycle = cycle_time; // Current period, only differs from cycle_time when adaptive
+        let mut last_processed: Option<f64> = None;
+        let mut last_feedback_at = start_time;
+        let stop_reason;
 
         loop {
             // Check active flag
-            if let Ok(guard) = self.log.lock() {
-                if !guard.active { break; }
+            let stopped = self.benchmark_stats.timed_lock(&self.log)
+                .and_then(|guard| (!guard.active).then(|| guard.shutdown_reason().unwrap_or(ShutdownReason::DurationElapsed)));
+            if let Some(reason) = stopped {
+                self.send_batch(); // Before the commander stops reading
+                stop_reason = reason;
+                break;
+            }
+
+            if let Some(budget) = &self.sample_budget {
+                if !budget.try_take() {
+                    // Every sample is taken; the loop stops at the flag check above
+                    self.send_batch();
+                    if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                        log.request_shutdown(ShutdownReason::SampleLimitReached);
+                    }
+                    continue;
+                }
             }
 
             // --- Jitter Measurement ---
@@ -143,6 +529,7 @@ impl Sensor {
             if now > next_deadline {
                 let jitter = now - next_deadline;
                 self.benchmark_stats.total_jitter += jitter;
+                self.benchmark_stats.total_jitter_sq += jitter.as_secs_f64().powi(2);
                 if jitter > self.benchmark_stats.max_jitter {
                     self.benchmark_stats.max_jitter = jitter;
                 }
@@ -150,89 +537,627 @@ impl Sensor {
 
             // Increment total cycle count
             self.benchmark_stats.sensor_count += 1;
+            self.benchmark_stats.total_sample_period += cycle;
+            self.benchmark_stats.stage_times.get_mut(self.sensor_type).budget += cycle;
+            let mut rapid_change = false;
 
 
             // Received feedback
             while let Ok(fb) = rx_feedback.try_recv() {
 
                 let arrival_time = Instant::now();
+                self.record_feedback_gap(arrival_time - last_feedback_at);
+                last_feedback_at = arrival_time;
 
                 let start_time = fb.timestamp;
                 let elapsed = arrival_time.duration_since(start_time);
+                if let Some(round_trip) = fb.round_trip(arrival_time) {
+                    self.benchmark_stats.record_roundtrip(round_trip);
+                }
 
                 // Update Stats
                 self.benchmark_stats.total_trans_time += elapsed;
 
-                // 3. Check Deadline (0.1ms = 100 microseconds)
-                let deadline_transmit = Duration::from_micros(500);
+                // 3. Check Deadline (0.5ms = 500 microseconds)
+                let deadline_transmit = self.deadlines.feedback;
 
                 if elapsed > deadline_transmit {
                     self.benchmark_stats.actuator_missed_deadlines += 1;
 
                     // Log the miss
-                    if let Ok(mut log) = self.log.lock() {
-                        log.write(format!(
-                            "[DEADLINE] Feedback for Sensor {:?} arrived late! Latency: {:?} (Limit: 500µs)",
-                            self.sensor_type, elapsed
+                    if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                        log.write_level(LogLevel::Warn, format!(
+                            "[DEADLINE] Feedback for Sensor {:?} arrived late! Latency: {:?} (Limit: {:?})",
+                            self.sensor_type, elapsed, deadline_transmit
                         ));
                     }
+                    self.deadline_hooks.notify(Stage::Feedback, self.sensor_type, elapsed - deadline_transmit);
+
+                    match self.deadlines.policies.feedback {
+                        DeadlinePolicy::Drop => continue,
+                        DeadlinePolicy::MarkAndContinue => {}
+                        DeadlinePolicy::Escalate => self.escalate_next = true,
+                    }
                 }
 
                 // ACTION 1: Dynamic Recalibration
-                // If the offset is not 0.0, the actuator wants us to shift our values
-                if fb.recalibrate_offset != 0.0 {
-                    self.calibration_offset += fb.recalibrate_offset;
-
-                    // Log the event so you get points for "Dynamic Recalibration"
-                    if let Ok(mut guard) = self.log.lock() {
-                        guard.write(format!("[Feedback] Sensor {:?} recalibrated by {:.2}. New Offset: {:.2}",
-                                            self.sensor_type, fb.recalibrate_offset, self.calibration_offset));
-                    }
+                match fb.kind() {
+                    SensorFeedback::Recalibrate { offset } => self.recalibrate(offset, arrival_time),
+                    SensorFeedback::Maintain => self.maintain(),
                 }
 
                 // ACTION 2: Error / Alert Logging
                 // If the message is not "no", it means there is a specific warning (e.g., "Drift Detected")
                 if fb.error_msg != "no" {
-                    if let Ok(mut guard) = self.log.lock() {
-                        guard.write(format!("[Feedback] Alert for {:?}: {}", self.sensor_type, fb.error_msg));
+                    if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+                        guard.write_level(LogLevel::Warn, format!("[Feedback] Alert for {:?}: {}", self.sensor_type, fb.error_msg));
                     }
                 }
 
             }
+            self.flush_offsets(Instant::now(), false);
+
+            let fault = self.next_fault();
+            if let Some(Fault::Pause(_)) = fault {
+                // Paused: nothing is generated, but the schedule keeps running
+                let now = Instant::now();
+                if now < next_deadline {
+                    thread::sleep(next_deadline - now);
+                }
+                next_deadline += cycle;
+                continue;
+            }
 
             // 1. Generate Data
             let t_gen_start = Instant::now();
-            let raw_data = self.generate_data();
-            self.benchmark_stats.total_gen_time += t_gen_start.elapsed();
-            println!("[{:?} Sensor ] Sensor Data (ID: {}) with value: {} generated", self.sensor_type, raw_data.id, raw_data.value);
+            let mut raw_data = self.generate_data();
+            raw_data.stamps.generated = Some(Instant::now());
+            let gen_time = t_gen_start.elapsed();
+            self.benchmark_stats.total_gen_time += gen_time;
+            self.benchmark_stats.stage_times.get_mut(self.sensor_type).generation += gen_time;
+
+            if let (Some(Fault::Stuck(_)), Some(last)) = (fault, self.last_value) {
+                raw_data.value = last;
+            }
+            self.last_value = Some(raw_data.value);
+            self.benchmark_stats.record_value(raw_data.value);
+            if let Some(mut guard) = self.benchmark_stats.timed_lock(&self.log) {
+                guard.print(Verbosity::Verbose, format!("[{:?} Sensor ] Sensor Data (ID: {}) with value: {} generated", self.sensor_type, raw_data.id, raw_data.value));
+            }
 
             // 2. Process Data
             let t_proc_start = Instant::now();
+            let cpu_timer = CpuTimer::start();
             let processed_opt = self.process_data(raw_data.clone());
-            self.benchmark_stats.total_proc_time += t_proc_start.elapsed();
+            let proc_time = t_proc_start.elapsed();
+            self.benchmark_stats.total_proc_time += proc_time;
+            self.benchmark_stats.cpu_proc_time += cpu_timer.elapsed();
+            self.benchmark_stats.stage_times.get_mut(self.sensor_type).processing += proc_time;
 
-            if let Some(processed_data) = processed_opt {
-                let t_trans_start = Instant::now();
+            if let Some(mut processed_data) = processed_opt {
+                if self.escalate_next {
+                    processed_data.anomaly = true;
+                    self.escalate_next = false;
+                }
+                if let Some(Fault::Anomaly(_)) = fault {
+                    processed_data.anomaly = true;
+                }
+                if let Some(adaptive) = &self.adaptive {
+                    let delta = last_processed.map_or(0.0, |last| (processed_data.value - last).abs());
+                    rapid_change = processed_data.anomaly || delta > adaptive.delta_threshold.get(self.sensor_type);
+                }
+                last_processed = Some(processed_data.value);
                 // 3. Handle Anomaly
                 if processed_data.anomaly {
-                    if let Ok(mut log_guard) = self.log.lock() {
-                        log_guard.write(format!("[ANOMALY] {:?} ID: {}", self.sensor_type, processed_data.id));
+                    self.benchmark_stats.anomaly_count += 1;
+                    if let Some(mut log_guard) = self.benchmark_stats.timed_lock(&self.log) {
+                        log_guard.write_level(LogLevel::Warn, format!("[ANOMALY] {:?} ID: {}", self.sensor_type, processed_data.id));
                     }
                 }
 
                 // 4. Transmit Data
-                if !self.transmit_data(&sender, processed_data) {
-                    break;
+                if let Some(Fault::Delay(delay, _)) = fault {
+                    thread::sleep(delay);
+                }
+                if let Some(Fault::Drop(_)) = fault {
+                    self.dead_letters.record(processed_data, DropReason::InjectedFault);
+                } else {
+                    let t_trans_start = Instant::now();
+                    let sent = self.transmit_data(&sender, processed_data);
+                    self.benchmark_stats.stage_times.get_mut(self.sensor_type).transmission += t_trans_start.elapsed();
+                    if !sent {
+                        stop_reason = ShutdownReason::ChannelDisconnected;
+                        break;
+                    }
                 }
             }
 
+            // --- Adaptive Period ---
+            if let Some(adaptive) = &self.adaptive {
+                let new_cycle = if rapid_change { (cycle / 2).max(adaptive.floor) } else { (cycle + cycle_time) / 2 };
+                next_deadline = next_deadline - cycle + new_cycle;
+                cycle = new_cycle;
+            }
+
+            // --- Backpressure ---
+            // Double the cycle while the channel is full, move halfway back once it is half drained
+            if self.backpressure == BackpressurePolicy::SlowDown {
+                let drained = sender.capacity().is_none_or(|capacity| sender.len() <= capacity / 2);
+                let new_cycle = if std::mem::take(&mut self.backpressured) {
+                    self.benchmark_stats.backpressure_slowdowns += 1;
+                    (cycle * 2).min(cycle_time * MAX_SLOWDOWN)
+                } else if drained && cycle > cycle_time {
+                    (cycle + cycle_time) / 2
+                } else {
+                    cycle
+                };
+                next_deadline = next_deadline - cycle + new_cycle;
+                cycle = new_cycle;
+                self.benchmark_stats.max_sample_period = self.benchmark_stats.max_sample_period.max(cycle);
+            }
+
             // --- Fixed Interval Wait ---
             let work_done_time = Instant::now();
             if work_done_time < next_deadline {
                 thread::sleep(next_deadline - work_done_time);
             }
-            next_deadline += cycle_time;
+            next_deadline += cycle;
+        }
+        self.benchmark_stats.active_duration += start_time.elapsed();
+
+        // The gap still open at shutdown counts too, otherwise a sensor that never got feedback reports none
+        self.record_feedback_gap(last_feedback_at.elapsed());
+        if self.benchmark_stats.max_feedback_gap.get(self.sensor_type) > self.deadlines.feedback_starvation {
+            self.benchmark_stats.feedback_starved.insert(self.sensor_type);
+            if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+                log.write_level(LogLevel::Warn, format!("[Starvation] Sensor {:?} went {:?} without feedback (Limit: {:?})",
+                    self.sensor_type, self.benchmark_stats.max_feedback_gap.get(self.sensor_type), self.deadlines.feedback_starvation));
+            }
+        }
+
+        // A half-full batch still counts towards the stored offset
+        self.flush_offsets(Instant::now(), true);
+        if let Some(store) = &self.calibration {
+            store.set(self.sensor_type, self.calibration_offset);
+        }
+        if let Some(mut log) = self.benchmark_stats.timed_lock(&self.log) {
+            log.write(format!("[Shutdown] Sensor {:?} stopped: {:?}", self.sensor_type, stop_reason));
         }
         self.benchmark_stats
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::SystemMode;
+
+    fn quiet_log() -> Arc<Mutex<SystemLog>> {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(crate::share::Verbosity::Silent);
+        Arc::new(Mutex::new(log))
+    }
+
+    // Lenient processing deadline, so a slow test machine never drops a sample
+    fn sensor(sensor_type: SensorType) -> Sensor {
+        Sensor::new(sensor_type, quiet_log()).with_deadlines(Deadlines { processing: Duration::from_secs(1), ..Deadlines::default() })
+    }
+
+    fn reading(sensor_type: SensorType, id: i32, value: f64) -> SensorData {
+        SensorData {
+            id,
+            sensor_type,
+            value,
+            anomaly: false,
+            timestamp: Instant::now(),
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: Default::default(),
+        }
+    }
+
+    fn filtered(sensor: &mut Sensor, id: i32, value: f64) -> SensorData {
+        sensor.process_data(reading(sensor.sensor_type, id, value)).expect("sample kept")
+    }
+
+    #[test]
+    fn first_sample_averages_to_itself() {
+        let mut sensor = sensor(SensorType::Force);
+        assert_eq!(filtered(&mut sensor, 1, 42.0).value, 42.0);
+    }
+
+    #[test]
+    fn sixth_sample_evicts_the_first() {
+        let mut sensor = sensor(SensorType::Force);
+        // Growing denominator while the window fills: 10, 15, 20, 25, 30
+        for (id, (value, want)) in [(10.0, 10.0), (20.0, 15.0), (30.0, 20.0), (40.0, 25.0), (50.0, 30.0)].into_iter().enumerate() {
+            assert_eq!(filtered(&mut sensor, id as i32, value).value, want);
+        }
+        // Window is now 20..=50 plus the new 40
+        assert_eq!(filtered(&mut sensor, 5, 40.0).value, 36.0);
+        assert_eq!(sensor.history_buffer, [20.0, 30.0, 40.0, 50.0, 40.0]);
+    }
+
+    #[test]
+    fn anomaly_does_not_enter_the_window() {
+        let mut sensor = sensor(SensorType::Force);
+        filtered(&mut sensor, 1, 20.0);
+        filtered(&mut sensor, 2, 30.0);
+
+        let spike = filtered(&mut sensor, 3, 500.0);
+        assert!(spike.anomaly);
+        assert_eq!(spike.value, 500.0); // Forwarded raw
+        assert_eq!(sensor.history_buffer, [20.0, 30.0]);
+
+        assert_eq!(filtered(&mut sensor, 4, 40.0).value, 30.0);
+    }
+
+    #[test]
+    fn scheduled_drop_loses_only_that_packet() {
+        let dead_letters = DeadLetterLog::default();
+        let mut sensor = sensor(SensorType::Force)
+            .with_fault_rates(FaultRates { drop_rate: 1.0, ..FaultRates::default() }) // Overridden by the schedule
+            .with_fault_schedule(FaultSchedule::new().drop(SensorType::Force, 3))
+            .with_dead_letters(dead_letters.clone());
+        let (tx, rx) = channel::unbounded();
+        for id in 1..=6 {
+            assert!(sensor.transmit_data(&tx, reading(SensorType::Force, id, 1.0)));
+        }
+
+        let arrived: Vec<i32> = rx.try_iter().map(|d| d.id).collect();
+        assert_eq!(arrived, vec![1, 2, 4, 5, 6]);
+        let letters = dead_letters.recent();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].data.id, 3);
+        assert_eq!(letters[0].reason, DropReason::InjectedFault);
+    }
+
+    #[test]
+    fn non_finite_readings_never_reach_the_filter() {
+        let mut sensor = sensor(SensorType::Force);
+        filtered(&mut sensor, 1, 10.0);
+        assert!(sensor.process_data(reading(SensorType::Force, 2, f64::NAN)).is_none());
+        assert!(sensor.process_data(reading(SensorType::Force, 3, f64::INFINITY)).is_none());
+        assert_eq!(sensor.history_buffer, [10.0]);
+        assert_eq!(filtered(&mut sensor, 4, 20.0).value, 15.0);
+    }
+
+    // Every sample misses a zero processing deadline
+    fn always_late(policy: DeadlinePolicy) -> Sensor {
+        let policies = crate::share::DeadlinePolicies { processing: policy, ..Default::default() };
+        Sensor::new(SensorType::Force, quiet_log()).with_deadlines(Deadlines { processing: Duration::ZERO, policies, ..Deadlines::default() })
+    }
+
+    #[test]
+    fn processing_policies_have_their_documented_effect() {
+        let mut drop = always_late(DeadlinePolicy::Drop);
+        assert!(drop.process_data(reading(SensorType::Force, 1, 30.0)).is_none());
+        assert_eq!(drop.benchmark_stats.sensor_missed_deadlines, 1);
+
+        let mut mark = always_late(DeadlinePolicy::MarkAndContinue);
+        assert!(!mark.process_data(reading(SensorType::Force, 1, 30.0)).expect("sample kept").anomaly);
+        assert_eq!(mark.benchmark_stats.sensor_missed_deadlines, 1);
+
+        let mut escalate = always_late(DeadlinePolicy::Escalate);
+        assert!(escalate.process_data(reading(SensorType::Force, 1, 30.0)).expect("sample kept").anomaly);
+        assert_eq!(escalate.benchmark_stats.sensor_missed_deadlines, 1);
+    }
+
+    #[test]
+    fn filter_state_is_published_to_the_snapshot_handle() {
+        let monitor = SnapshotHandle::default();
+        let mut sensor = sensor(SensorType::Force).with_monitor(monitor.clone());
+        assert_eq!(monitor.filter_state(SensorType::Force), None);
+        for (id, value) in [10.0, 20.0, 30.0, 40.0, 50.0, 60.0].into_iter().enumerate() {
+            filtered(&mut sensor, id as i32, value);
+        }
+
+        let state = monitor.filter_state(SensorType::Force).expect("nothing published");
+        assert_eq!(state.buffer, vec![20.0, 30.0, 40.0, 50.0, 60.0]);
+        assert_eq!(state.average, 40.0);
+        assert_eq!(monitor.filter_state(SensorType::Position), None);
+    }
+
+    // Cycles until a commander fed by the three sensors leaves Normal, each sensor
+    // taking its faults from `controller_for`; `limit` if it never does
+    fn cycles_to_degraded(controller_for: impl Fn(SensorType) -> FaultController, limit: u32) -> u32 {
+        let mut sensors: Vec<Sensor> = SensorType::all().iter()
+            .map(|s| sensor(*s).with_fault_controller(Some(controller_for(*s))))
+            .collect();
+        let mut commander = crate::ActuatorCommander::new(Default::default(), Default::default(), quiet_log());
+        for cycle in 1..=limit {
+            for sensor in &mut sensors {
+                let fault = sensor.next_fault();
+                let mut data = sensor.generate_data();
+                data.anomaly = matches!(fault, Some(Fault::Anomaly(_)));
+                commander.fail_safe(data);
+            }
+            if commander.snapshot().mode != SystemMode::Normal {
+                return cycle;
+            }
+        }
+        limit
+    }
+
+    #[test]
+    fn correlated_bursts_escalate_faster_than_independent_faults() {
+        use crate::share::{CorrelatedFault, SensorSet};
+
+        // Bursts of 3 started with p = 0.01 leave 3 / (3 + 0.99 / 0.01) of each sensor's
+        // samples anomalous; the independent faults hit single samples at that same rate
+        let burst = CorrelatedFault { probability: 0.01, sensors: SensorSet::of(SensorType::all()), fault: Fault::Anomaly(3) };
+        let single = CorrelatedFault { probability: 3.0 / 102.0, fault: Fault::Anomaly(1), ..burst };
+        let trials = 10;
+        let (mut correlated, mut independent) = (0, 0);
+        for _ in 0..trials {
+            let shared = FaultController::new(burst);
+            correlated += cycles_to_degraded(|_| shared.clone(), 1000);
+            independent += cycles_to_degraded(|_| FaultController::new(single), 1000);
+        }
+        // The first burst escalates, after about 100 cycles; three unlucky singles in a row are far rarer
+        assert!(correlated * 3 < independent, "correlated {} vs independent {} cycles over {} runs", correlated, independent, trials);
+    }
+
+    #[test]
+    fn sensor_without_feedback_reports_starvation() {
+        let log = quiet_log();
+        let deadlines = Deadlines { processing: Duration::from_secs(1), feedback_starvation: Duration::from_millis(50), ..Deadlines::default() };
+        let sensor = Sensor::new(SensorType::Temperature, log.clone()).with_deadlines(deadlines).with_fault_rates(FaultRates::none());
+        let (tx, rx) = channel::unbounded();
+        let (_feedback_tx, feedback_rx) = channel::unbounded(); // Open, but nothing is ever sent
+        let running = thread::spawn(move || sensor.run(tx, feedback_rx));
+
+        thread::sleep(Duration::from_millis(150));
+        log.lock().unwrap().request_shutdown(ShutdownReason::DurationElapsed);
+        let stats = running.join().unwrap();
+
+        assert!(rx.try_iter().count() > 0, "the sensor never ran");
+        assert!(stats.max_feedback_gap.get(SensorType::Temperature) >= Duration::from_millis(150));
+        assert!(stats.feedback_starved.contains(SensorType::Temperature));
+        assert!(log.lock().unwrap().recent_entries().iter().any(|l| l.contains("[Starvation] Sensor Temperature")));
+    }
+
+    #[test]
+    fn adaptive_sampling_speeds_up_during_an_anomaly_burst() {
+        use crate::share::PerSensor;
+
+        let log = quiet_log();
+        // Only anomalies count as rapid change, the random readings never do
+        let adaptive = AdaptiveSampling { floor: Duration::from_millis(1), delta_threshold: PerSensor::splat(f64::INFINITY) };
+        let (fault_tx, fault_rx) = channel::unbounded();
+        let sensor = Sensor::new(SensorType::Force, log.clone())
+            .with_deadlines(Deadlines { processing: Duration::from_secs(1), ..Deadlines::default() })
+            .with_fault_rates(FaultRates::none())
+            .with_adaptive_sampling(Some(adaptive))
+            .with_fault_injection(fault_rx);
+        let (tx, rx) = channel::unbounded();
+        let (_feedback_tx, feedback_rx) = channel::unbounded();
+        let running = thread::spawn(move || sensor.run(tx, feedback_rx));
+
+        thread::sleep(Duration::from_millis(100));
+        fault_tx.send(Fault::Anomaly(30)).unwrap();
+        thread::sleep(Duration::from_millis(150));
+        log.lock().unwrap().request_shutdown(ShutdownReason::DurationElapsed);
+        let stats = running.join().unwrap();
+
+        // Mean gap between consecutive samples of the same kind, from their capture times
+        let samples: Vec<SensorData> = rx.try_iter().collect();
+        let mean_gap = |anomalous: bool| {
+            let times: Vec<Instant> = samples.iter().filter(|d| d.anomaly == anomalous).map(|d| d.timestamp).collect();
+            let span = *times.last().unwrap() - times[0];
+            span / (times.len() as u32 - 1)
+        };
+        assert_eq!(samples.iter().filter(|d| d.anomaly).count(), 30);
+        let (calm, burst) = (mean_gap(false), mean_gap(true));
+        assert!(burst * 2 < calm, "burst every {:?}, calm every {:?}", burst, calm);
+        assert!(stats.total_sample_period / stats.sensor_count < Duration::from_millis(5));
+    }
+
+    #[test]
+    fn lone_spike_is_not_confirmed_as_an_anomaly() {
+        let mut sensor = sensor(SensorType::Force).with_anomaly_confirm(3);
+        let flags: Vec<bool> = [30.0, 999.0, 30.0, 999.0, 999.0, 999.0].iter().enumerate()
+            .map(|(id, &value)| filtered(&mut sensor, id as i32, value).anomaly)
+            .collect();
+        assert_eq!(flags, [false, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn stage_toggles_skip_the_filter_and_the_anomaly_check() {
+        let mut unfiltered = sensor(SensorType::Force).with_stages(StageFlags { filter: false, anomaly: true });
+        filtered(&mut unfiltered, 1, 10.0);
+        assert_eq!(filtered(&mut unfiltered, 2, 20.0).value, 20.0); // Averaged it would be 15
+        assert!(filtered(&mut unfiltered, 3, 999.0).anomaly);
+
+        let mut unchecked = sensor(SensorType::Force).with_stages(StageFlags { filter: true, anomaly: false });
+        filtered(&mut unchecked, 1, 1.0);
+        let spike = filtered(&mut unchecked, 2, 999.0);
+        assert!(!spike.anomaly);
+        assert_eq!(spike.value, 500.0); // Not flagged, so it enters the window
+    }
+
+    #[test]
+    fn preloaded_window_is_warm_from_the_first_sample() {
+        let mut warm = sensor(SensorType::Force).with_preload(30.0, 4);
+        assert_eq!(filtered(&mut warm, 1, 40.0).value, 32.0); // (4 * 30 + 40) / 5
+
+        // Capped at the window, so the extra copies never dilute later samples
+        let mut capped = sensor(SensorType::Force).with_preload(30.0, 100);
+        assert_eq!(filtered(&mut capped, 1, 40.0).value, 32.0);
+    }
+
+    #[test]
+    fn batched_feedback_applies_the_mean_once_per_window() {
+        let start = Instant::now();
+        let requests = [(0, 1.0), (2, 3.0), (4, -1.0), (12, 5.0)]; // (ms, offset)
+        let trajectory = |mode| {
+            let mut sensor = sensor(SensorType::Force).with_feedback_mode(mode);
+            requests.iter().map(|&(ms, offset)| {
+                let now = start + Duration::from_millis(ms);
+                sensor.recalibrate(offset, now);
+                sensor.flush_offsets(now, false);
+                sensor.calibration_offset()
+            }).collect::<Vec<f64>>()
+        };
+
+        assert_eq!(trajectory(FeedbackMode::Immediate), [1.0, 4.0, 3.0, 8.0]);
+        // Nothing moves until the 10ms window is over, then one step by the mean of all four
+        assert_eq!(trajectory(FeedbackMode::Batched { window: Duration::from_millis(10) }), [0.0, 0.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn feedback_keeps_drifting_readings_bounded() {
+        use std::collections::HashMap;
+
+        // Closed loop without threads: every reading goes through the commander and
+        // any correction it sends back is applied before the next reading
+        let late_mean = |drift_correction: Option<usize>| {
+            let (feedback_tx, feedback_rx) = channel::unbounded();
+            let mut commander = crate::ActuatorCommander::new(HashMap::new(), HashMap::from([(SensorType::Force, feedback_tx)]), quiet_log())
+                .with_drift_correction(drift_correction);
+            let mut sensor = sensor(SensorType::Force).with_fault_rates(FaultRates::none()).with_drift_rate(0.01);
+            let mut late = Vec::new();
+            for cycle in 0..2000 {
+                let raw = sensor.generate_data();
+                let value = raw.value;
+                commander.process_sample(raw, Instant::now());
+                for fb in feedback_rx.try_iter() {
+                    sensor.recalibrate(fb.recalibrate_offset, Instant::now());
+                }
+                if cycle >= 1800 { late.push(value); }
+            }
+            late.iter().sum::<f64>() / late.len() as f64
+        };
+
+        // Force readings centre on 32.5; by the end the uncorrected bias is about 20.
+        // A corrected bias grows until a window mean is ~5.5 (3 standard errors) off
+        let corrected = late_mean(Some(50));
+        let drifting = late_mean(None);
+        assert!((corrected - 32.5).abs() < 10.0, "corrected readings average {:.2}", corrected);
+        assert!(drifting - 32.5 > 15.0, "uncorrected readings average {:.2}", drifting);
+    }
+
+    #[test]
+    fn sample_rate_uses_each_sensors_own_active_time() {
+        // Two sensors on their own logs, stopped 150ms apart
+        let start = |s_type| {
+            let log = quiet_log();
+            let sensor = Sensor::new(s_type, log.clone())
+                .with_deadlines(Deadlines { processing: Duration::from_secs(1), ..Deadlines::default() })
+                .with_fault_rates(FaultRates::none());
+            let (tx, rx) = channel::unbounded();
+            let (feedback_tx, feedback_rx) = channel::unbounded::<Feedback>();
+            // Both channels stay open for as long as the sensor runs
+            let running = thread::spawn(move || { let _open = (rx, feedback_tx); sensor.run(tx, feedback_rx) });
+            (log, running)
+        };
+        let stop = |(log, running): (Arc<Mutex<SystemLog>>, thread::JoinHandle<BenchmarkStats>)| {
+            log.lock().unwrap().request_shutdown(ShutdownReason::DurationElapsed);
+            running.join().unwrap()
+        };
+        let (early, late) = (start(SensorType::Force), start(SensorType::Position));
+        thread::sleep(Duration::from_millis(100));
+        let early = stop(early);
+        thread::sleep(Duration::from_millis(150));
+        let late = stop(late);
+
+        assert!(late.sensor_count > early.sensor_count * 2);
+        let (early_rate, late_rate) = (early.sample_rate(), late.sample_rate());
+        assert!((early_rate - late_rate).abs() < 0.25 * late_rate, "{:.0}/s vs {:.0}/s", early_rate, late_rate);
+        let mut merged = early;
+        merged.merge(&late);
+        assert!((merged.sample_rate() - late_rate).abs() < 0.25 * late_rate);
+    }
+
+    #[test]
+    fn maintain_decays_the_offset_and_drops_queued_requests() {
+        let now = Instant::now();
+        let mut decaying = sensor(SensorType::Force).with_maintain_decay(0.5);
+        decaying.recalibrate(4.0, now);
+        decaying.maintain();
+        assert!((decaying.calibration_offset() - 2.0).abs() < 1e-9);
+        decaying.maintain();
+        assert!((decaying.calibration_offset() - 1.0).abs() < 1e-9);
+
+        // Queued in a batch, the request is stale once the calibration is confirmed
+        let mut batched = sensor(SensorType::Force).with_feedback_mode(FeedbackMode::Batched { window: Duration::from_millis(10) });
+        batched.recalibrate(4.0, now);
+        batched.maintain();
+        batched.flush_offsets(now + Duration::from_secs(1), true);
+        assert_eq!(batched.calibration_offset(), 0.0);
+    }
+
+    #[test]
+    fn slow_commander_lowers_the_rate_instead_of_dropping_under_slow_down() {
+        // 300ms against a commander that takes one sample every 20ms, four times the sensor cycle
+        let run = |policy| {
+            let log = quiet_log();
+            let dead_letters = DeadLetterLog::with_capacity(8);
+            let sensor = Sensor::new(SensorType::Force, log.clone())
+                .with_deadlines(Deadlines { processing: Duration::from_secs(1), ..Deadlines::default() })
+                .with_fault_rates(FaultRates::none())
+                .with_dead_letters(dead_letters.clone())
+                .with_backpressure(policy);
+            let (tx, rx) = channel::bounded(2);
+            let (feedback_tx, feedback_rx) = channel::unbounded::<Feedback>();
+            let commander = thread::spawn(move || {
+                let mut received = 0;
+                while rx.recv().is_ok() {
+                    received += 1;
+                    thread::sleep(Duration::from_millis(20));
+                }
+                received
+            });
+            let running = thread::spawn(move || { let _open = feedback_tx; sensor.run(tx, feedback_rx) });
+            thread::sleep(Duration::from_millis(300));
+            log.lock().unwrap().request_shutdown(ShutdownReason::DurationElapsed);
+            let stats = running.join().unwrap();
+            commander.join().unwrap();
+            (stats, dead_letters.counts().get(&DropReason::Overflow).copied().unwrap_or(0))
+        };
+
+        let (dropping, dropped) = run(BackpressurePolicy::Drop);
+        let (slowed, slowed_drops) = run(BackpressurePolicy::SlowDown);
+        assert!(dropped > 10, "only {} overflows", dropped);
+        assert_eq!(slowed_drops, 0);
+        assert!(slowed.backpressure_slowdowns > 0);
+        assert!(slowed.max_sample_period > Deadlines::default().sensor_cycle);
+        assert!(slowed.sample_rate() < dropping.sample_rate() / 2.0, "{:.0}/s vs {:.0}/s", slowed.sample_rate(), dropping.sample_rate());
+    }
+
+    #[test]
+    fn filter_window_stays_within_its_preallocated_capacity() {
+        let mut sensor = sensor(SensorType::Force);
+        let capacity = sensor.history_buffer.capacity();
+        assert!(capacity >= FILTER_WINDOW);
+        for id in 0..1000 {
+            filtered(&mut sensor, id, 30.0 + (id % 7) as f64);
+            assert!(sensor.history_buffer.len() <= FILTER_WINDOW);
+        }
+        assert_eq!(sensor.history_buffer.len(), FILTER_WINDOW);
+        assert_eq!(sensor.history_buffer.capacity(), capacity); // Never reallocated
+    }
+
+    #[test]
+    fn returning_feedback_records_the_round_trip_from_capture() {
+        let log = quiet_log();
+        let sensor = Sensor::new(SensorType::Force, log.clone())
+            .with_deadlines(Deadlines { processing: Duration::from_secs(1), ..Deadlines::default() })
+            .with_fault_rates(FaultRates::none());
+        let (tx, rx) = channel::unbounded();
+        let (feedback_tx, feedback_rx) = channel::unbounded();
+        let running = thread::spawn(move || { let _open = rx; sensor.run(tx, feedback_rx) });
+
+        let feedback = |origin| Feedback { is_ack: true, error_msg: "no".to_string(), recalibrate_offset: 0.0, timestamp: Instant::now(), origin };
+        feedback_tx.send(feedback(Some((1, Instant::now() - Duration::from_millis(20))))).unwrap();
+        feedback_tx.send(feedback(None)).unwrap(); // Answers no sample, so it has no round trip
+        thread::sleep(Duration::from_millis(50));
+        log.lock().unwrap().request_shutdown(ShutdownReason::DurationElapsed);
+        let stats = running.join().unwrap();
+
+        assert_eq!(stats.roundtrip_count, 1);
+        let round_trip = stats.avg_roundtrip();
+        assert!(round_trip >= Duration::from_millis(20) && round_trip < Duration::from_millis(500), "{:?}", round_trip);
+        assert_eq!(stats.max_roundtrip, round_trip);
+    }
+}
@@ -4,12 +4,13 @@ use tokio::sync::{Mutex, mpsc, broadcast};
 use rand::Rng;
 use std::sync::Arc;
 use std::time::Instant;
-use crate::share::{Feedback, SensorData, SensorType, SystemLog};
+use crate::share::{BenchmarkStats, Feedback, LogLevel, SensorData, SensorType, SystemLog};
 
 struct Sensor {
     id_counter:i32,
     history_buffer:VecDeque<f64>,
     sensor_type: SensorType,
+    benchmark_stats: BenchmarkStats,
 }
 
 impl Sensor {
@@ -18,6 +19,7 @@ impl Sensor {
             id_counter: 0,
             history_buffer: VecDeque::new(),
             sensor_type,
+            benchmark_stats: BenchmarkStats::new(),
         }
     }
 
@@ -26,7 +28,7 @@ impl Sensor {
         let mut random = rand::rng();
 
         self.id_counter += 1;
-        let mut value = match self.sensor_type {
+        let value = match self.sensor_type {
             SensorType::Force => random.random_range(10.0..55.0),
             SensorType::Position => random.random_range(-0.1..0.2),
             SensorType::Temperature => random.random_range(20.0..130.0)
@@ -38,7 +40,9 @@ impl Sensor {
             value,
             anomaly: false,
             timestamp:Instant::now(),
-
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: Default::default(),
         }
     }
 
@@ -78,16 +82,25 @@ impl Sensor {
     }
 
     // FUNCTION 3: Transmit Data
-    async fn transmit_data(&self, tx: mpsc::Sender<SensorData>, data: SensorData) {
+    // A send that misses the deadline is abandoned and counted, never a panic
+    async fn transmit_data(&mut self, tx: mpsc::Sender<SensorData>, data: SensorData, log: &Arc<Mutex<SystemLog>>) {
 
         // Deadline for transmit data
         let deadline_transmit = Duration::from_micros(100);
-
-        let result = timeout(deadline_transmit, tx.send(data)).await.unwrap();
-
-        match result {
-            Ok(_) => println!("transmit data within the time!"),
-            Err(_) => println!("OUT OF TIME: TASK ABANDONED")
+        let id = data.id;
+
+        match timeout(deadline_transmit, tx.send(data)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => {
+                log.lock().await.write_level(LogLevel::Warn, format!("[Sensor {:?}] Receiver disconnected, sample {} lost", self.sensor_type, id));
+            }
+            Err(_elapsed) => {
+                self.benchmark_stats.sensor_missed_deadlines += 1;
+                *self.benchmark_stats.transmission_misses.get_mut(self.sensor_type) += 1;
+                log.lock().await.write_level(LogLevel::Warn, format!(
+                    "[DEADLINE] Sensor {:?} (ID: {}) not sent within {:?}, sample abandoned", self.sensor_type, id, deadline_transmit
+                ));
+            }
         }
     }
     
@@ -96,7 +109,7 @@ impl Sensor {
 pub async fn run_sensor(
     sensor_type: SensorType,
     tx: mpsc::Sender<SensorData>,
-    mut rx_feedback: broadcast::Receiver<Feedback>,
+    _rx_feedback: broadcast::Receiver<Feedback>,
     log: Arc<Mutex<SystemLog>>)
     {
         let mut sensor = Sensor::new(sensor_type);
@@ -120,7 +133,7 @@ pub async fn run_sensor(
                             }
 
                             // 4. Transmit data
-                            sensor.transmit_data(tx.clone(), processed_data).await;
+                            sensor.transmit_data(tx.clone(), processed_data, &log).await;
                         }
                     }
                 }
@@ -128,4 +141,26 @@ pub async fn run_sensor(
 
         // 5. Handle feedback
 
-    }
\ No newline at end of file
+    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stalled_receiver_counts_a_miss_instead_of_panicking() {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(crate::share::Verbosity::Silent);
+        let log = Arc::new(Mutex::new(log));
+        let mut sensor = Sensor::new(SensorType::Force);
+        let (tx, _stalled) = mpsc::channel(1); // Never drained
+
+        let first = sensor.generate_data();
+        sensor.transmit_data(tx.clone(), first, &log).await; // Takes the only slot
+        let second = sensor.generate_data();
+        sensor.transmit_data(tx, second, &log).await;
+
+        assert_eq!(sensor.benchmark_stats.sensor_missed_deadlines, 1);
+        assert_eq!(sensor.benchmark_stats.transmission_misses.get(SensorType::Force), 1);
+        assert!(log.lock().await.recent_entries().iter().any(|l| l.contains("(ID: 2) not sent within")));
+    }
+}
@@ -1,12 +1,134 @@
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
 use std::fs::{File, OpenOptions};
-use std::io::Write;
-use std::sync::Mutex;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use rand::Rng;
 
 // --------------- SENSOR MODULE -------------------
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SensorType { Force, Position, Temperature }
 
+impl SensorType {
+    pub fn all() -> &'static [SensorType] {
+        &[SensorType::Force, SensorType::Position, SensorType::Temperature]
+    }
+}
+
+// Built-in defaults of one sensor type. The sensors, the commanders and
+// `Deadlines::default` read their per-type constants from here.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorProfile {
+    pub range: (f64, f64),          // Generated readings, min inclusive, max exclusive
+    pub anomaly_bounds: (f64, f64), // Readings outside [min, max] are out of range
+    pub setpoint: f64,
+    pub gains: (f64, f64, f64),     // (kp, ki, kd)
+    pub transmission_deadline: Duration,
+}
+
+impl SensorProfile {
+    pub fn out_of_range(&self, value: f64) -> bool {
+        value < self.anomaly_bounds.0 || value > self.anomaly_bounds.1
+    }
+}
+
+// Readings the sensors' moving-average filter averages over
+pub const FILTER_WINDOW: usize = 5;
+
+pub fn default_profile(sensor_type: SensorType) -> SensorProfile {
+    match sensor_type {
+        SensorType::Force => SensorProfile {
+            range: (10.0, 55.0),
+            anomaly_bounds: (5.0, 60.0),
+            setpoint: 30.0,
+            gains: (1.5, 0.1, 0.05),
+            transmission_deadline: Duration::from_micros(100),
+        },
+        SensorType::Position => SensorProfile {
+            range: (-0.1, 0.2),
+            anomaly_bounds: (-0.5, 0.5),
+            setpoint: 0.0,
+            gains: (0.8, 0.2, 0.1),
+            transmission_deadline: Duration::from_micros(100),
+        },
+        SensorType::Temperature => SensorProfile {
+            range: (20.0, 130.0),
+            anomaly_bounds: (f64::NEG_INFINITY, 120.0), // No lower limit
+            setpoint: 240.0,
+            gains: (0.5, 0.05, 0.01),
+            transmission_deadline: Duration::from_micros(100),
+        },
+    }
+}
+
+// Stable identity of a sensor or actuator, printed as "Motor-0"
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ComponentId {
+    pub sensor_type: SensorType,
+    pub index: usize, // Distinguishes several instances sharing a name
+    pub name: String,
+}
+
+impl fmt::Display for ComponentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.name, self.index)
+    }
+}
+
+// Central mapping of sensor types to the components that produce and consume them
+#[derive(Debug, Clone, Default)]
+pub struct ComponentRegistry {
+    sensors: Vec<ComponentId>,
+    actuators: Vec<ComponentId>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn register_sensor(&mut self, sensor_type: SensorType, name: &str) -> ComponentId {
+        let id = Self::next_id(&self.sensors, sensor_type, name);
+        self.sensors.push(id.clone());
+        id
+    }
+
+    pub fn register_actuator(&mut self, sensor_type: SensorType, name: &str) -> ComponentId {
+        let id = Self::next_id(&self.actuators, sensor_type, name);
+        self.actuators.push(id.clone());
+        id
+    }
+
+    pub fn sensors(&self) -> &[ComponentId] { &self.sensors }
+    pub fn actuators(&self) -> &[ComponentId] { &self.actuators }
+
+    pub fn sensor_for(&self, sensor_type: SensorType) -> Option<&ComponentId> {
+        self.sensors.iter().find(|id| id.sensor_type == sensor_type)
+    }
+
+    pub fn actuators_for(&self, sensor_type: SensorType) -> Vec<&ComponentId> {
+        self.actuators.iter().filter(|id| id.sensor_type == sensor_type).collect()
+    }
+
+    // Human label for log lines, falling back to the bare type when unregistered
+    pub fn sensor_label(&self, sensor_type: SensorType) -> String {
+        match self.sensor_for(sensor_type) {
+            Some(id) => id.to_string(),
+            None => format!("{:?}", sensor_type),
+        }
+    }
+
+    fn next_id(existing: &[ComponentId], sensor_type: SensorType, name: &str) -> ComponentId {
+        let index = existing.iter().filter(|id| id.name == name).count();
+        ComponentId { sensor_type, index, name: name.to_string() }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SystemMode {
     Normal,
@@ -14,125 +136,2349 @@ pub enum SystemMode {
     EmergencyStop, // Halt and hold safe position
 }
 
-#[derive(Debug, Clone)]
-pub struct SensorData {
-    pub id: i32,
-    pub sensor_type: SensorType,
-    pub value: f64,
-    pub anomaly: bool,
-    pub timestamp: Instant,
-    pub processed_timestamp: Option<Instant>,
+// Why the simulation (or a single thread of it) stopped
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShutdownReason {
+    DurationElapsed,        // Clean run: the configured duration passed
+    SampleLimitReached,     // Clean run: the sensors generated `StopCondition::Samples` samples
+    ChannelDisconnected,    // A peer hung up before shutdown was signalled
+    EmergencyStop,          // E-STOP latched by the commander
+    ThreadPanicked,         // At least one thread failed to join
+    SelfTestFailed(String), // Wiring check failed, nothing was started
+    RuntimeUnavailable(String), // The tokio runtime could not be built, nothing was started
+    InvalidConfig(String),  // Rejected by `SimulationConfig::validate`, nothing was started
+    InvariantViolation(String), // A runtime invariant failed, see `InvariantChecker`
+    WatchdogTimeout(Vec<String>), // These threads were still running `shutdown_grace` after the stop
+}
+
+impl ShutdownReason {
+    // Keeps only the reason `validate` gave, without the "invalid config" prefix
+    pub fn invalid_config(error: ConfigError) -> Self {
+        match error {
+            ConfigError::Invalid(msg) => ShutdownReason::InvalidConfig(msg),
+            other => ShutdownReason::InvalidConfig(other.to_string()),
+        }
+    }
+}
+
+// Numeric type of readings and efforts. The pipeline runs on f64; f32 halves the
+// memory and matches the width most ADCs deliver.
+pub trait Sample: Copy + Default + PartialOrd + fmt::Debug + fmt::Display + Send + Sync + 'static
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self> + AddAssign {
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn is_finite(self) -> bool;
+    fn abs(self) -> Self;
+}
+
+impl Sample for f64 {
+    fn from_f64(value: f64) -> Self { value }
+    fn to_f64(self) -> f64 { self }
+    fn is_finite(self) -> bool { f64::is_finite(self) }
+    fn abs(self) -> Self { f64::abs(self) }
+}
+
+impl Sample for f32 {
+    fn from_f64(value: f64) -> Self { value as f32 }
+    fn to_f64(self) -> f64 { self as f64 }
+    fn is_finite(self) -> bool { f32::is_finite(self) }
+    fn abs(self) -> Self { f32::abs(self) }
+}
+
+// Mean of a moving-average window, zero while it is empty
+pub fn moving_average<T: Sample>(window: &VecDeque<T>) -> T {
+    if window.is_empty() { return T::default(); }
+    let total = window.iter().fold(T::default(), |sum, v| sum + *v);
+    total / T::from_f64(window.len() as f64)
+}
+
+#[derive(Debug, Clone)]
+pub struct SensorData<T = f64> {
+    pub id: i32,
+    pub sensor_type: SensorType,
+    pub value: T,
+    pub anomaly: bool,
+    pub timestamp: Instant,
+    pub processed_timestamp: Option<Instant>,
+    pub capture_time: Option<Instant>, // When the hardware took the reading, if the source reports it
+    pub stamps: StageStamps,
+}
+
+// Why `SensorData::validate` rejected a sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataError {
+    NonFinite(f64),          // NaN or infinite value
+    NegativeId(i32),
+    FromTheFuture(Duration), // Captured this far after the check
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::NonFinite(value) => write!(f, "value is not finite ({})", value),
+            DataError::NegativeId(id) => write!(f, "negative id {}", id),
+            DataError::FromTheFuture(ahead) => write!(f, "timestamp {:?} in the future", ahead),
+        }
+    }
+}
+
+// When a sample passed the stages `processed_timestamp` does not cover, for `CycleTimeline`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageStamps {
+    pub generated: Option<Instant>, // Reading generated, before processing
+    pub sent: Option<Instant>,      // Batch handed to the channel, None when sent on its own
+    pub received: Option<Instant>,  // Taken off the channel by the commander
+    pub commanded: Option<Instant>, // Command handed to the actuator channel
+}
+
+impl<T: Sample> SensorData<T> {
+    // Checks a sample must pass before it reaches a controller, against the caller's
+    // clock. The sensor type needs none: an unknown one never decodes into `SensorType`.
+    pub fn validate(&self, now: Instant) -> Result<(), DataError> {
+        if !self.value.is_finite() { return Err(DataError::NonFinite(self.value.to_f64())); }
+        if self.id < 0 { return Err(DataError::NegativeId(self.id)); }
+        let captured = self.captured_at();
+        if captured > now { return Err(DataError::FromTheFuture(captured - now)); }
+        Ok(())
+    }
+
+    // Capture time when known, otherwise when the sample was constructed
+    pub fn captured_at(&self) -> Instant {
+        self.capture_time.unwrap_or(self.timestamp)
+    }
+
+    // Time since the reading was captured; the end-to-end latency once it reaches an actuator
+    pub fn age(&self) -> Duration {
+        self.age_since(Instant::now())
+    }
+
+    // Same, against a clock reading taken by the caller; zero if `now` is before the capture
+    pub fn age_since(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.captured_at())
+    }
+
+    // Time since the sample was handed to the channel, which is when the sensor finished
+    // processing unless the sample waited in a batch. None if processing never finished.
+    pub fn transit_since(&self, now: Instant) -> Option<Duration> {
+        let sent = self.processed_timestamp.map(|processed| self.stamps.sent.unwrap_or(processed));
+        sent.map(|sent| now.saturating_duration_since(sent))
+    }
+}
+
+// Random transmission faults rolled by every sensor on every sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaultRates {
+    pub drop_rate: f64,    // Share of packets silently dropped
+    pub latency_rate: f64, // Share of packets delayed by `latency`
+    pub latency: Duration,
+}
+
+impl Default for FaultRates {
+    fn default() -> Self {
+        Self { drop_rate: 0.05, latency_rate: 0.05, latency: Duration::from_micros(30) }
+    }
+}
+
+impl FaultRates {
+    // Fault-free transmission, for runs that must meet every deadline
+    pub fn none() -> Self {
+        Self { drop_rate: 0.0, latency_rate: 0.0, ..Self::default() }
+    }
+
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        Self { latency: self.latency.div_f64(time_scale), ..*self }
+    }
+}
+
+// Transmission fault scheduled for one sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransmissionFault {
+    Drop,
+    Delay(Duration),
+}
+
+// Transmission faults for specific samples, addressed by sensor and ID, e.g.
+//
+// FaultSchedule::new()
+//     .drop(SensorType::Force, 42)
+//     .delay(SensorType::Position, 100, Duration::from_millis(2))
+//
+// A sensor with anything scheduled stops rolling its random `FaultRates`, so the
+// run fails exactly where it was told to and nowhere else.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultSchedule {
+    faults: HashMap<(SensorType, i32), TransmissionFault>,
+}
+
+impl FaultSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn drop(self, sensor_type: SensorType, id: i32) -> Self {
+        self.with(sensor_type, id, TransmissionFault::Drop)
+    }
+
+    pub fn delay(self, sensor_type: SensorType, id: i32, delay: Duration) -> Self {
+        self.with(sensor_type, id, TransmissionFault::Delay(delay))
+    }
+
+    // Replaces whatever was scheduled for that sample
+    pub fn with(mut self, sensor_type: SensorType, id: i32, fault: TransmissionFault) -> Self {
+        self.faults.insert((sensor_type, id), fault);
+        self
+    }
+
+    pub fn fault_for(&self, sensor_type: SensorType, id: i32) -> Option<TransmissionFault> {
+        self.faults.get(&(sensor_type, id)).copied()
+    }
+
+    // Whether the sensor's random rolls are off
+    pub fn covers(&self, sensor_type: SensorType) -> bool {
+        self.faults.keys().any(|(s, _)| *s == sensor_type)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faults.is_empty()
+    }
+
+    // Every scheduled fault, in no particular order
+    pub fn faults(&self) -> impl Iterator<Item = (SensorType, i32, TransmissionFault)> + '_ {
+        self.faults.iter().map(|((s, id), fault)| (*s, *id, *fault))
+    }
+
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        let faults = self.faults.iter().map(|(key, fault)| {
+            let fault = match fault {
+                TransmissionFault::Delay(delay) => TransmissionFault::Delay(delay.div_f64(time_scale)),
+                TransmissionFault::Drop => TransmissionFault::Drop,
+            };
+            (*key, fault)
+        }).collect();
+        Self { faults }
+    }
+}
+
+// Sample faster while readings are anomalous or changing quickly: each such
+// sample halves the cycle (down to `floor`), each calm one moves it halfway back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    pub floor: Duration,                  // Shortest cycle allowed
+    pub delta_threshold: PerSensor<f64>, // Change between filtered samples that counts as rapid
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            floor: Duration::from_millis(1),
+            delta_threshold: PerSensor { force: 10.0, position: 0.07, temperature: 25.0 },
+        }
+    }
+}
+
+impl AdaptiveSampling {
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        Self { floor: self.floor.div_f64(time_scale), ..*self }
+    }
+}
+
+// How a sensor applies the recalibration offsets it gets as feedback
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FeedbackMode {
+    #[default]
+    Immediate,                    // Every offset as soon as it arrives
+    Batched { window: Duration }, // The mean of the offsets received in each window, once per window
+}
+
+impl FeedbackMode {
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        match *self {
+            FeedbackMode::Batched { window } => FeedbackMode::Batched { window: window.div_f64(time_scale) },
+            FeedbackMode::Immediate => FeedbackMode::Immediate,
+        }
+    }
+}
+
+// Sensor pipeline stages that can be switched off for ablation runs. Without the
+// filter the raw reading is forwarded; without detection nothing is flagged out of range
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageFlags {
+    pub filter: bool,
+    pub anomaly: bool,
+}
+
+impl Default for StageFlags {
+    fn default() -> Self { Self { filter: true, anomaly: true } }
+}
+
+// Escalates to Degraded when more than `max_rate` of one sensor's samples in the
+// last `window` were anomalous, catching intermittent faults that never build a streak
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyRateGate {
+    pub window: Duration,
+    pub max_rate: f64,
+    pub min_samples: usize, // Below this many samples in the window the gate stays open
+}
+
+impl Default for AnomalyRateGate {
+    fn default() -> Self {
+        Self { window: Duration::from_millis(100), max_rate: 0.3, min_samples: 10 }
+    }
+}
+
+impl AnomalyRateGate {
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        Self { window: self.window.div_f64(time_scale), ..*self }
+    }
+}
+
+// Deterministic fault forced onto the next N samples of one sensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    Drop(u32),            // Never transmitted
+    Delay(Duration, u32), // Transmitted after an extra delay
+    Stuck(u32),           // Repeats the last generated value
+    Anomaly(u32),         // Flagged as anomalous
+    Pause(u32),           // Not sampled at all, the cycle still elapses
+}
+
+impl Fault {
+    pub fn samples(&self) -> u32 {
+        match *self {
+            Fault::Drop(n) | Fault::Delay(_, n) | Fault::Stuck(n) | Fault::Anomaly(n) | Fault::Pause(n) => n,
+        }
+    }
+}
+
+// A fault that hits several sensors in the same cycle, e.g. a power glitch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorrelatedFault {
+    pub probability: f64, // Per cycle
+    pub sensors: SensorSet,
+    pub fault: Fault,
+}
+
+// Rolls once per cycle for all sensors, so every sensor in the set sees the
+// same outcome instead of an independent roll
+#[derive(Clone)]
+pub struct FaultController {
+    config: CorrelatedFault,
+    decisions: Arc<Mutex<VecDeque<(i32, bool)>>>, // Outcome of the last few cycles
+}
+
+impl FaultController {
+    pub fn new(config: CorrelatedFault) -> Self {
+        Self { config, decisions: Arc::new(Mutex::new(VecDeque::new())) }
+    }
+
+    // Sensors share the 5ms grid, so their sample ids identify the same cycle
+    pub fn fault_for(&self, sensor_type: SensorType, cycle: i32) -> Option<Fault> {
+        if !self.config.sensors.contains(sensor_type) { return None; }
+        let mut decisions = self.decisions.lock().ok()?;
+
+        let hit = match decisions.iter().find(|(c, _)| *c == cycle) {
+            Some(&(_, hit)) => hit,
+            None => {
+                let hit = rand::rng().random_bool(self.config.probability.clamp(0.0, 1.0));
+                if decisions.len() >= 8 { decisions.pop_front(); }
+                decisions.push_back((cycle, hit));
+                hit
+            }
+        };
+        hit.then_some(self.config.fault)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SensorFeedback {
+    Recalibrate { offset: f64 }, // Instruct sensor to shift values
+    Maintain,                    // Current calibration is good
+}
+
+// Inefficient Struct Approach
+pub struct Feedback {
+    pub is_ack: bool,
+    pub error_msg: String,
+    pub recalibrate_offset: f64,
+    pub timestamp: Instant,
+    pub origin: Option<(i32, Instant)>, // ID and capture time of the sample it answers
+}
+
+impl Feedback {
+    // Capture of the originating sample to `now`: the whole sensor -> commander ->
+    // actuator -> sensor loop. None for feedback that answers no particular sample.
+    pub fn round_trip(&self, now: Instant) -> Option<Duration> {
+        self.origin.map(|(_, captured)| now.saturating_duration_since(captured))
+    }
+
+    // What the sensor is asked to do; a zero offset confirms the calibration
+    pub fn kind(&self) -> SensorFeedback {
+        if self.recalibrate_offset != 0.0 {
+            SensorFeedback::Recalibrate { offset: self.recalibrate_offset }
+        } else {
+            SensorFeedback::Maintain
+        }
+    }
+}
+
+// --------------- ACTUATOR COMMANDER MODULE -------------------
+
+// Pipeline stage that owns a deadline check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Processing,   // Sensor filtering + anomaly detection
+    Transmission, // Sensor -> Commander
+    Actuation,    // Actuator operation
+    Feedback,     // Actuator -> Sensor
+}
+
+pub type DeadlineCallback = Box<dyn Fn(Stage, SensorType, Duration) + Send + Sync>;
+type SharedDeadlineCallback = Arc<dyn Fn(Stage, SensorType, Duration) + Send + Sync>; // As stored, so `notify` can copy the list
+
+// Callbacks fired synchronously on every deadline miss (stage, sensor, overshoot).
+// Shared between the commander and the components it drives; callers must invoke
+// `notify` after releasing the log mutex so a callback can never deadlock on it.
+#[derive(Clone, Default)]
+pub struct DeadlineHooks {
+    callbacks: Arc<Mutex<Vec<SharedDeadlineCallback>>>,
+}
+
+impl DeadlineHooks {
+    pub fn register(&self, callback: DeadlineCallback) {
+        if let Ok(mut callbacks) = self.callbacks.lock() {
+            callbacks.push(Arc::from(callback));
+        }
+    }
+
+    // Runs the callbacks on a copy of the list, with no lock held, so a callback
+    // may register another hook or trigger a nested `notify`
+    pub fn notify(&self, stage: Stage, sensor_type: SensorType, overshoot: Duration) {
+        let callbacks = match self.callbacks.lock() {
+            Ok(callbacks) => callbacks.clone(),
+            Err(_) => return,
+        };
+        for callback in &callbacks {
+            callback(stage, sensor_type, overshoot);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ActuatorStatus {
+    ActionComplete { sensor_type: SensorType, effort: f64, saturated: bool }, // `effort` as applied, after clamping
+    HardwareFailure { sensor_type: SensorType, msg: String },
+    Recovered { sensor_type: SensorType }, // Back from a `HardwareFailure`, taking commands again
+}
+
+impl ActuatorStatus {
+    pub fn sensor_type(&self) -> SensorType {
+        match self {
+            ActuatorStatus::ActionComplete { sensor_type, .. }
+            | ActuatorStatus::HardwareFailure { sensor_type, .. }
+            | ActuatorStatus::Recovered { sensor_type } => *sensor_type,
+        }
+    }
+}
+
+// Simulated hardware outage: the actuator fails `fail_at` after start-up and
+// comes back `recover_after` later. Commands arriving in between are not applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FailureSchedule {
+    pub fail_at: Duration,
+    pub recover_after: Duration,
+}
+
+impl FailureSchedule {
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        Self { fail_at: self.fail_at.div_f64(time_scale), recover_after: self.recover_after.div_f64(time_scale) }
+    }
+
+    // Whether the actuator is down `elapsed` after start-up
+    pub fn is_failed(&self, elapsed: Duration) -> bool {
+        elapsed >= self.fail_at && elapsed < self.fail_at + self.recover_after
+    }
+}
+
+// How long the threaded commander keeps trying a failed actuator: it sends it
+// commands again `backoff` after a failure, doubling for every failure in a row,
+// and gives up for good after `max_retries` retries, keeping the system in Degraded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActuatorRetry {
+    pub max_retries: u32,
+    pub backoff: Duration,
+}
+
+impl ActuatorRetry {
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        Self { backoff: self.backoff.div_f64(time_scale), ..*self }
+    }
+
+    // Wait before the retry after this many failures in a row
+    pub fn backoff_for(&self, failures: u32) -> Duration {
+        self.backoff.saturating_mul(1 << failures.saturating_sub(1).min(16))
+    }
+}
+
+
+// --------------- PLANT MODEL -------------------
+// First-order process behind one actuator/sensor pair, closing the loop instead of
+// generating readings at random. The state relaxes towards
+// `initial + gain * (effort + disturbance)` with the given time constant, where the
+// effort is the last command applied and the disturbance follows the schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlantModel {
+    pub gain: f64,
+    pub time_constant: Duration,
+    pub initial: f64, // State at start-up, and where it settles without effort
+    pub noise: f64,   // Readings are off by up to this much either way
+    pub disturbances: Vec<(Duration, f64)>, // (time since start, disturbance) steps; zero before the first
+}
+
+impl Default for PlantModel {
+    fn default() -> Self {
+        Self { gain: 1.0, time_constant: Duration::from_millis(200), initial: 0.0, noise: 0.0, disturbances: Vec::new() }
+    }
+}
+
+impl PlantModel {
+    // Step the disturbance to `value` at `time`, replacing any step at the same time
+    pub fn with_disturbance(mut self, time: Duration, value: f64) -> Self {
+        self.disturbances.retain(|(t, _)| *t != time);
+        self.disturbances.push((time, value));
+        self.disturbances.sort_by_key(|(t, _)| *t);
+        self
+    }
+
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        Self {
+            time_constant: self.time_constant.div_f64(time_scale),
+            disturbances: self.disturbances.iter().map(|(t, d)| (t.div_f64(time_scale), *d)).collect(),
+            ..self.clone()
+        }
+    }
+
+    // Disturbance in force `elapsed` after start-up
+    pub fn disturbance_at(&self, elapsed: Duration) -> f64 {
+        self.disturbances.iter().take_while(|(t, _)| *t <= elapsed).last().map_or(0.0, |(_, d)| *d)
+    }
+}
+
+struct PlantState {
+    value: f64,
+    effort: f64, // Held until the next command
+    updated: Instant,
+}
+
+// Shared handle on a running plant: the actuator applies efforts, the sensor reads it
+#[derive(Clone)]
+pub struct Plant {
+    model: Arc<PlantModel>,
+    started: Instant,
+    state: Arc<Mutex<PlantState>>,
+}
+
+impl Plant {
+    pub fn new(model: PlantModel) -> Self {
+        let started = Instant::now();
+        let state = PlantState { value: model.initial, effort: 0.0, updated: started };
+        Self { model: Arc::new(model), started, state: Arc::new(Mutex::new(state)) }
+    }
+
+    pub fn model(&self) -> &PlantModel {
+        &self.model
+    }
+
+    // Exact first-order response to the held effort since the last update, so the
+    // state stays stable however irregular the updates are. The interval is split
+    // at every disturbance step it crosses.
+    fn advance(&self, state: &mut PlantState, now: Instant) {
+        let since = state.updated.saturating_duration_since(self.started);
+        let to = now.saturating_duration_since(self.started);
+        state.updated = state.updated.max(now);
+        let mut from = since;
+        for (step, _) in self.model.disturbances.iter().filter(|(t, _)| *t > since && *t < to) {
+            self.relax(state, from, *step);
+            from = *step;
+        }
+        self.relax(state, from, to);
+    }
+
+    fn relax(&self, state: &mut PlantState, from: Duration, to: Duration) {
+        let Some(dt) = to.checked_sub(from) else { return };
+        let target = self.model.initial + self.model.gain * (state.effort + self.model.disturbance_at(from));
+        let tau = self.model.time_constant.as_secs_f64();
+        let decay = if tau > 0.0 { (-dt.as_secs_f64() / tau).exp() } else { 0.0 };
+        state.value = target + (state.value - target) * decay;
+    }
+
+    // Hold `effort` from now on
+    pub fn apply(&self, effort: f64) {
+        if !effort.is_finite() { return; }
+        if let Ok(mut state) = self.state.lock() {
+            self.advance(&mut state, Instant::now());
+            state.effort = effort;
+        }
+    }
+
+    // Current state, without the measurement noise
+    pub fn read(&self) -> f64 {
+        let Ok(mut state) = self.state.lock() else { return self.model.initial };
+        self.advance(&mut state, Instant::now());
+        state.value
+    }
+
+    // Noisy reading, as a sensor sees it
+    pub fn measure(&self) -> f64 {
+        let noise = self.model.noise.abs();
+        let value = self.read();
+        if noise > 0.0 { value + rand::rng().random_range(-noise..noise) } else { value }
+    }
+}
+
+
+// --------------- PID CONTROLLER -------------------
+#[derive(Clone)]
+pub struct PidController<T = f64> {
+    pub kp: T, pub ki: T, pub kd: T,
+    pub integral: T, pub prev_error: T,
+    pub hold_integral: bool, // Anti-windup: stop integrating while the actuator is saturated
+    pub divergence_limit: T, // Largest output magnitude `check` accepts
+}
+
+// Efforts stay within a few hundred for any sane gains
+pub const DEFAULT_DIVERGENCE_LIMIT: f64 = 1e6;
+
+// Why `PidController::check` rejected an output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PidError {
+    NonFinite(f64),                       // NaN or infinite output
+    Diverged { output: f64, limit: f64 }, // Magnitude above the divergence limit
+}
+
+impl fmt::Display for PidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PidError::NonFinite(output) => write!(f, "output is not finite ({})", output),
+            PidError::Diverged { output, limit } => write!(f, "output {:.3e} exceeds the divergence limit {:.3e}", output, limit),
+        }
+    }
+}
+
+impl std::error::Error for PidError {}
+
+impl<T: Sample> PidController<T> {
+    pub fn new(kp: T, ki: T, kd: T) -> Self {
+        Self { kp, ki, kd, integral: T::default(), prev_error: T::default(), hold_integral: false, divergence_limit: T::from_f64(DEFAULT_DIVERGENCE_LIMIT) }
+    }
+    // Fresh controller at half the gains, the default Degraded profile
+    pub fn halved(&self) -> Self {
+        let half = T::from_f64(0.5);
+        Self { divergence_limit: self.divergence_limit, ..Self::new(self.kp * half, self.ki * half, self.kd * half) }
+    }
+    pub fn with_divergence_limit(mut self, limit: T) -> Self {
+        self.divergence_limit = limit;
+        self
+    }
+    pub fn compute(&mut self, target: T, current: T, dt: T, scale: T) -> T {
+        self.compute_detailed(target, current, dt, scale).output
+    }
+
+    // Same as `compute`, but a diverged output is an error instead of an effort
+    pub fn compute_checked(&mut self, target: T, current: T, dt: T, scale: T) -> Result<T, PidError> {
+        let output = self.compute(target, current, dt, scale);
+        self.check(output)
+    }
+
+    // Misconfigured gains show up as an output that is non-finite or above the limit
+    pub fn check(&self, output: T) -> Result<T, PidError> {
+        if !output.is_finite() {
+            Err(PidError::NonFinite(output.to_f64()))
+        } else if output.abs() > self.divergence_limit {
+            Err(PidError::Diverged { output: output.to_f64(), limit: self.divergence_limit.to_f64() })
+        } else {
+            Ok(output)
+        }
+    }
+
+    // Same as `compute`, but also returns the individual P, I and D contributions
+    pub fn compute_detailed(&mut self, target: T, current: T, dt: T, scale: T) -> PidTerms<T> {
+        // A single NaN/Inf would poison `integral` and `prev_error` for good, so
+        // reject the sample and command zero effort without touching the state.
+        if !target.is_finite() || !current.is_finite() || !dt.is_finite() || dt <= T::default() {
+            return PidTerms::default();
+        }
+        let error = target - current;
+        if !self.hold_integral {
+            self.integral += error * dt;
+        }
+        let derivative = (error - self.prev_error) / dt;
+        self.prev_error = error;
+
+        let p = self.kp * error;
+        let i = self.ki * self.integral;
+        let d = self.kd * derivative;
+        PidTerms { p, i, d, output: (p + i + d) * scale }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PidTerms<T = f64> {
+    pub p: T,
+    pub i: T,
+    pub d: T,
+    pub output: T, // (p + i + d) * scale
+}
+
+// --------------- SETPOINTS -------------------
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Step,   // Hold each breakpoint's value until the next one
+    Linear, // Ramp between breakpoints
+}
+
+// Setpoint as a function of simulated time since start-up. Before the first
+// breakpoint its value applies, after the last one the last value holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetpointSchedule {
+    breakpoints: Vec<(Duration, f64)>, // Sorted by time
+    interpolation: Interpolation,
+}
+
+impl SetpointSchedule {
+    pub fn constant(value: f64) -> Self {
+        Self { breakpoints: vec![(Duration::ZERO, value)], interpolation: Interpolation::Step }
+    }
+
+    // e.g. step Force from 30 to 45 at t=1s: new(vec![(0s, 30.0), (1s, 45.0)], Step)
+    pub fn new(mut breakpoints: Vec<(Duration, f64)>, interpolation: Interpolation) -> Self {
+        breakpoints.sort_by_key(|(t, _)| *t);
+        if breakpoints.is_empty() {
+            breakpoints.push((Duration::ZERO, 0.0));
+        }
+        Self { breakpoints, interpolation }
+    }
+
+    pub fn breakpoints(&self) -> &[(Duration, f64)] {
+        &self.breakpoints
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn at(&self, elapsed: Duration) -> f64 {
+        let next = self.breakpoints.partition_point(|(t, _)| *t <= elapsed);
+        if next == 0 {
+            return self.breakpoints[0].1;
+        }
+        let (t0, v0) = self.breakpoints[next - 1];
+        match (self.interpolation, self.breakpoints.get(next)) {
+            (Interpolation::Linear, Some(&(t1, v1))) => {
+                let frac = (elapsed - t0).as_secs_f64() / (t1 - t0).as_secs_f64();
+                v0 + (v1 - v0) * frac
+            }
+            _ => v0,
+        }
+    }
+
+    // Breakpoints in wall-clock time for a run compressed by `time_scale`
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        Self {
+            breakpoints: self.breakpoints.iter().map(|(t, v)| (t.div_f64(time_scale), *v)).collect(),
+            interpolation: self.interpolation,
+        }
+    }
+}
+
+// Control quality of one setpoint step, measured on the readings that followed it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepResponse {
+    pub sensor_type: SensorType,
+    pub at: Duration, // Since start-up
+    pub from: f64,
+    pub to: f64,
+    pub overshoot: f64, // Peak past the target, in % of the step size
+    pub settling_time: Option<Duration>, // Until the readings stayed within the settling band; None if they never did
+    pub steady_state_error: f64, // Target minus the mean of the last readings
+}
+
+pub const DEFAULT_SETTLING_BAND: f64 = 0.02; // ±2% of the step size around the target
+const STEADY_STATE_SAMPLES: usize = 20;
+
+// Follows the readings after a step until the next one, see `StepResponse`
+#[derive(Debug, Clone)]
+pub struct StepTracker {
+    sensor_type: SensorType,
+    at: Duration,
+    from: f64,
+    to: f64,
+    peak: f64, // Furthest reading in the direction of the step
+    settled_since: Option<Duration>,
+    tail: VecDeque<f64>,
+    band: f64, // Settling band, as a share of the step size
+}
+
+impl StepTracker {
+    pub fn new(sensor_type: SensorType, at: Duration, from: f64, to: f64) -> Self {
+        Self { sensor_type, at, from, to, peak: from, settled_since: None, tail: VecDeque::new(), band: DEFAULT_SETTLING_BAND }
+    }
+
+    pub fn with_band(mut self, band: f64) -> Self {
+        self.band = band;
+        self
+    }
+
+    pub fn record(&mut self, elapsed: Duration, value: f64) {
+        let step = self.to - self.from;
+        if (value - self.peak) * step.signum() > 0.0 {
+            self.peak = value;
+        }
+        if (value - self.to).abs() <= self.band * step.abs() {
+            self.settled_since.get_or_insert(elapsed);
+        } else {
+            self.settled_since = None;
+        }
+        if self.tail.len() >= STEADY_STATE_SAMPLES {
+            self.tail.pop_front();
+        }
+        self.tail.push_back(value);
+    }
+
+    pub fn finish(&self) -> StepResponse {
+        let step = self.to - self.from;
+        let past_target = ((self.peak - self.to) * step.signum()).max(0.0);
+        let mean = if self.tail.is_empty() { self.from } else { self.tail.iter().sum::<f64>() / self.tail.len() as f64 };
+        StepResponse {
+            sensor_type: self.sensor_type,
+            at: self.at,
+            from: self.from,
+            to: self.to,
+            overshoot: past_target / step.abs() * 100.0,
+            settling_time: self.settled_since.map(|t| t.saturating_sub(self.at)),
+            steady_state_error: self.to - mean,
+        }
+    }
+}
+
+// --------------- EXPORT -------------------
+// Opens an export file, gzip-compressed when the path ends in `.gz`
+pub fn create_export(path: &Path) -> io::Result<Box<dyn Write + Send>> {
+    let file = File::create(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        #[cfg(feature = "gzip")]
+        return Ok(Box::new(flate2::write::GzEncoder::new(file, flate2::Compression::default())));
+        #[cfg(not(feature = "gzip"))]
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "gzip export requires the `gzip` feature"));
+    }
+    Ok(Box::new(file))
+}
+
+// --------------- PID TRACE -------------------
+// Per-cycle CSV of the controller state. Rows go through a BufWriter so the
+// commander never waits on the disk inside its transmission deadline.
+// The gzip stream is finished when the trace is dropped.
+pub struct PidTrace {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    start: Instant,
+}
+
+impl PidTrace {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(create_export(path)?);
+        writeln!(writer, "t_us,sensor_type,setpoint,measured,p,i,d,output")?;
+        Ok(Self { writer, start: Instant::now() })
+    }
+
+    pub fn record(&mut self, sensor_type: SensorType, setpoint: f64, measured: f64, terms: &PidTerms) {
+        let _ = writeln!(
+            self.writer,
+            "{},{:?},{},{},{},{},{},{}",
+            self.start.elapsed().as_micros(), sensor_type, setpoint, measured,
+            terms.p, terms.i, terms.d, terms.output
+        );
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// --------------- INFLUX -------------------
+// Metrics in InfluxDB line protocol, pushed over UDP. Callers only queue a line;
+// a background thread batches them into datagrams so the hot path never does a syscall.
+#[derive(Clone)]
+pub struct InfluxSink {
+    lines: crossbeam::channel::Sender<String>,
+}
+
+const INFLUX_QUEUE: usize = 4096;
+const INFLUX_DATAGRAM: usize = 1400; // Stay below a typical MTU
+
+impl InfluxSink {
+    // The sender thread stops once every clone of the sink is dropped
+    pub fn spawn(addr: SocketAddr, flush_interval: Duration) -> io::Result<Self> {
+        let socket = UdpSocket::bind(if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+        socket.connect(addr)?;
+        let (lines, rx) = crossbeam::channel::bounded::<String>(INFLUX_QUEUE);
+
+        thread::spawn(move || {
+            let mut batch = String::new();
+            let mut last_flush = Instant::now();
+            loop {
+                let open = match rx.recv_timeout(flush_interval) {
+                    Ok(line) => {
+                        if batch.len() + line.len() > INFLUX_DATAGRAM && !batch.is_empty() {
+                            let _ = socket.send(batch.as_bytes());
+                            batch.clear();
+                            last_flush = Instant::now();
+                        }
+                        batch.push_str(&line);
+                        true
+                    }
+                    Err(crossbeam::channel::RecvTimeoutError::Timeout) => true,
+                    Err(crossbeam::channel::RecvTimeoutError::Disconnected) => false,
+                };
+
+                if !batch.is_empty() && (!open || last_flush.elapsed() >= flush_interval) {
+                    let _ = socket.send(batch.as_bytes());
+                    batch.clear();
+                    last_flush = Instant::now();
+                }
+                if !open { break; }
+            }
+        });
+
+        Ok(Self { lines })
+    }
+
+    // `sensor_value,type=Force value=29.8 <ns>`
+    pub fn sensor_value(&self, sensor_type: SensorType, value: f64) {
+        self.push(format!("sensor_value,type={:?} value={}", sensor_type, value));
+    }
+
+    // `latency,stage=e2e value=<µs> <ns>`
+    pub fn latency(&self, stage: &str, latency: Duration) {
+        self.push(format!("latency,stage={} value={}", stage, latency.as_secs_f64() * 1e6));
+    }
+
+    fn push(&self, measurement: String) {
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+        // A full queue drops the point rather than stall the caller
+        let _ = self.lines.try_send(format!("{} {}\n", measurement, ts));
+    }
+}
+
+// --------------- MONITORING -------------------
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemSnapshot {
+    pub mode: SystemMode,
+    pub last_values: HashMap<SensorType, f64>,
+    pub consecutive_anomalies: u32,
+    pub samples_processed: u64,
+    pub windowed_throughput: f64, // Samples per second over the last `ThroughputWindow`
+}
+
+// Overall health in one call, see `SnapshotHandle::health`; the recent counts
+// cover the last second, like the windowed throughput
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    pub mode: SystemMode,
+    pub last_seen: HashMap<SensorType, Duration>, // Age of each sensor's latest sample; missing until its first
+    pub recent_anomalies: u32,
+    pub recent_deadline_misses: u32, // Any stage, any component
+}
+
+impl HealthReport {
+    pub fn missed_deadlines(&self) -> bool {
+        self.recent_deadline_misses > 0
+    }
+}
+
+// Contents of a sensor's moving-average window, oldest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterState {
+    pub buffer: Vec<f64>,
+    pub average: f64,
+}
+
+// One entry of the mode audit trail
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModeTransition {
+    pub from: SystemMode,
+    pub to: SystemMode,
+    pub at: Instant,
+    pub triggering_sensor: SensorType,
+    pub consecutive_anomalies: u32, // At the time of the transition
+}
+
+// Samples counted in ten 100ms buckets, i.e. throughput over the last second.
+// Reads low during the first second, before the ring has filled.
+pub struct ThroughputWindow {
+    buckets: [u32; THROUGHPUT_BUCKETS],
+    current: usize,
+    bucket_start: Instant,
+}
+
+const THROUGHPUT_BUCKETS: usize = 10;
+const THROUGHPUT_BUCKET: Duration = Duration::from_millis(100);
+
+impl ThroughputWindow {
+    pub fn new() -> Self {
+        Self { buckets: [0; THROUGHPUT_BUCKETS], current: 0, bucket_start: Instant::now() }
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        self.advance(now);
+        self.buckets[self.current] += 1;
+    }
+
+    // Rotate out the buckets that ended before `now`
+    pub fn advance(&mut self, now: Instant) {
+        let mut steps = 0;
+        while now.saturating_duration_since(self.bucket_start) >= THROUGHPUT_BUCKET && steps < THROUGHPUT_BUCKETS {
+            self.current = (self.current + 1) % THROUGHPUT_BUCKETS;
+            self.buckets[self.current] = 0;
+            self.bucket_start += THROUGHPUT_BUCKET;
+            steps += 1;
+        }
+        // Idle for longer than the whole window: every bucket is empty by now
+        if now.saturating_duration_since(self.bucket_start) >= THROUGHPUT_BUCKET {
+            self.bucket_start = now;
+        }
+    }
+
+    pub fn per_second(&self) -> f64 {
+        self.count() as f64 / (THROUGHPUT_BUCKET * THROUGHPUT_BUCKETS as u32).as_secs_f64()
+    }
+
+    // Events in the window as of the last `record` or `advance`
+    pub fn count(&self) -> u32 {
+        self.buckets.iter().sum()
+    }
+}
+
+impl Default for ThroughputWindow {
+    fn default() -> Self { Self::new() }
+}
+
+#[derive(Default)]
+struct MonitorState {
+    mode: AtomicU8,
+    consecutive_anomalies: AtomicU32,
+    samples_processed: AtomicU64,
+    windowed_throughput: AtomicU64, // f64 bits
+    last_values: Mutex<HashMap<SensorType, (f64, Instant)>>, // Value and arrival of the latest sample
+    recent_anomalies: Mutex<ThroughputWindow>,
+    recent_deadline_misses: Mutex<ThroughputWindow>,
+    transitions: Mutex<Vec<ModeTransition>>, // Rare, so a blocking lock is fine
+    filters: Mutex<HashMap<SensorType, FilterState>>,
+    step_responses: Mutex<Vec<StepResponse>>, // Added once per setpoint step
+}
+
+// Cloneable view of the commander's live state. The commander only does atomic
+// stores and a `try_lock` per sample, so a polling monitor never stalls it.
+#[derive(Clone, Default)]
+pub struct SnapshotHandle {
+    state: Arc<MonitorState>,
+}
+
+impl SnapshotHandle {
+    pub fn snapshot(&self) -> SystemSnapshot {
+        let last_values = self.state.last_values.lock()
+            .map(|v| v.iter().map(|(s_type, (value, _))| (*s_type, *value)).collect())
+            .unwrap_or_default();
+
+        SystemSnapshot {
+            mode: self.mode(),
+            last_values,
+            consecutive_anomalies: self.state.consecutive_anomalies.load(Ordering::Relaxed),
+            samples_processed: self.state.samples_processed.load(Ordering::Relaxed),
+            windowed_throughput: f64::from_bits(self.state.windowed_throughput.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn mode(&self) -> SystemMode {
+        match self.state.mode.load(Ordering::Relaxed) {
+            0 => SystemMode::Normal,
+            1 => SystemMode::Degraded,
+            _ => SystemMode::EmergencyStop,
+        }
+    }
+
+    pub fn health(&self) -> HealthReport {
+        let now = Instant::now();
+        let last_seen = self.state.last_values.lock()
+            .map(|v| v.iter().map(|(s_type, (_, at))| (*s_type, now.saturating_duration_since(*at))).collect())
+            .unwrap_or_default();
+        let recent = |window: &Mutex<ThroughputWindow>| window.lock().map(|mut w| { w.advance(now); w.count() }).unwrap_or(0);
+
+        HealthReport {
+            mode: self.mode(),
+            last_seen,
+            recent_anomalies: recent(&self.state.recent_anomalies),
+            recent_deadline_misses: recent(&self.state.recent_deadline_misses),
+        }
+    }
+
+    // Hooked onto the run's `DeadlineHooks`, so it is called from every component
+    pub fn record_deadline_miss(&self) {
+        if let Ok(mut misses) = self.state.recent_deadline_misses.lock() {
+            misses.record(Instant::now());
+        }
+    }
+
+    pub fn record_throughput(&self, per_second: f64) {
+        self.state.windowed_throughput.store(per_second.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn record_sample(&self, sensor_type: SensorType, value: f64, anomaly: bool, mode: SystemMode, consecutive_anomalies: u32) {
+        self.state.samples_processed.fetch_add(1, Ordering::Relaxed);
+        self.state.mode.store(mode as u8, Ordering::Relaxed);
+        self.state.consecutive_anomalies.store(consecutive_anomalies, Ordering::Relaxed);
+
+        // Skip the updates rather than wait while a monitor is reading
+        let now = Instant::now();
+        if let Ok(mut values) = self.state.last_values.try_lock() {
+            values.insert(sensor_type, (value, now));
+        }
+        if anomaly {
+            if let Ok(mut anomalies) = self.state.recent_anomalies.try_lock() {
+                anomalies.record(now);
+            }
+        }
+    }
+
+    pub fn record_transition(&self, transition: ModeTransition) {
+        self.state.mode.store(transition.to as u8, Ordering::Relaxed);
+        if let Ok(mut transitions) = self.state.transitions.lock() {
+            transitions.push(transition);
+        }
+    }
+
+    // Called by the sensors after each filter update; skipped while a monitor is reading
+    pub fn record_filter(&self, sensor_type: SensorType, buffer: &VecDeque<f64>, average: f64) {
+        if let Ok(mut filters) = self.state.filters.try_lock() {
+            filters.insert(sensor_type, FilterState { buffer: buffer.iter().copied().collect(), average });
+        }
+    }
+
+    pub fn filter_state(&self, sensor_type: SensorType) -> Option<FilterState> {
+        self.state.filters.lock().ok()?.get(&sensor_type).cloned()
+    }
+
+    // Every mode change so far, oldest first
+    pub fn transitions(&self) -> Vec<ModeTransition> {
+        self.state.transitions.lock().map(|t| t.clone()).unwrap_or_default()
+    }
+
+    pub fn record_step_response(&self, response: StepResponse) {
+        if let Ok(mut responses) = self.state.step_responses.lock() {
+            responses.push(response);
+        }
+    }
+
+    // Every finished setpoint step so far, oldest first; a step still running is not included
+    pub fn step_responses(&self) -> Vec<StepResponse> {
+        self.state.step_responses.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+}
+
+// --------------- DEAD LETTERS -------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    NonFinite,            // Value was NaN or infinite
+    ProcessingDeadline,   // Processing missed its deadline under the Drop policy
+    TransmissionDeadline, // Arrived late at the commander under the Drop policy
+    InjectedFault,        // Simulated packet loss
+    Disconnected,         // Receiving side hung up
+    Overflow,             // Bounded channel was full
+    ActuatorDown,         // Actuator inside a scheduled failure window
+    Invalid,              // Failed `SensorData::validate` for a reason other than NonFinite
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub data: SensorData,
+    pub reason: DropReason,
+    pub dropped_at: Instant,
+}
+
+struct DeadLetterState {
+    capacity: usize,
+    recent: VecDeque<DeadLetter>,
+    counts: HashMap<DropReason, u64>,
+}
+
+// Shared record of every discarded sample: the last `capacity` of them in full,
+// and a running count per reason for all of them
+#[derive(Clone)]
+pub struct DeadLetterLog {
+    state: Arc<Mutex<DeadLetterState>>,
+}
+
+impl Default for DeadLetterLog {
+    fn default() -> Self {
+        Self::with_capacity(64)
+    }
+}
+
+impl DeadLetterLog {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(DeadLetterState {
+                capacity,
+                recent: VecDeque::with_capacity(capacity),
+                counts: HashMap::new(),
+            })),
+        }
+    }
+
+    pub fn record(&self, data: SensorData, reason: DropReason) {
+        if let Ok(mut state) = self.state.lock() {
+            *state.counts.entry(reason).or_insert(0) += 1;
+            if state.capacity == 0 { return; }
+            if state.recent.len() >= state.capacity {
+                state.recent.pop_front();
+            }
+            state.recent.push_back(DeadLetter { data, reason, dropped_at: Instant::now() });
+        }
+    }
+
+    // Oldest first
+    pub fn recent(&self) -> Vec<DeadLetter> {
+        self.state.lock().map(|s| s.recent.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    pub fn counts(&self) -> HashMap<DropReason, u64> {
+        self.state.lock().map(|s| s.counts.clone()).unwrap_or_default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts().values().sum()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.state.lock().map(|s| s.capacity).unwrap_or(0)
+    }
+}
+
+// --------------- INVARIANTS -------------------
+// What the commander reports to the invariant checker as it runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SystemEvent {
+    SampleReceived { sensor_type: SensorType, id: i32 },
+    EffortComputed { sensor_type: SensorType, effort: f64 },
+    ModeChanged { from: SystemMode, to: SystemMode },
+}
+
+// Returns Err(description) when the event breaks the invariant
+pub type Invariant = Box<dyn FnMut(&SystemEvent) -> Result<(), String> + Send>;
+
+// Named invariants checked in registration order against every event
+#[derive(Default)]
+pub struct InvariantChecker {
+    invariants: Vec<(String, Invariant)>,
+}
+
+impl InvariantChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Bounded effort, E-STOP never left, and no sample id handled twice
+    pub fn with_builtins(effort_limit: f64) -> Self {
+        let mut checker = Self::new();
+
+        checker.add("effort-limit", Box::new(move |event| match *event {
+            SystemEvent::EffortComputed { sensor_type, effort } if !effort.is_finite() || effort.abs() > effort_limit => {
+                Err(format!("{:?} effort {} exceeds {}", sensor_type, effort, effort_limit))
+            }
+            _ => Ok(()),
+        }));
+
+        // There is no reset path yet, so E-STOP must stay latched
+        checker.add("estop-latched", Box::new(|event| match *event {
+            SystemEvent::ModeChanged { from: SystemMode::EmergencyStop, to } => {
+                Err(format!("left EmergencyStop for {:?} without a reset", to))
+            }
+            _ => Ok(()),
+        }));
+
+        // Sensor ids only ever increase, so a repeat or step back is a duplicate
+        let mut last_ids: HashMap<SensorType, i32> = HashMap::new();
+        checker.add("unique-sample-id", Box::new(move |event| match *event {
+            SystemEvent::SampleReceived { sensor_type, id } => match last_ids.insert(sensor_type, id) {
+                Some(last) if id <= last => Err(format!("{:?} sample {} processed after {}", sensor_type, id, last)),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }));
+
+        checker
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, invariant: Invariant) {
+        self.invariants.push((name.into(), invariant));
+    }
+
+    // Stops at the first violated invariant
+    pub fn check(&mut self, event: &SystemEvent) -> Result<(), String> {
+        for (name, invariant) in self.invariants.iter_mut() {
+            invariant(event).map_err(|e| format!("{}: {}", name, e))?;
+        }
+        Ok(())
+    }
+}
+
+// --------------- CALIBRATION -------------------
+// Per-sensor calibration offsets shared by the sensors of one run. Each sensor
+// seeds its offset from here and writes the learned value back when it stops.
+// Persisted as a flat JSON object, e.g. {"Force": 0.25, "Position": -0.01}
+#[derive(Clone, Default)]
+pub struct CalibrationStore {
+    offsets: Arc<Mutex<HashMap<SensorType, f64>>>,
+}
+
+impl CalibrationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A missing file is an empty store, a malformed one is an error
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e),
+        };
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let body = text.trim().strip_prefix('{').and_then(|t| t.strip_suffix('}'))
+            .ok_or_else(|| invalid("expected a JSON object".to_string()))?;
+
+        let store = Self::new();
+        for entry in body.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, value) = entry.split_once(':')
+                .ok_or_else(|| invalid(format!("expected \"key\": value, got {}", entry)))?;
+            let s_type = match key.trim().trim_matches('"') {
+                "Force" => SensorType::Force,
+                "Position" => SensorType::Position,
+                "Temperature" => SensorType::Temperature,
+                other => return Err(invalid(format!("unknown sensor type {}", other))),
+            };
+            let offset: f64 = value.trim().parse()
+                .map_err(|_| invalid(format!("invalid offset for {:?}: {}", s_type, value.trim())))?;
+            store.set(s_type, offset);
+        }
+        Ok(store)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let offsets = self.offsets.lock().map(|o| o.clone()).unwrap_or_default();
+        let entries: Vec<String> = SensorType::all().iter()
+            .filter_map(|s| offsets.get(s).map(|v| format!("  \"{:?}\": {}", s, v)))
+            .collect();
+        std::fs::write(path, format!("{{\n{}\n}}\n", entries.join(",\n")))
+    }
+
+    pub fn get(&self, sensor_type: SensorType) -> Option<f64> {
+        self.offsets.lock().ok()?.get(&sensor_type).copied()
+    }
+
+    pub fn set(&self, sensor_type: SensorType, offset: f64) {
+        if let Ok(mut offsets) = self.offsets.lock() {
+            offsets.insert(sensor_type, offset);
+        }
+    }
+}
+
+// --------------- LOG FILE -------------------
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Critical,
+}
+
+// How much the components print to stdout while running
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Silent,  // Nothing until the final report
+    Normal,  // Alerts and disconnects
+    Verbose, // Plus one line per generated sample and actuation
+}
+
+// One retained log line and when the event it describes happened
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub at: Instant,
+    pub level: LogLevel,
+    pub line: String, // As written to system.log, without the newline
+}
+
+pub struct SystemLog {
+    file: Option<File>,
+    pub active: bool,
+    shutdown_reason: Option<ShutdownReason>,
+    live_output: bool,   // Mirror entries to stderr as they are written
+    min_level: LogLevel, // Threshold for the live output
+    verbosity: Verbosity,
+    recent: VecDeque<LogEntry>, // Last `RECENT_LOG_LINES` entries, kept for `SimulationHandle::join`
+}
+
+const RECENT_LOG_LINES: usize = 256;
+
+impl Default for SystemLog {
+    fn default() -> Self { Self::new() }
+}
+
+impl SystemLog {
+    pub fn new() -> Self {
+        // Create/Truncate simulation.txt
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open("system.log")
+            .expect("Unable to open log file");
+
+        Self { file: Some(file), ..Self::in_memory() }
+    }
+
+    // Same, without the system.log file, e.g. for a commander replaying a recording
+    pub fn in_memory() -> Self {
+        Self {
+            file: None,
+            active: true,
+            shutdown_reason: None,
+            live_output: false,
+            min_level: LogLevel::Info,
+            verbosity: Verbosity::Normal,
+            recent: VecDeque::with_capacity(RECENT_LOG_LINES),
+        }
+    }
+
+    // Signal every thread to stop. The first reason wins, later calls only log.
+    pub fn request_shutdown(&mut self, reason: ShutdownReason) {
+        if self.shutdown_reason.is_none() {
+            self.write(format!("[Shutdown] Initiated: {:?}", reason));
+            self.shutdown_reason = Some(reason);
+        }
+        self.active = false;
+    }
+
+    pub fn shutdown_reason(&self) -> Option<ShutdownReason> {
+        self.shutdown_reason.clone()
+    }
+
+    pub fn set_min_level(&mut self, level: LogLevel) {
+        self.min_level = level;
+    }
+
+    pub fn set_live_output(&mut self, enabled: bool) {
+        self.live_output = enabled;
+    }
+
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    // How many entries the ring buffer holds now, and at most
+    pub fn retained_entries(&self) -> usize { self.recent.len() }
+    pub fn retention(&self) -> usize { RECENT_LOG_LINES }
+
+    // Most recent entries, oldest first
+    pub fn recent_entries(&self) -> Vec<String> {
+        self.recent.iter().map(|e| e.line.clone()).collect()
+    }
+
+    // Same entries ordered by event time instead of by who got the lock first,
+    // so two runs of the same scenario produce comparable logs. Ties keep their write order.
+    pub fn sorted_entries(&self) -> Vec<&LogEntry> {
+        let mut entries: Vec<&LogEntry> = self.recent.iter().collect();
+        entries.sort_by_key(|e| e.at); // Stable
+        entries
+    }
+
+    // Console message: printed when `verbosity` is enabled, and logged to file
+    // unless it is a per-sample Verbose line that is not being printed anyway
+    pub fn print(&mut self, verbosity: Verbosity, msg: String) {
+        let enabled = self.verbosity >= verbosity;
+        if !enabled && verbosity == Verbosity::Verbose {
+            return;
+        }
+        if enabled {
+            println!("{}", msg);
+        }
+        self.write(msg);
+    }
+
+    pub fn write(&mut self, msg: String) {
+        self.write_level(LogLevel::Info, msg);
+    }
+
+    pub fn write_level(&mut self, level: LogLevel, msg: String) {
+        self.write_level_at(Instant::now(), level, msg);
+    }
+
+    // For an event that happened at `at`, before the caller got hold of the lock
+    pub fn write_level_at(&mut self, at: Instant, level: LogLevel, msg: String) {
+        let happened = chrono::Local::now() - chrono::Duration::from_std(at.elapsed()).unwrap_or_default();
+        let timestamp = happened.format("%H:%M:%S%.3f"); // Requires 'chrono' crate, or use debug formatting
+        let log_line = format!("[{}] [{:?}] {}\n", timestamp, level, msg);
+
+        // Write to file instead of println
+        if let Some(ref mut file) = self.file {
+            let _ = file.write_all(log_line.as_bytes());
+        }
+
+        // Live output is printed while the lock is held, so lines never interleave
+        if self.live_output && level >= self.min_level {
+            eprint!("{}", log_line);
+        }
+
+        if self.recent.len() == RECENT_LOG_LINES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(LogEntry { at, level, line: log_line.trim_end().to_string() });
+    }
+
+    // Lines are written as they are logged, so nothing is queued in the process; this
+    // waits until the OS has them on disk, for callers that exit right after a run
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(ref mut file) = self.file {
+            file.flush()?;
+            file.sync_data()?;
+        }
+        io::stderr().flush()
+    }
+    pub fn alert(&mut self, msg: String) {
+        let banner = format!("\n**************************************************\n!!! {} !!!\n**************************************************\n", msg);
+        if self.verbosity >= Verbosity::Normal {
+            println!("{}", banner); // Force print to console
+        }
+        self.write_level(LogLevel::Critical, msg); // Log to file
+    }
+}
+
+// --------------- SIMULATION CONFIG -------------------
+// What a stage does with the item it was handling when it misses its deadline
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlinePolicy {
+    Drop,            // Discard the item
+    MarkAndContinue, // Count + log the miss, keep the item
+    Escalate,        // As MarkAndContinue, and count it as an anomaly for the mode state machine
+}
+
+// Defaults keep the historical behaviour of each stage:
+// - processing:   Drop (the late sample is never sent)
+// - transmission: MarkAndContinue (the commander still runs PID on it)
+// - feedback:     MarkAndContinue (the recalibration is still applied)
+// - actuation:    MarkAndContinue. Actuators have no path back to the commander,
+//   so Escalate behaves like MarkAndContinue there and Drop suppresses the feedback.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadlinePolicies {
+    pub processing: DeadlinePolicy,
+    pub transmission: DeadlinePolicy,
+    pub feedback: DeadlinePolicy,
+    pub actuation: DeadlinePolicy,
+}
+
+impl Default for DeadlinePolicies {
+    fn default() -> Self {
+        Self {
+            processing: DeadlinePolicy::Drop,
+            transmission: DeadlinePolicy::MarkAndContinue,
+            feedback: DeadlinePolicy::MarkAndContinue,
+            actuation: DeadlinePolicy::MarkAndContinue,
+        }
+    }
+}
+
+impl DeadlinePolicies {
+    pub fn for_stage(&self, stage: Stage) -> DeadlinePolicy {
+        match stage {
+            Stage::Processing => self.processing,
+            Stage::Transmission => self.transmission,
+            Stage::Feedback => self.feedback,
+            Stage::Actuation => self.actuation,
+        }
+    }
+}
+
+// One value per sensor type, `Copy` whenever the value is
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PerSensor<T> {
+    pub force: T,
+    pub position: T,
+    pub temperature: T,
+}
+
+impl<T: Copy> PerSensor<T> {
+    pub fn splat(value: T) -> Self {
+        Self { force: value, position: value, temperature: value }
+    }
+
+    pub fn get(&self, sensor_type: SensorType) -> T {
+        match sensor_type {
+            SensorType::Force => self.force,
+            SensorType::Position => self.position,
+            SensorType::Temperature => self.temperature,
+        }
+    }
+
+    pub fn get_mut(&mut self, sensor_type: SensorType) -> &mut T {
+        match sensor_type {
+            SensorType::Force => &mut self.force,
+            SensorType::Position => &mut self.position,
+            SensorType::Temperature => &mut self.temperature,
+        }
+    }
+
+    pub fn map<U>(&self, f: impl Fn(T) -> U) -> PerSensor<U> {
+        PerSensor { force: f(self.force), position: f(self.position), temperature: f(self.temperature) }
+    }
+}
+
+// Every cycle time, simulated delay and deadline of the pipeline in one place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deadlines {
+    pub sensor_cycle: Duration,   // Sensor sampling period
+    pub processing: Duration,     // Sensor filtering + anomaly detection
+    pub transmission: PerSensor<Duration>, // Sensor -> Commander, by criticality
+    pub feedback: Duration,       // Actuator -> Sensor
+    pub actuation: Duration,      // Actuator operation
+    pub actuation_work: Duration, // Simulated actuator work
+    pub feedback_starvation: Duration, // Longest tolerated gap between feedbacks to one sensor
+    pub end_to_end: Duration,     // Sample generation -> actuation complete
+    pub commander_budget: Duration, // One `handle_sensor_data` call in the commander
+    pub policies: DeadlinePolicies,
+}
+
+impl Default for Deadlines {
+    fn default() -> Self {
+        Self {
+            sensor_cycle: Duration::from_millis(5),
+            processing: Duration::from_micros(200),
+            transmission: PerSensor {
+                force: default_profile(SensorType::Force).transmission_deadline,
+                position: default_profile(SensorType::Position).transmission_deadline,
+                temperature: default_profile(SensorType::Temperature).transmission_deadline,
+            },
+            feedback: Duration::from_micros(500),
+            actuation: Duration::from_micros(2000),
+            actuation_work: Duration::from_micros(100),
+            feedback_starvation: Duration::from_secs(1),
+            end_to_end: Duration::from_millis(5),
+            commander_budget: Duration::from_millis(1),
+            policies: DeadlinePolicies::default(),
+        }
+    }
+}
+
+impl Deadlines {
+    // Compress (time_scale > 1) or stretch (time_scale < 1) every duration
+    pub fn scaled(&self, time_scale: f64) -> Self {
+        Self {
+            sensor_cycle: self.sensor_cycle.div_f64(time_scale),
+            processing: self.processing.div_f64(time_scale),
+            transmission: self.transmission.map(|d| d.div_f64(time_scale)),
+            feedback: self.feedback.div_f64(time_scale),
+            actuation: self.actuation.div_f64(time_scale),
+            actuation_work: self.actuation_work.div_f64(time_scale),
+            feedback_starvation: self.feedback_starvation.div_f64(time_scale),
+            end_to_end: self.end_to_end.div_f64(time_scale),
+            commander_budget: self.commander_budget.div_f64(time_scale),
+            policies: self.policies,
+        }
+    }
+}
+
+// Scheduling policy of the sensor and commander threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedPolicy {
+    #[default]
+    Normal,                 // Leave the OS default
+    Fifo { priority: u8 },  // SCHED_FIFO, 1 (lowest) to 99
+}
+
+// Opt-in real-time setup, applied by each thread to itself at start-up (Linux only,
+// a warning elsewhere or without the needed privileges)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RtConfig {
+    pub policy: SchedPolicy,
+    pub core_affinity: Option<Vec<usize>>, // Cores handed out to the threads in turn
+}
+
+// When a run ends on its own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopCondition {
+    Duration(Duration), // Wall-clock run time
+    Samples(u64),       // Samples generated across all sensors, for runs comparable between machines
+}
+
+// Sample count shared by the sensors under `StopCondition::Samples`
+#[derive(Debug, Clone)]
+pub struct SampleBudget {
+    taken: Arc<AtomicU64>,
+    limit: u64,
+}
+
+impl SampleBudget {
+    pub fn new(limit: u64) -> Self {
+        Self { taken: Arc::new(AtomicU64::new(0)), limit }
+    }
+
+    // Claim one sample; false once all of them are taken
+    pub fn try_take(&self) -> bool {
+        self.taken.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| (n < self.limit).then_some(n + 1)).is_ok()
+    }
+
+    pub fn taken(&self) -> u64 {
+        self.taken.load(Ordering::Relaxed)
+    }
+}
+// What a threaded sensor does when its bounded channel to the commander is full
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackpressurePolicy {
+    #[default]
+    Drop,     // Discard the sample as `DropReason::Overflow`
+    SlowDown, // Wait for room and lengthen the cycle until the commander catches up
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    pub stop_after: StopCondition,
+    pub live_log: bool,          // Print log entries to stderr while running
+    pub min_log_level: LogLevel, // Threshold for the live log output
+    pub deadlines: Deadlines,    // In simulated time
+    pub time_scale: f64,         // Simulated seconds per wall-clock second
+    pub pid_trace_output: Option<PathBuf>, // Per-cycle PID CSV, off by default
+    pub commander_tick: Duration, // Wall-clock period of the commander's maintenance tick
+    pub heartbeat: bool,          // Log a liveness line on every tick
+    pub recalibration_decay: Option<Duration>, // In simulated time, tokio sensors only, see `SensorAsync::with_recalibration_decay`
+    pub invariant_effort_limit: Option<f64>, // Run the built-in invariants with this effort bound
+    pub verbosity: Verbosity,
+    pub calibration_file: Option<PathBuf>, // Offsets loaded at start-up and saved on shutdown
+    pub setpoints: HashMap<SensorType, SetpointSchedule>, // In simulated time; missing types keep their default
+    pub fault_rates: FaultRates,
+    pub fault_schedule: FaultSchedule, // In simulated time, threaded backend only; replaces the random rolls of the sensors it names
+    pub correlated_fault: Option<CorrelatedFault>,
+    pub adaptive_sampling: Option<AdaptiveSampling>, // None samples at the fixed `sensor_cycle`
+    pub feedback_mode: FeedbackMode, // Threaded sensors only
+    pub anomaly_rate_gate: Option<AnomalyRateGate>,  // None escalates on consecutive anomalies only
+    pub influx_addr: Option<SocketAddr>, // Push metrics in InfluxDB line protocol over UDP
+    pub worker_threads: Option<usize>,   // Tokio backend only, see `run_simulation_async`
+    pub anomaly_confirm: usize,          // Consecutive out-of-range readings before a sample is anomalous
+    pub stages: HashMap<SensorType, StageFlags>, // Missing types run every stage
+    pub record: bool, // Keep every commander input and command, see `verify_replay`
+    pub shutdown_grace: Duration, // Wall-clock time the threads get to stop before the watchdog gives up on them
+    pub rt: Option<RtConfig>, // Threaded backend only; None keeps the OS scheduling
+    pub gains: HashMap<SensorType, (f64, f64, f64)>, // (kp, ki, kd); missing types keep their default
+    pub degraded_gains: HashMap<SensorType, (f64, f64, f64)>, // Degraded mode; missing types run at half the normal gains
+    pub degraded_setpoints: HashMap<SensorType, SetpointSchedule>, // Degraded mode; missing types keep the normal schedule
+    pub actuator_limits: HashMap<SensorType, (f64, f64)>, // (min, max) effort; missing types never saturate
+    pub failure_schedules: HashMap<SensorType, FailureSchedule>, // In simulated time, threaded actuators only; missing types never fail
+    pub actuator_retry: Option<ActuatorRetry>, // In simulated time; None holds a failed actuator's commands until it recovers
+    pub plants: HashMap<SensorType, PlantModel>, // In simulated time, threaded backend only; missing types generate random readings
+    pub deadbands: HashMap<SensorType, f64>, // Threaded commander only; missing types send every effort
+    pub sensor_priority: Vec<SensorType>, // Threaded commander's order for ready sensor channels; missing types come last
+    pub drift_rates: HashMap<SensorType, f64>, // Bias added per sample, threaded sensors only; missing types do not drift
+    pub drift_correction: Option<usize>, // Readings per bias check in the threaded commander, None leaves drift alone
+    pub batch_size: usize, // Samples the threaded sensors send per channel operation
+    pub parallel_commanders: bool, // One threaded commander per sensor type, see `ActuatorCommander::run_parallel`
+    pub settling_band: f64, // Threaded commander's step responses, as a share of the step size
+    pub soak_check_interval: Option<Duration>, // Wall-clock period of the soak checks, threaded backend only; see `soak::SoakCheck`
+    pub sensor_channel_capacity: Option<usize>, // Threaded backend only; None keeps the sensor channels unbounded
+    pub backpressure: BackpressurePolicy, // Used once `sensor_channel_capacity` is set
+    pub maintain_decay: f64, // Share of the calibration offset a threaded sensor drops on `SensorFeedback::Maintain`
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            stop_after: StopCondition::Duration(Duration::from_secs(2)),
+            live_log: false, // Keep the bench quiet by default
+            min_log_level: LogLevel::Warn,
+            deadlines: Deadlines::default(),
+            time_scale: 1.0,
+            pid_trace_output: None,
+            commander_tick: Duration::from_millis(100),
+            heartbeat: false,
+            recalibration_decay: None,
+            invariant_effort_limit: None,
+            verbosity: Verbosity::Normal,
+            calibration_file: None,
+            setpoints: HashMap::new(),
+            fault_rates: FaultRates::default(),
+            fault_schedule: FaultSchedule::default(),
+            correlated_fault: None,
+            adaptive_sampling: None,
+            feedback_mode: FeedbackMode::Immediate,
+            anomaly_rate_gate: Some(AnomalyRateGate::default()),
+            influx_addr: None,
+            worker_threads: None,
+            anomaly_confirm: 1,
+            stages: HashMap::new(),
+            record: false,
+            shutdown_grace: Duration::from_secs(2),
+            rt: None,
+            gains: HashMap::new(),
+            degraded_gains: HashMap::new(),
+            degraded_setpoints: HashMap::new(),
+            actuator_limits: HashMap::new(),
+            failure_schedules: HashMap::new(),
+            actuator_retry: None,
+            plants: HashMap::new(),
+            deadbands: HashMap::new(),
+            sensor_priority: SensorType::all().to_vec(),
+            drift_rates: HashMap::new(),
+            drift_correction: None,
+            batch_size: 1,
+            parallel_commanders: false,
+            soak_check_interval: None,
+            settling_band: DEFAULT_SETTLING_BAND,
+            sensor_channel_capacity: None,
+            backpressure: BackpressurePolicy::Drop,
+            maintain_decay: 0.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(String),   // Not valid JSON, or fields of the wrong type
+    Invalid(String), // Parsed, but rejected by `SimulationConfig::validate`
 }
 
-#[derive(Debug, Clone)]
-pub enum SensorFeedback {
-    Recalibrate { offset: f64 }, // Instruct sensor to shift values
-    Maintain,
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "cannot read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "cannot parse config: {}", e),
+            ConfigError::Invalid(e) => write!(f, "invalid config: {}", e),
+        }
+    }
 }
 
-// Inefficient Struct Approach
-pub struct Feedback {
-    pub is_ack: bool,
-    pub error_msg: String,
-    pub recalibrate_offset: f64,
-    pub timestamp: Instant,
+impl std::error::Error for ConfigError {}
+
+// Every way `try_run_simulation` can fail
+#[derive(Debug)]
+pub enum SimError {
+    Config(ConfigError),          // Rejected before anything was started
+    SelfTest(String),             // Wiring check failed, nothing was started
+    RuntimeUnavailable(String),   // The tokio runtime could not be built
+    SetupPanicked(String),        // Start-up panicked, e.g. the log file could not be created
+    ThreadPanicked(SensorType),   // A sensor or actuator thread of this type panicked
+    CommanderPanicked,
+    PoisonedLog,                  // A thread panicked while holding the system log
+    WatchdogTimeout(Vec<String>), // These threads never stopped, see `SimulationConfig::shutdown_grace`
+    ChannelDisconnected,          // A peer hung up while the simulation was starting
+    EmergencyStop,                // E-STOP latched before the run got going
+    InvariantViolation(String),   // See `InvariantChecker`
+    StoppedDuringStartup(ShutdownReason), // Stopped cleanly before start-up finished
 }
 
-// --------------- ACTUATOR COMMANDER MODULE -------------------
+// Why `start_simulation` gave up, as the matching `SimError`
+impl From<ShutdownReason> for SimError {
+    fn from(reason: ShutdownReason) -> Self {
+        match reason {
+            ShutdownReason::InvalidConfig(msg) => SimError::Config(ConfigError::Invalid(msg)),
+            ShutdownReason::SelfTestFailed(e) => SimError::SelfTest(e),
+            ShutdownReason::RuntimeUnavailable(e) => SimError::RuntimeUnavailable(e),
+            ShutdownReason::ChannelDisconnected => SimError::ChannelDisconnected,
+            ShutdownReason::EmergencyStop => SimError::EmergencyStop,
+            ShutdownReason::InvariantViolation(e) => SimError::InvariantViolation(e),
+            ShutdownReason::WatchdogTimeout(stuck) => SimError::WatchdogTimeout(stuck),
+            ShutdownReason::ThreadPanicked => SimError::SetupPanicked("a thread panicked during start-up".to_string()),
+            ShutdownReason::DurationElapsed | ShutdownReason::SampleLimitReached => SimError::StoppedDuringStartup(reason),
+        }
+    }
+}
 
-#[derive(Debug, Clone)]
-pub enum ActuatorStatus {
-    ActionComplete { sensor_type: SensorType, effort: f64 },
-    HardwareFailure(String),
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimError::Config(e) => write!(f, "{}", e),
+            SimError::SelfTest(e) => write!(f, "self-test failed: {}", e),
+            SimError::RuntimeUnavailable(e) => write!(f, "cannot build tokio runtime: {}", e),
+            SimError::SetupPanicked(msg) => write!(f, "start-up panicked: {}", msg),
+            SimError::ThreadPanicked(s_type) => write!(f, "a {:?} thread panicked", s_type),
+            SimError::CommanderPanicked => write!(f, "the commander thread panicked"),
+            SimError::PoisonedLog => write!(f, "the system log was poisoned"),
+            SimError::WatchdogTimeout(stuck) => write!(f, "threads did not stop: {}", stuck.join(", ")),
+            SimError::ChannelDisconnected => write!(f, "a channel disconnected during start-up"),
+            SimError::EmergencyStop => write!(f, "emergency stop during start-up"),
+            SimError::InvariantViolation(e) => write!(f, "invariant violated: {}", e),
+            SimError::StoppedDuringStartup(reason) => write!(f, "stopped during start-up: {:?}", reason),
+        }
+    }
 }
 
+impl std::error::Error for SimError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SimError::Config(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
-// --------------- PID CONTROLLER -------------------
-#[derive(Clone)]
-pub struct PidController {
-    pub kp: f64, pub ki: f64, pub kd: f64,
-    pub integral: f64, pub prev_error: f64,
+impl SimulationConfig {
+    // Shared by the sensors when the run is capped by sample count
+    pub fn sample_budget(&self) -> Option<SampleBudget> {
+        match self.stop_after {
+            StopCondition::Samples(limit) => Some(SampleBudget::new(limit)),
+            StopCondition::Duration(_) => None,
+        }
+    }
+
+    // Reject values the simulation cannot run with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |msg: &str| Err(ConfigError::Invalid(msg.to_string()));
+        let rate = |r: f64| (0.0..=1.0).contains(&r);
+
+        match self.stop_after {
+            StopCondition::Duration(d) if d.is_zero() => return invalid("duration must be positive"),
+            StopCondition::Samples(0) => return invalid("sample limit must be at least 1"),
+            _ => {}
+        }
+        if !self.time_scale.is_finite() || self.time_scale <= 0.0 { return invalid("time_scale must be a positive number"); }
+        if self.deadlines.sensor_cycle.is_zero() { return invalid("sensor_cycle must be positive"); }
+        if self.commander_tick.is_zero() { return invalid("commander_tick must be positive"); }
+        if !rate(self.fault_rates.drop_rate) || !rate(self.fault_rates.latency_rate) {
+            return invalid("fault rates must be between 0 and 1");
+        }
+        if self.fault_rates.drop_rate + self.fault_rates.latency_rate > 1.0 {
+            return invalid("drop_rate + latency_rate must not exceed 1");
+        }
+        if let Some(fault) = &self.correlated_fault {
+            if !rate(fault.probability) { return invalid("correlated fault probability must be between 0 and 1"); }
+        }
+        if self.gains.values().chain(self.degraded_gains.values()).any(|(kp, ki, kd)| !kp.is_finite() || !ki.is_finite() || !kd.is_finite()) {
+            return invalid("PID gains must be finite");
+        }
+        if self.anomaly_confirm == 0 { return invalid("anomaly_confirm must be at least 1"); }
+        if let Some(rt) = &self.rt {
+            if let SchedPolicy::Fifo { priority } = rt.policy {
+                if !(1..=99).contains(&priority) { return invalid("SCHED_FIFO priority must be between 1 and 99"); }
+            }
+            if rt.core_affinity.as_ref().is_some_and(|cores| cores.is_empty()) {
+                return invalid("core_affinity must list at least one core");
+            }
+        }
+        if self.worker_threads == Some(0) { return invalid("worker_threads must be at least 1"); }
+        if let Some(gate) = &self.anomaly_rate_gate {
+            if gate.window.is_zero() || !rate(gate.max_rate) {
+                return invalid("anomaly rate gate needs a positive window and a rate between 0 and 1");
+            }
+        }
+        if let Some(adaptive) = &self.adaptive_sampling {
+            if adaptive.floor.is_zero() || adaptive.floor > self.deadlines.sensor_cycle {
+                return invalid("adaptive sampling floor must be between 0 and sensor_cycle");
+            }
+        }
+        if self.actuator_limits.values().any(|(min, max)| min.is_nan() || max.is_nan() || min > max) {
+            return invalid("actuator limits must satisfy min <= max");
+        }
+        if self.failure_schedules.values().any(|f| f.recover_after.is_zero()) {
+            return invalid("failure schedules must have a non-zero recover_after");
+        }
+        if self.actuator_retry.is_some_and(|r| r.backoff.is_zero()) {
+            return invalid("actuator_retry needs a non-zero backoff");
+        }
+        for plant in self.plants.values() {
+            if !plant.gain.is_finite() || !plant.initial.is_finite() || !plant.noise.is_finite() || plant.disturbances.iter().any(|(_, d)| !d.is_finite()) {
+                return invalid("plant gains, initial states, noise and disturbances must be finite");
+            }
+            if plant.time_constant.is_zero() { return invalid("plant time constants must not be zero"); }
+        }
+        if self.drift_rates.values().any(|r| !r.is_finite()) {
+            return invalid("drift rates must be finite");
+        }
+        if self.batch_size == 0 { return invalid("batch_size must be at least 1"); }
+        if !(self.settling_band > 0.0 && self.settling_band < 1.0) { return invalid("settling_band must be between 0 and 1"); }
+        if self.soak_check_interval.is_some_and(|i| i.is_zero()) { return invalid("soak_check_interval must not be zero"); }
+        if self.sensor_channel_capacity == Some(0) { return invalid("sensor_channel_capacity must be at least 1"); }
+        if !(0.0..=1.0).contains(&self.maintain_decay) { return invalid("maintain_decay must be between 0 and 1"); }
+        if self.drift_correction == Some(0) {
+            return invalid("drift_correction must average at least 1 reading");
+        }
+        if self.deadbands.values().any(|d| d.is_nan() || *d < 0.0) {
+            return invalid("deadbands must not be negative");
+        }
+        if self.sensor_priority.iter().enumerate().any(|(i, s)| self.sensor_priority[..i].contains(s)) {
+            return invalid("sensor_priority must list each sensor type once");
+        }
+        if self.invariant_effort_limit.is_some_and(|l| l.is_nan() || l <= 0.0) {
+            return invalid("invariant_effort_limit must be positive");
+        }
+        Ok(())
+    }
 }
 
-impl PidController {
-    pub fn new(kp: f64, ki: f64, kd: f64) -> Self {
-        Self { kp, ki, kd, integral: 0.0, prev_error: 0.0 }
+// --------------- BENCHMARK -------------------
+// Time accumulator kept as u128 nanoseconds so summing millions of cycles cannot
+// overflow. It is only converted back to a (saturated) Duration for reporting.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DurationTotal(u128);
+
+impl DurationTotal {
+    pub fn as_nanos(&self) -> u128 { self.0 }
+    pub fn as_duration(&self) -> Duration {
+        let secs = self.0 / 1_000_000_000;
+        if secs > u64::MAX as u128 { return Duration::MAX; }
+        Duration::new(secs as u64, (self.0 % 1_000_000_000) as u32)
     }
-    pub fn compute(&mut self, target: f64, current: f64, dt: f64, scale: f64) -> f64 {
-        let error = target - current;
-        self.integral += error * dt;
-        let derivative = (error - self.prev_error) / dt;
-        self.prev_error = error;
-        ((self.kp * error) + (self.ki * self.integral) + (self.kd * derivative)) * scale
+}
+
+impl AddAssign<Duration> for DurationTotal {
+    fn add_assign(&mut self, rhs: Duration) { self.0 = self.0.saturating_add(rhs.as_nanos()); }
+}
+
+impl AddAssign for DurationTotal {
+    fn add_assign(&mut self, rhs: DurationTotal) { self.0 = self.0.saturating_add(rhs.0); }
+}
+
+impl DurationTotal {
+    pub fn mul_f64(&self, factor: f64) -> Self {
+        DurationTotal((self.0 as f64 * factor) as u128)
     }
 }
 
-// --------------- LOG FILE -------------------
-pub struct SystemLog {
-    file: Option<File>,
-    pub active: bool,
+impl Div<u32> for DurationTotal {
+    type Output = Duration;
+    fn div(self, rhs: u32) -> Duration {
+        let avg = self.0 / rhs as u128;
+        DurationTotal(avg).as_duration()
+    }
 }
 
-impl SystemLog {
-    pub fn new() -> Self {
-        // Create/Truncate simulation.txt
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open("system.log")
-            .expect("Unable to open log file");
+impl fmt::Debug for DurationTotal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.as_duration(), f)
+    }
+}
 
-        Self {
-            file: Some(file),
-            active: true,
+// Small copyable set of sensor types, used for per-sensor flags in the stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SensorSet(u8);
+
+impl SensorSet {
+    pub fn of(sensor_types: &[SensorType]) -> Self {
+        let mut set = Self::default();
+        for s in sensor_types { set.insert(*s); }
+        set
+    }
+    pub fn insert(&mut self, sensor_type: SensorType) { self.0 |= 1 << sensor_type as u8; }
+    pub fn remove(&mut self, sensor_type: SensorType) { self.0 &= !(1 << sensor_type as u8); }
+    pub fn contains(&self, sensor_type: SensorType) -> bool { self.0 & (1 << sensor_type as u8) != 0 }
+    pub fn is_empty(&self) -> bool { self.0 == 0 }
+    pub fn union(&self, other: &SensorSet) -> SensorSet { SensorSet(self.0 | other.0) }
+}
+
+// Cumulative time spent in each SystemMode
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ModeTimes {
+    pub normal: Duration,
+    pub degraded: Duration,
+    pub emergency_stop: Duration,
+}
+
+impl ModeTimes {
+    pub fn get(&self, mode: SystemMode) -> Duration {
+        match mode {
+            SystemMode::Normal => self.normal,
+            SystemMode::Degraded => self.degraded,
+            SystemMode::EmergencyStop => self.emergency_stop,
         }
     }
 
-    pub fn write(&mut self, msg: String) {
-        let timestamp = chrono::Local::now().format("%H:%M:%S%.3f"); // Requires 'chrono' crate, or use debug formatting
-        let log_line = format!("[{}] {}\n", timestamp, msg);
+    pub fn get_mut(&mut self, mode: SystemMode) -> &mut Duration {
+        match mode {
+            SystemMode::Normal => &mut self.normal,
+            SystemMode::Degraded => &mut self.degraded,
+            SystemMode::EmergencyStop => &mut self.emergency_stop,
+        }
+    }
 
-        // Write to file instead of println
-        if let Some(ref mut file) = self.file {
-            let _ = file.write_all(log_line.as_bytes());
+    pub fn total(&self) -> Duration {
+        self.normal + self.degraded + self.emergency_stop
+    }
+
+    // Share of the tracked time, 0-100
+    pub fn percent(&self, mode: SystemMode) -> f64 {
+        let total = self.total().as_secs_f64();
+        if total == 0.0 { 0.0 } else { self.get(mode).as_secs_f64() / total * 100.0 }
+    }
+}
+
+// Four buckets per doubling of the latency, so a percentile is off by at most ~19%
+const HISTOGRAM_BUCKETS: usize = 160; // Up to 2^40 ns, about 18 minutes
+
+// Log-scale latency histogram, cheap enough to keep in `BenchmarkStats`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyHistogram {
+    buckets: [u32; HISTOGRAM_BUCKETS], // Bucket i holds [2^(i/4), 2^((i+1)/4)) ns
+    scale: f64,                        // Applied when reading a percentile, see `to_simulated`
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self { buckets: [0; HISTOGRAM_BUCKETS], scale: 1.0 }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let nanos = latency.as_nanos().max(1) as f64;
+        let bucket = ((nanos.log2() * 4.0) as usize).min(HISTOGRAM_BUCKETS - 1);
+        self.buckets[bucket] = self.buckets[bucket].saturating_add(1);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|n| *n as u64).sum()
+    }
+
+    // Upper edge of the bucket holding the `p` quantile (0-1), zero when empty
+    pub fn percentile(&self, p: f64) -> Duration {
+        let target = (self.count() as f64 * p.clamp(0.0, 1.0)).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, n) in self.buckets.iter().enumerate() {
+            seen += *n as u64;
+            if seen >= target && *n > 0 {
+                let upper = 2f64.powf((bucket + 1) as f64 / 4.0);
+                return Duration::from_nanos(upper as u64).mul_f64(self.scale);
+            }
         }
+        Duration::ZERO
     }
-    pub fn alert(&mut self, msg: String) {
-        let banner = format!("\n**************************************************\n!!! {} !!!\n**************************************************\n", msg);
-        println!("{}", banner); // Force print to console
-        self.write(msg); // Log to file
+
+    fn merge(&mut self, other: &LatencyHistogram) {
+        for (mine, theirs) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *mine = mine.saturating_add(*theirs);
+        }
     }
 }
 
-// --------------- BENCHMARK -------------------
-#[derive(Debug, Default, Clone, Copy)]
+// Where one sensor's cycles went; whatever the stages did not use of `budget` was idle
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StageTimes {
+    pub budget: DurationTotal, // Sum of the cycle lengths
+    pub generation: DurationTotal,
+    pub processing: DurationTotal,
+    pub transmission: DurationTotal,
+}
+
+impl StageTimes {
+    pub fn idle(&self) -> DurationTotal {
+        let busy = self.generation.as_nanos() + self.processing.as_nanos() + self.transmission.as_nanos();
+        DurationTotal(self.budget.as_nanos().saturating_sub(busy))
+    }
+
+    // Share of the cycle budget, 0-100
+    pub fn percent(&self, stage: DurationTotal) -> f64 {
+        if self.budget.as_nanos() == 0 { 0.0 } else { stage.as_nanos() as f64 / self.budget.as_nanos() as f64 * 100.0 }
+    }
+
+    fn merge(&mut self, other: &StageTimes) {
+        self.budget += other.budget;
+        self.generation += other.generation;
+        self.processing += other.processing;
+        self.transmission += other.transmission;
+    }
+
+    fn mul_f64(&self, factor: f64) -> StageTimes {
+        StageTimes {
+            budget: self.budget.mul_f64(factor),
+            generation: self.generation.mul_f64(factor),
+            processing: self.processing.mul_f64(factor),
+            transmission: self.transmission.mul_f64(factor),
+        }
+    }
+}
+
+// Relative weight of each penalty in `BenchmarkStats::health_score_with`. Every penalty
+// runs from 0 (perfect) to 1 (as bad as it gets):
+//   deadline_misses: missed deadlines of any stage per sample
+//   non_normal_time: share of the run spent in Degraded or E-STOP
+//   p99_latency:     how far the p99 end-to-end latency is past its deadline, 1 at twice the deadline
+//   dropped:         share of the samples that never reached an actuator
+//   jitter:          average sensor jitter as a share of the sample period
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthWeights {
+    pub deadline_misses: f64,
+    pub non_normal_time: f64,
+    pub p99_latency: f64,
+    pub dropped: f64,
+    pub jitter: f64,
+}
+
+impl Default for HealthWeights {
+    fn default() -> Self {
+        Self { deadline_misses: 0.3, non_normal_time: 0.2, p99_latency: 0.2, dropped: 0.2, jitter: 0.1 }
+    }
+}
+
+// Where one sample spent its time, from capture to its actuator finishing with it.
+// A stage without a stamp counts as zero and its time goes to the next stage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CycleTimeline {
+    pub sensor_type: SensorType,
+    pub id: i32,
+    pub generation: Duration,
+    pub processing: Duration,
+    pub queue_wait: Duration, // Sensor to commander, including injected latency and batching
+    pub commander: Duration,
+    pub actuation: Duration,  // Including the wait in the actuator channel
+    pub feedback: Duration,
+    pub total: Duration,
+}
+
+impl CycleTimeline {
+    // `actuated` is when the actuation finished, `done` when the feedback was sent
+    pub fn of(data: &SensorData, actuated: Instant, done: Instant) -> Self {
+        let start = data.captured_at();
+        let mut last = start;
+        let mut stage = |stamp: Option<Instant>| {
+            let Some(t) = stamp else { return Duration::ZERO; };
+            let spent = t.saturating_duration_since(last);
+            last = last.max(t);
+            spent
+        };
+        Self {
+            sensor_type: data.sensor_type,
+            id: data.id,
+            generation: stage(data.stamps.generated),
+            processing: stage(data.processed_timestamp),
+            queue_wait: stage(data.stamps.received),
+            commander: stage(data.stamps.commanded),
+            actuation: stage(Some(actuated)),
+            feedback: stage(Some(done)),
+            total: done.saturating_duration_since(start),
+        }
+    }
+
+    pub fn stages(&self) -> [(&'static str, Duration); 6] {
+        [("Generation", self.generation), ("Processing", self.processing), ("Queue Wait", self.queue_wait),
+         ("Commander", self.commander), ("Actuation", self.actuation), ("Feedback", self.feedback)]
+    }
+
+    fn mul_f64(&self, factor: f64) -> CycleTimeline {
+        CycleTimeline {
+            generation: self.generation.mul_f64(factor),
+            processing: self.processing.mul_f64(factor),
+            queue_wait: self.queue_wait.mul_f64(factor),
+            commander: self.commander.mul_f64(factor),
+            actuation: self.actuation.mul_f64(factor),
+            feedback: self.feedback.mul_f64(factor),
+            total: self.total.mul_f64(factor),
+            ..*self
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct BenchmarkStats {
     pub sensor_count: u32,
     pub actuator_count: u32,
-    pub total_actuator_time:Duration,
-    pub total_gen_time: Duration,
-    pub total_proc_time: Duration,
-    pub total_trans_time: Duration,
-    pub total_jitter: Duration,
+    pub total_actuator_time: DurationTotal,
+    pub total_gen_time: DurationTotal,
+    pub total_proc_time: DurationTotal,
+    pub cpu_proc_time: DurationTotal, // Thread CPU time of the threaded sensors' processing, see `cpu_time::supported`
+    pub total_trans_time: DurationTotal,
+    pub total_jitter: DurationTotal,
+    pub total_jitter_sq: f64, // Sum of squared jitter in s², for the standard deviation
     pub max_jitter: Duration,
-    pub total_at_jitter: Duration,
+    pub total_at_jitter: DurationTotal,
     pub max_at_jitter: Duration,
-    pub total_latency: Duration,
+    pub total_latency: DurationTotal,
+    pub latency_histogram: LatencyHistogram, // End-to-end latency, for percentiles
+    pub anomaly_count: u32, // Samples the sensors flagged
     pub sensor_missed_deadlines: u32,
     pub actuator_missed_deadlines: u32,
+    pub stability_warning: SensorSet, // Sensors whose effort oscillated
+    pub transmission_misses: PerSensor<u32>,
+    pub feedback_drops: u32, // Feedback the actuators could not deliver
+    pub max_feedback_gap: PerSensor<Duration>, // Longest a sensor went without feedback
+    pub feedback_starved: SensorSet, // Sensors whose gap exceeded the starvation threshold
+    pub actuator_saturations: u32, // Commands clamped to the actuator limits
+    pub e2e_deadline_misses: u32,
+    pub commander_overruns: u32, // Samples the commander took longer than its budget on
+    pub suppressed_commands: u32, // Efforts inside the deadband, not sent
+    pub invalid_samples: u32, // Rejected by `SensorData::validate` at the commander
+    pub dropped_logs: u32, // Async log lines skipped because the log was busy, see `try_log`
+    pub backpressure_slowdowns: u32, // Times a sensor lengthened its cycle on a full channel
+    pub max_sample_period: Duration, // Longest cycle a sensor slowed down to
+    pub e2e_deadline: Duration, // End-to-end deadline the actuators measured against
+    pub active_duration: Duration, // From start to stop of each sensor loop, summed over the sensors
+    pub drift_corrections: u32, // Recalibrations the commander sent against bias drift
+    pub faulted_actuators: SensorSet, // Actuators the commander gave up sending to
+    pub total_sample_period: DurationTotal, // Sum of the cycle lengths the sensors ran with
+    pub time_in_mode: ModeTimes,            // Filled in by the commander
+    pub total_lock_wait: DurationTotal,     // Time spent waiting for the shared log, see `timed_lock`
+    pub max_lock_wait: Duration,
+    pub rt_threads: u32, // Threads running with the full `RtConfig` applied
+    pub stage_times: PerSensor<StageTimes>, // Per-sensor breakdown of the cycle budget
+    pub worst_cycle: Option<CycleTimeline>, // Sample with the highest end-to-end latency
+    pub value_range: Option<(f64, f64)>,    // (min, max) raw reading generated, non-finite ones skipped
+    pub step_settling: Option<Duration>, // Longest settling time of the steps that settled, see `record_step`
+    pub step_overshoot: Option<f64>,     // Largest overshoot of any step, in % of the step size
+    pub total_roundtrip: DurationTotal,  // Sample capture to the feedback answering it, see `Feedback::round_trip`
+    pub roundtrip_count: u32,
+    pub max_roundtrip: Duration,
 }
 
 impl BenchmarkStats {
     pub fn new() -> Self { Self::default() }
+    // Zero every counter, e.g. between a warm-up and a measurement phase;
+    // the deadline the actuators measure against is configuration and stays
+    pub fn reset(&mut self) { *self = Self { e2e_deadline: self.e2e_deadline, ..Self::default() }; }
     pub fn avg_gen(&self) -> Duration { if self.sensor_count == 0 { Duration::ZERO } else { self.total_gen_time / self.sensor_count } }
     pub fn avg_proc(&self) -> Duration { if self.sensor_count == 0 { Duration::ZERO } else { self.total_proc_time / self.sensor_count } }
     pub fn avg_trans(&self) -> Duration { if self.sensor_count == 0 { Duration::ZERO } else { self.total_trans_time / self.sensor_count } }
+    pub fn avg_sample_period(&self) -> Duration { if self.sensor_count == 0 { Duration::ZERO } else { self.total_sample_period / self.sensor_count } }
     pub fn avg_jitter(&self) -> Duration { if self.sensor_count == 0 { Duration::ZERO } else { self.total_jitter / self.sensor_count } }
+    pub fn p99_latency(&self) -> Duration { self.latency_histogram.percentile(0.99) }
+
+    // Early samples count as zero jitter, like in `avg_jitter`
+    pub fn jitter_std_dev(&self) -> Duration {
+        if self.sensor_count == 0 { return Duration::ZERO; }
+        let n = self.sensor_count as f64;
+        let mean = self.total_jitter.as_duration().as_secs_f64() / n;
+        Duration::from_secs_f64((self.total_jitter_sq / n - mean * mean).max(0.0).sqrt())
+    }
     pub fn avg_at_jitter(&self) -> Duration { if self.actuator_count == 0 { Duration::ZERO } else { self.total_at_jitter / self.actuator_count } }
 
     pub fn avg_actuator(&self) -> Duration { if self.sensor_count == 0 { Duration::ZERO } else { self.total_actuator_time / self.sensor_count } }
     pub fn avg_latency(&self) -> Duration { if self.sensor_count == 0 { Duration::ZERO } else { self.total_latency / self.sensor_count } }
+    pub fn avg_roundtrip(&self) -> Duration { if self.roundtrip_count == 0 { Duration::ZERO } else { self.total_roundtrip / self.roundtrip_count } }
+
+    pub fn record_roundtrip(&mut self, round_trip: Duration) {
+        self.total_roundtrip += round_trip;
+        self.roundtrip_count += 1;
+        self.max_roundtrip = self.max_roundtrip.max(round_trip);
+    }
+    // Samples per second of the time the sensors actually ran, so a sensor that stopped
+    // a cycle early is not penalised; the mean over the sensors for merged stats
+    pub fn sample_rate(&self) -> f64 {
+        let active = self.active_duration.as_secs_f64();
+        if active == 0.0 { 0.0 } else { self.sensor_count as f64 / active }
+    }
     pub fn throughput(&self, total_run_time: Duration) -> f64 {
         if total_run_time.as_secs_f64() == 0.0 { 0.0 } else { self.sensor_count as f64 / total_run_time.as_secs_f64() }
     }
@@ -145,19 +2491,542 @@ impl BenchmarkStats {
         (self.actuator_missed_deadlines as f64 / self.actuator_count as f64) * 100.0
     }
 
+    pub fn e2e_deadline_rate (&self) -> f64 {
+        (self.e2e_deadline_misses as f64 / self.actuator_count as f64) * 100.0
+    }
+
+    // 0-100 verdict on the whole run with the default weights
+    pub fn health_score(&self) -> f64 {
+        self.health_score_with(&HealthWeights::default())
+    }
+
+    // 100 minus the weighted mean of the penalties described at `HealthWeights`
+    pub fn health_score_with(&self, weights: &HealthWeights) -> f64 {
+        let ratio = |part: f64, whole: f64| if whole > 0.0 { (part / whole).clamp(0.0, 1.0) } else { 0.0 };
+        let samples = self.sensor_count as f64;
+        let misses = (self.sensor_missed_deadlines + self.actuator_missed_deadlines + self.e2e_deadline_misses) as f64;
+        let modes = &self.time_in_mode;
+        let non_normal = (modes.percent(SystemMode::Degraded) + modes.percent(SystemMode::EmergencyStop)) / 100.0;
+        let p99 = self.p99_latency().as_secs_f64();
+        let deadline = self.e2e_deadline.as_secs_f64();
+        let reached = (self.actuator_count + self.suppressed_commands) as f64;
+
+        let penalties = [
+            (weights.deadline_misses, ratio(misses, samples)),
+            (weights.non_normal_time, non_normal.clamp(0.0, 1.0)),
+            (weights.p99_latency, ratio(p99 - deadline, deadline)),
+            (weights.dropped, ratio(samples - reached, samples)),
+            (weights.jitter, ratio(self.avg_jitter().as_secs_f64(), self.avg_sample_period().as_secs_f64())),
+        ];
+        let total: f64 = penalties.iter().map(|(w, _)| w).sum();
+        if total <= 0.0 { return 100.0; }
+        100.0 * (1.0 - penalties.iter().map(|(w, p)| w * p).sum::<f64>() / total)
+    }
+
+    // Lock `mutex`, counting the wait as contention. None if it is poisoned
+    pub fn timed_lock<'a, T>(&mut self, mutex: &'a Mutex<T>) -> Option<MutexGuard<'a, T>> {
+        let start = Instant::now();
+        let guard = mutex.lock().ok();
+        let waited = start.elapsed();
+        self.total_lock_wait += waited;
+        self.max_lock_wait = self.max_lock_wait.max(waited);
+        guard
+    }
+
+    // Write a non-critical line from an async task without waiting: a busy log drops
+    // it and counts the drop. Anything that must be logged awaits the lock instead.
+    pub fn try_log(&mut self, log: &tokio::sync::Mutex<SystemLog>, level: LogLevel, msg: String) {
+        match log.try_lock() {
+            Ok(mut log) => log.write_level(level, msg),
+            Err(_) => self.dropped_logs += 1,
+        }
+    }
+
+    // Convert wall-clock measurements back to simulated time
+    pub fn to_simulated(&self, time_scale: f64) -> BenchmarkStats {
+        let mut stats = *self;
+        stats.total_actuator_time = self.total_actuator_time.mul_f64(time_scale);
+        stats.total_gen_time = self.total_gen_time.mul_f64(time_scale);
+        stats.total_proc_time = self.total_proc_time.mul_f64(time_scale);
+        stats.cpu_proc_time = self.cpu_proc_time.mul_f64(time_scale);
+        stats.total_trans_time = self.total_trans_time.mul_f64(time_scale);
+        stats.total_jitter = self.total_jitter.mul_f64(time_scale);
+        stats.total_jitter_sq = self.total_jitter_sq * time_scale * time_scale;
+        stats.max_jitter = self.max_jitter.mul_f64(time_scale);
+        stats.max_sample_period = self.max_sample_period.mul_f64(time_scale);
+        stats.step_settling = self.step_settling.map(|t| t.mul_f64(time_scale));
+        stats.total_at_jitter = self.total_at_jitter.mul_f64(time_scale);
+        stats.max_at_jitter = self.max_at_jitter.mul_f64(time_scale);
+        stats.total_latency = self.total_latency.mul_f64(time_scale);
+        stats.total_roundtrip = self.total_roundtrip.mul_f64(time_scale);
+        stats.max_roundtrip = self.max_roundtrip.mul_f64(time_scale);
+        stats.latency_histogram.scale = self.latency_histogram.scale * time_scale;
+        stats.total_sample_period = self.total_sample_period.mul_f64(time_scale);
+        stats.time_in_mode.normal = self.time_in_mode.normal.mul_f64(time_scale);
+        stats.time_in_mode.degraded = self.time_in_mode.degraded.mul_f64(time_scale);
+        stats.time_in_mode.emergency_stop = self.time_in_mode.emergency_stop.mul_f64(time_scale);
+        stats.max_feedback_gap = self.max_feedback_gap.map(|d| d.mul_f64(time_scale));
+        stats.total_lock_wait = self.total_lock_wait.mul_f64(time_scale);
+        stats.max_lock_wait = self.max_lock_wait.mul_f64(time_scale);
+        stats.e2e_deadline = self.e2e_deadline.mul_f64(time_scale);
+        stats.active_duration = self.active_duration.mul_f64(time_scale);
+        stats.stage_times = self.stage_times.map(|s| s.mul_f64(time_scale));
+        stats.worst_cycle = self.worst_cycle.map(|c| c.mul_f64(time_scale));
+        stats
+    }
+
     pub fn merge(&mut self, other: &BenchmarkStats) {
         self.sensor_count += other.sensor_count;
         self.actuator_count += other.actuator_count;
         self.sensor_missed_deadlines += other.sensor_missed_deadlines;
         self.actuator_missed_deadlines += other.actuator_missed_deadlines;
+        self.feedback_drops += other.feedback_drops;
+        self.actuator_saturations += other.actuator_saturations;
+        self.e2e_deadline_misses += other.e2e_deadline_misses;
+        self.commander_overruns += other.commander_overruns;
+        self.suppressed_commands += other.suppressed_commands;
+        self.invalid_samples += other.invalid_samples;
+        self.dropped_logs += other.dropped_logs;
+        self.backpressure_slowdowns += other.backpressure_slowdowns;
+        self.max_sample_period = self.max_sample_period.max(other.max_sample_period);
+        self.e2e_deadline = self.e2e_deadline.max(other.e2e_deadline);
+        self.active_duration += other.active_duration;
+        self.drift_corrections += other.drift_corrections;
+        self.total_sample_period += other.total_sample_period;
+        self.time_in_mode.normal += other.time_in_mode.normal;
+        self.time_in_mode.degraded += other.time_in_mode.degraded;
+        self.time_in_mode.emergency_stop += other.time_in_mode.emergency_stop;
         self.total_gen_time += other.total_gen_time;
         self.total_proc_time += other.total_proc_time;
+        self.cpu_proc_time += other.cpu_proc_time;
         self.total_trans_time += other.total_trans_time;
         self.total_jitter += other.total_jitter;
+        self.total_jitter_sq += other.total_jitter_sq;
+        self.anomaly_count += other.anomaly_count;
+        self.latency_histogram.merge(&other.latency_histogram);
         self.total_at_jitter += other.total_at_jitter;
         self.max_jitter = self.max_jitter.max(other.max_jitter);
         self.max_at_jitter = self.max_at_jitter.max(other.max_at_jitter);
         self.total_actuator_time += other.total_actuator_time;
         self.total_latency += other.total_latency;
+        self.total_roundtrip += other.total_roundtrip;
+        self.roundtrip_count += other.roundtrip_count;
+        self.max_roundtrip = self.max_roundtrip.max(other.max_roundtrip);
+        self.stability_warning = self.stability_warning.union(&other.stability_warning);
+        self.transmission_misses.force += other.transmission_misses.force;
+        self.transmission_misses.position += other.transmission_misses.position;
+        self.transmission_misses.temperature += other.transmission_misses.temperature;
+        self.max_feedback_gap.force = self.max_feedback_gap.force.max(other.max_feedback_gap.force);
+        self.max_feedback_gap.position = self.max_feedback_gap.position.max(other.max_feedback_gap.position);
+        self.max_feedback_gap.temperature = self.max_feedback_gap.temperature.max(other.max_feedback_gap.temperature);
+        self.feedback_starved = self.feedback_starved.union(&other.feedback_starved);
+        self.faulted_actuators = self.faulted_actuators.union(&other.faulted_actuators);
+        self.total_lock_wait += other.total_lock_wait;
+        self.rt_threads += other.rt_threads;
+        self.stage_times.force.merge(&other.stage_times.force);
+        self.stage_times.position.merge(&other.stage_times.position);
+        self.stage_times.temperature.merge(&other.stage_times.temperature);
+        self.max_lock_wait = self.max_lock_wait.max(other.max_lock_wait);
+        if let Some(cycle) = other.worst_cycle {
+            self.record_cycle(cycle);
+        }
+        if let Some((min, max)) = other.value_range {
+            self.record_value(min);
+            self.record_value(max);
+        }
+        self.step_settling = self.step_settling.max(other.step_settling);
+        if let Some(overshoot) = other.step_overshoot {
+            self.step_overshoot = Some(self.step_overshoot.map_or(overshoot, |o| o.max(overshoot)));
+        }
+    }
+
+    // Keep the worst settling time and overshoot seen so far
+    pub fn record_step(&mut self, response: &StepResponse) {
+        self.step_settling = self.step_settling.max(response.settling_time);
+        self.step_overshoot = Some(self.step_overshoot.map_or(response.overshoot, |o| o.max(response.overshoot)));
+    }
+
+    // Widen `value_range` to cover `value`
+    pub fn record_value(&mut self, value: f64) {
+        if !value.is_finite() { return; }
+        self.value_range = Some(match self.value_range {
+            Some((min, max)) => (min.min(value), max.max(value)),
+            None => (value, value),
+        });
+    }
+
+    // Keep `cycle` if it is the slowest so far
+    pub fn record_cycle(&mut self, cycle: CycleTimeline) {
+        if self.worst_cycle.is_none_or(|worst| cycle.total > worst.total) {
+            self.worst_cycle = Some(cycle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone_is_unchanged_after_the_original_is_reset() {
+        let mut stats = BenchmarkStats::new();
+        stats.sensor_count = 10;
+        stats.total_latency += Duration::from_millis(3);
+        stats.latency_histogram.record(Duration::from_millis(3));
+        stats.e2e_deadline = Duration::from_millis(5);
+        let snapshot = stats;
+
+        stats.reset();
+        assert_eq!(stats, BenchmarkStats { e2e_deadline: Duration::from_millis(5), ..BenchmarkStats::default() });
+        assert_eq!(snapshot.sensor_count, 10);
+        assert_eq!(snapshot.avg_latency(), Duration::from_micros(300));
+        assert_ne!(snapshot, stats);
+    }
+
+    const EPS: f64 = 1e-9;
+
+    #[test]
+    fn pid_step_response_matches_hand_computed_values() {
+        // kp 2, ki 1, kd 0.5, unit step at dt 0.1: the derivative kicks in on the
+        // first step only, then the integral adds 0.1 per step
+        let mut pid = PidController::new(2.0, 1.0, 0.5);
+        let expected = [7.1, 2.2, 2.3, 2.4, 2.5];
+        for (step, want) in expected.iter().enumerate() {
+            let got = pid.compute(1.0, 0.0, 0.1, 1.0);
+            assert!((got - want).abs() < EPS, "step {}: got {}, want {}", step, got, want);
+        }
+        assert!((pid.integral - 0.5).abs() < EPS);
+        assert!((pid.prev_error - 1.0).abs() < EPS);
+    }
+
+    #[test]
+    fn pid_integral_grows_monotonically_under_constant_positive_error() {
+        let mut pid = PidController::new(1.0, 0.5, 0.1);
+        let mut last = pid.compute_detailed(10.0, 7.0, 0.01, 1.0).i;
+        for _ in 0..100 {
+            let terms = pid.compute_detailed(10.0, 7.0, 0.01, 1.0);
+            assert!(terms.i > last, "integral term fell from {} to {}", last, terms.i);
+            last = terms.i;
+        }
+    }
+
+    #[test]
+    fn pid_derivative_is_zero_at_steady_state() {
+        let mut pid = PidController::new(1.0, 0.5, 2.0);
+        pid.compute(5.0, 5.0, 0.01, 1.0);
+        for _ in 0..10 {
+            let terms = pid.compute_detailed(5.0, 5.0, 0.01, 1.0);
+            assert_eq!(terms.d, 0.0);
+            assert_eq!(terms.p, 0.0);
+        }
+
+        // Also once a constant error has been seen twice
+        pid.compute(6.0, 5.0, 0.01, 1.0);
+        assert_eq!(pid.compute_detailed(6.0, 5.0, 0.01, 1.0).d, 0.0);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gz_exports_decompress_to_what_was_written() {
+        use std::io::Read;
+        let path = std::env::temp_dir().join(format!("rts_export_{}.csv.gz", std::process::id()));
+        let mut trace = PidTrace::create(&path).unwrap();
+        let terms = PidTerms { p: 1.0, i: 0.5, d: 0.25, output: 1.75 };
+        trace.record(SensorType::Force, 30.0, 28.0, &terms);
+        drop(trace); // Finishes the gzip stream
+
+        let mut csv = String::new();
+        flate2::read::GzDecoder::new(File::open(&path).unwrap()).read_to_string(&mut csv).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "t_us,sensor_type,setpoint,measured,p,i,d,output");
+        assert!(lines[1].ends_with(",Force,30,28,1,0.5,0.25,1.75"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn f32_pid_matches_the_f64_step_response() {
+        let mut pid: PidController<f32> = PidController::new(2.0, 1.0, 0.5);
+        let expected = [7.1f32, 2.2, 2.3, 2.4, 2.5];
+        for (step, want) in expected.iter().enumerate() {
+            let got = pid.compute(1.0, 0.0, 0.1, 1.0);
+            assert!((got - want).abs() < 1e-5, "step {}: got {}, want {}", step, got, want);
+        }
+        assert!(matches!(pid.check(f32::NAN), Err(PidError::NonFinite(_))));
+    }
+
+    #[test]
+    fn flushed_log_lines_are_in_the_file() {
+        let path = std::env::temp_dir().join(format!("rts_system_{}.log", std::process::id()));
+        let mut log = SystemLog { file: Some(File::create(&path).unwrap()), ..SystemLog::in_memory() };
+        log.write_level(LogLevel::Warn, "last words before exit".to_string());
+        log.flush().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(contents.ends_with("[Warn] last words before exit\n"), "{:?}", contents);
+    }
+
+    #[test]
+    fn duration_totals_accumulate_past_duration_max_without_panicking() {
+        let mut stats = BenchmarkStats::new();
+        for _ in 0..1_000_000 {
+            stats.total_latency += Duration::from_millis(1);
+        }
+        assert_eq!(stats.total_latency.as_duration(), Duration::from_secs(1000));
+
+        // A plain Duration sum would panic on the second addition
+        for _ in 0..4 {
+            stats.total_jitter += Duration::MAX;
+        }
+        assert_eq!(stats.total_jitter.as_nanos(), Duration::MAX.as_nanos() * 4);
+        assert_eq!(stats.total_jitter.as_duration(), Duration::MAX);
+        assert_eq!(stats.total_jitter / 4, Duration::MAX);
+    }
+
+    #[test]
+    fn non_finite_inputs_leave_the_pid_state_untouched() {
+        let mut pid = PidController::new(2.0, 1.0, 0.5);
+        pid.compute(1.0, 0.0, 0.1, 1.0);
+        let (integral, prev_error) = (pid.integral, pid.prev_error);
+
+        for (target, current, dt) in [(f64::NAN, 0.0, 0.1), (1.0, f64::INFINITY, 0.1), (1.0, 0.0, f64::NAN), (1.0, 0.0, 0.0)] {
+            assert_eq!(pid.compute(target, current, dt, 1.0), 0.0);
+        }
+        assert_eq!((pid.integral, pid.prev_error), (integral, prev_error));
+
+        // The next good sample continues the step response as if nothing happened
+        assert!((pid.compute(1.0, 0.0, 0.1, 1.0) - 2.2).abs() < EPS);
+    }
+
+    #[test]
+    fn setpoint_schedule_follows_its_breakpoints() {
+        let ms = Duration::from_millis;
+        let step = SetpointSchedule::new(vec![(ms(1000), 45.0), (ms(0), 30.0)], Interpolation::Step);
+        for (t, want) in [(0, 30.0), (999, 30.0), (1000, 45.0), (5000, 45.0)] {
+            assert_eq!(step.at(ms(t)), want, "step at {}ms", t);
+        }
+
+        let ramp = SetpointSchedule::new(vec![(ms(1000), 30.0), (ms(2000), 40.0)], Interpolation::Linear);
+        for (t, want) in [(0, 30.0), (1000, 30.0), (1250, 32.5), (1500, 35.0), (2000, 40.0), (3000, 40.0)] {
+            assert!((ramp.at(ms(t)) - want).abs() < EPS, "ramp at {}ms: {}", t, ramp.at(ms(t)));
+        }
+    }
+
+    #[test]
+    fn all_sensor_types_lists_every_variant_once() {
+        // Exhaustive, so a new variant fails to compile here until it gets an index
+        let index = |s_type: SensorType| match s_type {
+            SensorType::Force => 0,
+            SensorType::Position => 1,
+            SensorType::Temperature => 2,
+        };
+        let mut seen = [0; 3];
+        for &s_type in SensorType::all() {
+            seen[index(s_type)] += 1;
+        }
+        assert_eq!(seen, [1, 1, 1]);
+    }
+
+    #[test]
+    fn windowed_throughput_rises_with_a_burst_and_decays() {
+        let mut window = ThroughputWindow::new();
+        let start = window.bucket_start;
+        for _ in 0..50 {
+            window.record(start);
+        }
+        assert!((window.per_second() - 50.0).abs() < EPS);
+
+        window.advance(start + Duration::from_millis(550)); // Burst still inside the last second
+        assert!((window.per_second() - 50.0).abs() < EPS);
+        window.record(start + Duration::from_millis(550));
+        window.advance(start + Duration::from_millis(1050)); // Burst bucket rotated out
+        assert!((window.per_second() - 1.0).abs() < EPS);
+        window.advance(start + Duration::from_secs(5));
+        assert_eq!(window.per_second(), 0.0);
+    }
+
+    #[test]
+    fn unstable_gains_trip_the_divergence_detector() {
+        // The plant moves by the full effort each step, so with kp = 3 the error
+        // flips sign and doubles every step
+        let run = |kp: f64| {
+            let mut pid = PidController::new(kp, 0.0, 0.0).with_divergence_limit(1e3);
+            let mut current = 0.0;
+            for step in 0..100 {
+                match pid.compute_checked(1.0, current, 0.01, 1.0) {
+                    Ok(effort) => current += effort,
+                    Err(e) => return Some((step, e)),
+                }
+            }
+            None
+        };
+        assert_eq!(run(0.5), None);
+        let (step, error) = run(3.0).expect("never diverged");
+        assert!(step < 15, "took {} steps", step);
+        assert!(matches!(error, PidError::Diverged { limit, .. } if limit == 1e3), "{:?}", error);
+    }
+
+    #[test]
+    fn step_response_reports_overshoot_settling_and_error() {
+        let ms = Duration::from_millis;
+        let mut tracker = StepTracker::new(SensorType::Force, ms(100), 0.0, 10.0);
+        let readings = [5.0, 9.0, 12.0, 11.0, 10.1, 9.9].into_iter().chain(std::iter::repeat_n(10.0, 30));
+        for (i, value) in readings.enumerate() {
+            tracker.record(ms(100 + i as u64), value);
+        }
+
+        let response = tracker.finish();
+        assert!((response.overshoot - 20.0).abs() < EPS, "{}", response.overshoot); // Peak of 12 on a step of 10
+        assert_eq!(response.settling_time, Some(ms(4))); // Within ±0.2 from the reading of 10.1 on
+        assert!(response.steady_state_error.abs() < EPS);
+    }
+
+    #[tokio::test]
+    async fn contended_log_drops_routine_lines_but_not_critical_ones() {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(Verbosity::Silent);
+        let log = tokio::sync::Mutex::new(log);
+        let mut stats = BenchmarkStats::new();
+
+        let busy = log.lock().await;
+        for id in 0..3 {
+            stats.try_log(&log, LogLevel::Warn, format!("routine {}", id));
+        }
+        // A critical line waits for the lock instead, so it is written once the holder lets go
+        let critical = async { log.lock().await.alert("E-STOP".to_string()) };
+        let release = async { tokio::task::yield_now().await; drop(busy); };
+        tokio::join!(critical, release);
+        stats.try_log(&log, LogLevel::Warn, "uncontended".to_string());
+
+        assert_eq!(stats.dropped_logs, 3);
+        let entries = log.lock().await.recent_entries();
+        assert!(entries.iter().any(|l| l.contains("E-STOP")));
+        assert!(entries.iter().any(|l| l.contains("uncontended")));
+        assert!(!entries.iter().any(|l| l.contains("routine")));
+    }
+
+    #[test]
+    fn health_score_separates_a_clean_run_from_a_faulty_one() {
+        let clean = BenchmarkStats {
+            sensor_count: 1000,
+            actuator_count: 1000,
+            e2e_deadline: Duration::from_millis(5),
+            time_in_mode: ModeTimes { normal: Duration::from_secs(1), ..ModeTimes::default() },
+            ..BenchmarkStats::new()
+        };
+        assert!((clean.health_score() - 100.0).abs() < EPS, "{}", clean.health_score());
+
+        let faulty = BenchmarkStats {
+            sensor_missed_deadlines: 300,
+            actuator_count: 600, // 40% never reached an actuator
+            time_in_mode: ModeTimes { normal: Duration::from_millis(500), degraded: Duration::from_millis(500), ..ModeTimes::default() },
+            ..clean
+        };
+        assert!(faulty.health_score() < 75.0, "{}", faulty.health_score());
+    }
+
+    #[test]
+    fn value_range_tracks_the_extremes_across_merges() {
+        let mut first = BenchmarkStats::new();
+        assert_eq!(first.value_range, None);
+        for value in [3.0, -1.5, f64::NAN, 7.25, 2.0] {
+            first.record_value(value);
+        }
+        assert_eq!(first.value_range, Some((-1.5, 7.25)));
+
+        let mut second = BenchmarkStats::new();
+        second.record_value(9.0);
+        first.merge(&second);
+        assert_eq!(first.value_range, Some((-1.5, 9.0)));
+    }
+
+    #[test]
+    fn sorted_entries_follow_event_time_not_write_order() {
+        let mut log = SystemLog::in_memory();
+        log.set_verbosity(Verbosity::Silent);
+        let now = Instant::now();
+        log.write_level_at(now, LogLevel::Info, "third".to_string());
+        log.write_level_at(now - Duration::from_millis(20), LogLevel::Info, "first".to_string());
+        log.write_level_at(now, LogLevel::Warn, "fourth".to_string()); // Ties keep their write order
+        log.write_level_at(now - Duration::from_millis(10), LogLevel::Info, "second".to_string());
+
+        let order: Vec<&str> = log.sorted_entries().iter().map(|e| e.line.rsplit(' ').next().unwrap()).collect();
+        assert_eq!(order, ["first", "second", "third", "fourth"]);
+        assert!(log.recent_entries()[0].ends_with("third")); // Unsorted view is unchanged
+    }
+
+    #[test]
+    fn validate_rejects_each_kind_of_corrupt_sample() {
+        let now = Instant::now();
+        let valid = SensorData {
+            id: 1,
+            sensor_type: SensorType::Force,
+            value: 30.0,
+            anomaly: false,
+            timestamp: now,
+            processed_timestamp: None,
+            capture_time: None,
+            stamps: Default::default(),
+        };
+        assert_eq!(valid.validate(now), Ok(()));
+
+        assert!(matches!(SensorData { value: f64::NAN, ..valid.clone() }.validate(now), Err(DataError::NonFinite(v)) if v.is_nan()));
+        assert_eq!(SensorData { value: f64::INFINITY, ..valid.clone() }.validate(now), Err(DataError::NonFinite(f64::INFINITY)));
+        assert_eq!(SensorData { id: -3, ..valid.clone() }.validate(now), Err(DataError::NegativeId(-3)));
+        let ahead = Duration::from_millis(5);
+        assert_eq!(SensorData { capture_time: Some(now + ahead), ..valid.clone() }.validate(now), Err(DataError::FromTheFuture(ahead)));
+        assert_eq!(SensorData { timestamp: now + ahead, ..valid }.validate(now), Err(DataError::FromTheFuture(ahead)));
+    }
+
+    #[test]
+    fn first_order_response_settles_when_the_band_is_reached() {
+        // y = 10 (1 - e^(-t/10ms)) sampled every 1ms; within ±b of the step from t = -10ms * ln(b)
+        let response = |band| {
+            let mut tracker = StepTracker::new(SensorType::Force, Duration::ZERO, 0.0, 10.0).with_band(band);
+            for ms in 0..200u64 {
+                tracker.record(Duration::from_millis(ms), 10.0 * (1.0 - (-(ms as f64) / 10.0).exp()));
+            }
+            tracker.finish()
+        };
+        let (loose, tight) = (response(0.05), response(DEFAULT_SETTLING_BAND));
+        assert_eq!(loose.settling_time, Some(Duration::from_millis(30))); // 29.96ms
+        assert_eq!(tight.settling_time, Some(Duration::from_millis(40))); // 39.12ms
+        assert!(tight.overshoot.abs() < EPS); // A first-order system never overshoots
+
+        let mut stats = BenchmarkStats::new();
+        stats.record_step(&loose);
+        stats.record_step(&tight);
+        assert_eq!(stats.step_settling, Some(Duration::from_millis(40)));
+        assert!(stats.step_overshoot.is_some_and(|o| o.abs() < EPS));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn step_disturbance_error_decays_under_pid_control() {
+        // Setpoint 1 on a 20ms plant; the disturbance of -2 at 150ms pulls it away
+        let ms = Duration::from_millis;
+        let plant = Plant::new(PlantModel { time_constant: ms(20), ..PlantModel::default() }.with_disturbance(ms(150), -2.0));
+        let mut pid = PidController::new(1.0, 50.0, 0.0);
+        let (mut before, mut peak, mut after) = (0.0f64, 0.0f64, 0.0f64);
+        let start = Instant::now();
+        let mut last = start;
+        while start.elapsed() < ms(450) {
+            thread::sleep(ms(2));
+            let now = Instant::now();
+            let value = plant.read();
+            let error = (1.0 - value).abs();
+            plant.apply(pid.compute(1.0, value, (now - last).as_secs_f64(), 1.0));
+            last = now;
+            match now - start {
+                t if t >= ms(120) && t < ms(150) => before = before.max(error),
+                t if t >= ms(150) && t < ms(250) => peak = peak.max(error),
+                t if t >= ms(400) => after = after.max(error),
+                _ => {}
+            }
+        }
+        assert!(peak > 0.2, "disturbance barely showed: {:.3}", peak);
+        assert!(before < peak / 4.0, "not settled before the step: {:.3} vs peak {:.3}", before, peak);
+        assert!(after < peak / 4.0, "error did not decay: {:.3} vs peak {:.3}", after, peak);
+    }
+}
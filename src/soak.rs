@@ -0,0 +1,122 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::share::{DeadLetterLog, LogLevel, ShutdownReason, SnapshotHandle, SystemLog, SystemMode};
+
+// One periodic sanity check of a soak run, see `SimulationConfig::soak_check_interval`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoakCheck {
+    pub at: Duration, // Wall-clock time since start-up
+    pub mode: SystemMode,
+    pub samples_processed: u64,
+    pub log_entries: usize,   // Retained by the log's ring buffer
+    pub dead_letters: u64,    // Dropped so far, for any reason
+    pub retained_dead_letters: usize,
+    pub violation: Option<String>, // The first broken invariant; the run is stopped
+}
+
+pub type SoakCallback = Box<dyn Fn(&SoakCheck) + Send>;
+
+// Callbacks fired on every soak check, from the soak thread
+#[derive(Clone, Default)]
+pub struct SoakHooks {
+    callbacks: Arc<Mutex<Vec<SoakCallback>>>,
+}
+
+impl SoakHooks {
+    pub fn register(&self, callback: SoakCallback) {
+        if let Ok(mut callbacks) = self.callbacks.lock() {
+            callbacks.push(callback);
+        }
+    }
+
+    fn notify(&self, check: &SoakCheck) {
+        if let Ok(callbacks) = self.callbacks.lock() {
+            for callback in callbacks.iter() {
+                callback(check);
+            }
+        }
+    }
+}
+
+// What a soak thread watches
+pub(crate) struct SoakTargets {
+    pub start_time: Instant,
+    pub interval: Duration,
+    pub log: Arc<Mutex<SystemLog>>,
+    pub snapshot: SnapshotHandle,
+    pub dead_letters: DeadLetterLog,
+    pub hooks: SoakHooks,
+}
+
+// Checks the running simulation every `interval` until it stops. A broken
+// invariant is logged as Critical and stops the run. Returns the checks made.
+pub(crate) fn run(targets: SoakTargets) -> usize {
+    let mut previous: Option<SoakCheck> = None;
+    let mut checks = 0;
+    let mut due = targets.start_time + targets.interval;
+
+    loop {
+        // Sleep in short steps so a stopped simulation is noticed quickly
+        loop {
+            let active = targets.log.lock().map(|log| log.active).unwrap_or(false);
+            if !active { return checks; }
+            let now = Instant::now();
+            if now >= due { break; }
+            thread::sleep((due - now).min(Duration::from_millis(50)));
+        }
+        due += targets.interval;
+
+        let Ok(mut log) = targets.log.lock() else { return checks; };
+        let snapshot = targets.snapshot.snapshot();
+        let mut check = SoakCheck {
+            at: targets.start_time.elapsed(),
+            mode: snapshot.mode,
+            samples_processed: snapshot.samples_processed,
+            log_entries: log.retained_entries(),
+            dead_letters: targets.dead_letters.total(),
+            retained_dead_letters: targets.dead_letters.recent().len(),
+            violation: None,
+        };
+        check.violation = violation(&check, previous.as_ref(), &log, &targets.dead_letters);
+        checks += 1;
+
+        if let Some(violation) = &check.violation {
+            log.write_level(LogLevel::Critical, format!("[Soak] Check {} failed at {:?}: {}", checks, check.at, violation));
+            log.request_shutdown(ShutdownReason::InvariantViolation(format!("soak: {}", violation)));
+        } else {
+            log.write(format!("[Soak] Check {} passed: {} samples, {} log entries retained, {} dead letters",
+                checks, check.samples_processed, check.log_entries, check.dead_letters));
+        }
+        // Callbacks are free to take the log themselves
+        drop(log);
+        targets.hooks.notify(&check);
+
+        if check.violation.is_some() { return checks; }
+        previous = Some(check);
+    }
+}
+
+fn violation(check: &SoakCheck, previous: Option<&SoakCheck>, log: &SystemLog, dead_letters: &DeadLetterLog) -> Option<String> {
+    if check.log_entries > log.retention() {
+        return Some(format!("log retains {} entries, more than its {} ring buffer", check.log_entries, log.retention()));
+    }
+    if check.retained_dead_letters > dead_letters.capacity() {
+        return Some(format!("{} dead letters retained, capacity {}", check.retained_dead_letters, dead_letters.capacity()));
+    }
+    // E-STOP always stops the run, so one that is still going is stuck
+    if check.mode == SystemMode::EmergencyStop && log.shutdown_reason().is_none() {
+        return Some("in EmergencyStop without a shutdown".to_string());
+    }
+
+    let previous = previous?;
+    // Counters only grow; one that went down has wrapped
+    if check.samples_processed < previous.samples_processed || check.dead_letters < previous.dead_letters {
+        return Some(format!("counter went backwards: samples {} -> {}, dead letters {} -> {}",
+            previous.samples_processed, check.samples_processed, previous.dead_letters, check.dead_letters));
+    }
+    if check.samples_processed == previous.samples_processed {
+        return Some(format!("no samples processed since {:?}", previous.at));
+    }
+    None
+}